@@ -0,0 +1,257 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, Result};
+use keycode::{KeyMap, KeyMappingId};
+use relm4::gtk::gdk::{Key, ModifierType};
+
+use crate::sketch_board::KeyEventMsg;
+
+/// A key combination like `ctrl+shift+z`, as written in the `[keybinds]` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Accelerator {
+    key: Key,
+    modifier: ModifierType,
+}
+
+impl Accelerator {
+    const MODIFIERS: ModifierType = ModifierType::CONTROL_MASK
+        .union(ModifierType::SHIFT_MASK)
+        .union(ModifierType::ALT_MASK)
+        .union(ModifierType::SUPER_MASK);
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut modifier = ModifierType::empty();
+        let mut parts = text.split('+').map(str::trim).peekable();
+
+        while let Some(part) = parts.next() {
+            if parts.peek().is_none() {
+                let key = Self::key_from_name(part)
+                    .ok_or_else(|| anyhow!("unknown key '{part}'"))?
+                    .to_lower();
+                return Ok(Self { key, modifier });
+            }
+
+            modifier |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => ModifierType::CONTROL_MASK,
+                "shift" => ModifierType::SHIFT_MASK,
+                "alt" => ModifierType::ALT_MASK,
+                "super" | "meta" => ModifierType::SUPER_MASK,
+                _ => return Err(anyhow!("unknown modifier '{part}'")),
+            };
+        }
+        Err(anyhow!("empty keybind"))
+    }
+
+    /// GTK key names are case sensitive, e.g. `Delete` or `KP_Add`.
+    fn key_from_name(name: &str) -> Option<Key> {
+        let capitalize = |name: &str| match name.get(..1) {
+            Some(first) => first.to_uppercase() + &name[1..],
+            None => String::new(),
+        };
+        let lowercase = name.to_lowercase();
+        let capitalized = match lowercase.strip_prefix("kp_") {
+            Some(rest) => format!("KP_{}", capitalize(rest)),
+            None => capitalize(&lowercase),
+        };
+
+        Key::from_name(name)
+            .or_else(|| Key::from_name(name.to_lowercase()))
+            .or_else(|| Key::from_name(capitalized))
+    }
+
+    /// The character of a key combination without modifier, e.g. `]` for `bracketright`.
+    pub fn character(&self) -> Option<char> {
+        self.key
+            .to_unicode()
+            .filter(|c| self.modifier.is_empty() && !c.is_control())
+    }
+
+    /// Plain characters go to the input method and arrive as typed text, not as key presses.
+    pub fn matches_text(&self, text: &str) -> bool {
+        let mut chars = text.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => self.character() == Some(c),
+            _ => false,
+        }
+    }
+
+    /// Position of the key on a US keyboard, shortcuts keep working with other layouts.
+    fn layout_independent_code(&self) -> Option<KeyMappingId> {
+        let code = match self.key.to_unicode()? {
+            'a' => KeyMappingId::UsA,
+            'b' => KeyMappingId::UsB,
+            'c' => KeyMappingId::UsC,
+            'd' => KeyMappingId::UsD,
+            'e' => KeyMappingId::UsE,
+            'f' => KeyMappingId::UsF,
+            'g' => KeyMappingId::UsG,
+            'h' => KeyMappingId::UsH,
+            'i' => KeyMappingId::UsI,
+            'j' => KeyMappingId::UsJ,
+            'k' => KeyMappingId::UsK,
+            'l' => KeyMappingId::UsL,
+            'm' => KeyMappingId::UsM,
+            'n' => KeyMappingId::UsN,
+            'o' => KeyMappingId::UsO,
+            'p' => KeyMappingId::UsP,
+            'q' => KeyMappingId::UsQ,
+            'r' => KeyMappingId::UsR,
+            's' => KeyMappingId::UsS,
+            't' => KeyMappingId::UsT,
+            'u' => KeyMappingId::UsU,
+            'v' => KeyMappingId::UsV,
+            'w' => KeyMappingId::UsW,
+            'x' => KeyMappingId::UsX,
+            'y' => KeyMappingId::UsY,
+            'z' => KeyMappingId::UsZ,
+            '0' => KeyMappingId::Digit0,
+            '1' => KeyMappingId::Digit1,
+            '2' => KeyMappingId::Digit2,
+            '3' => KeyMappingId::Digit3,
+            '4' => KeyMappingId::Digit4,
+            '5' => KeyMappingId::Digit5,
+            '6' => KeyMappingId::Digit6,
+            '7' => KeyMappingId::Digit7,
+            '8' => KeyMappingId::Digit8,
+            '9' => KeyMappingId::Digit9,
+            '=' | '+' => KeyMappingId::Equal,
+            '-' => KeyMappingId::Minus,
+            _ => return None,
+        };
+        Some(code)
+    }
+
+    pub fn matches(&self, event: &KeyEventMsg) -> bool {
+        if event.modifier & Self::MODIFIERS != self.modifier {
+            return false;
+        }
+
+        // INFO: gtk4 reports x11 keycodes, which are the evdev keycodes shifted by 8
+        event.key.to_lower() == self.key
+            || self.layout_independent_code().is_some_and(|code| {
+                (event.code as u16).checked_sub(8) == Some(KeyMap::from(code).evdev)
+            })
+    }
+}
+
+impl Display for Accelerator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (modifier, name) in [
+            (ModifierType::CONTROL_MASK, "Ctrl"),
+            (ModifierType::SHIFT_MASK, "Shift"),
+            (ModifierType::ALT_MASK, "Alt"),
+            (ModifierType::SUPER_MASK, "Super"),
+        ] {
+            if self.modifier.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        match self.key.to_unicode() {
+            Some(c) if c.is_alphanumeric() => write!(f, "{}", c.to_uppercase()),
+            _ => match self.key.name() {
+                Some(name) => {
+                    let mut chars = name.chars();
+                    match chars.next() {
+                        Some(first) => write!(f, "{}{}", first.to_uppercase(), chars.as_str()),
+                        None => Ok(()),
+                    }
+                }
+                None => write!(f, "{:?}", self.key),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(key: Key, code: u32, modifier: ModifierType) -> KeyEventMsg {
+        KeyEventMsg::new(key, code, modifier)
+    }
+
+    #[test]
+    fn display_parses_back() {
+        for spec in [
+            "ctrl+z",
+            "ctrl+shift+z",
+            "ctrl+plus",
+            "ctrl+kp_add",
+            "alt+Delete",
+            "super+1",
+            "bracketright",
+        ] {
+            let accelerator = Accelerator::parse(spec).unwrap();
+            let displayed = accelerator.to_string();
+            assert_eq!(
+                Accelerator::parse(&displayed).unwrap(),
+                accelerator,
+                "{spec} is displayed as {displayed}"
+            );
+        }
+        assert_eq!(
+            Accelerator::parse("ctrl+shift+z").unwrap().to_string(),
+            "Ctrl+Shift+Z"
+        );
+    }
+
+    #[test]
+    fn parsing_ignores_case() {
+        assert_eq!(
+            Accelerator::parse("CTRL+Shift+Z").unwrap(),
+            Accelerator::parse("ctrl+shift+z").unwrap()
+        );
+        assert_eq!(
+            Accelerator::parse("ctrl+KP_ADD").unwrap(),
+            Accelerator::parse("ctrl+kp_add").unwrap()
+        );
+    }
+
+    #[test]
+    fn bad_specs_are_rejected() {
+        for spec in ["", "ctrl+", "hyper+z", "ctrl+nosuchkey", "z+ctrl"] {
+            assert!(Accelerator::parse(spec).is_err(), "{spec} was accepted");
+        }
+    }
+
+    #[test]
+    fn modifiers_have_to_match_exactly() {
+        let ctrl_z = Accelerator::parse("ctrl+z").unwrap();
+        assert!(ctrl_z.matches(&event(Key::z, 0, ModifierType::CONTROL_MASK)));
+        assert!(!ctrl_z.matches(&event(Key::z, 0, ModifierType::empty())));
+        assert!(!ctrl_z.matches(&event(
+            Key::z,
+            0,
+            ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK
+        )));
+        // caps lock and mouse buttons are no modifiers of a key combination
+        assert!(ctrl_z.matches(&event(
+            Key::Z,
+            0,
+            ModifierType::CONTROL_MASK | ModifierType::LOCK_MASK | ModifierType::BUTTON1_MASK
+        )));
+    }
+
+    #[test]
+    fn letters_match_the_us_layout_position() {
+        let ctrl_z = Accelerator::parse("ctrl+z").unwrap();
+        let cyrillic = Key::from_name("Cyrillic_ya").unwrap();
+        // evdev KEY_Z is 44, gtk reports it shifted by 8
+        assert!(ctrl_z.matches(&event(cyrillic, 52, ModifierType::CONTROL_MASK)));
+        assert!(!ctrl_z.matches(&event(cyrillic, 53, ModifierType::CONTROL_MASK)));
+    }
+
+    #[test]
+    fn plain_characters_match_typed_text() {
+        let size_up = Accelerator::parse("bracketright").unwrap();
+        assert_eq!(size_up.character(), Some(']'));
+        assert!(size_up.matches_text("]"));
+        assert!(!size_up.matches_text("]]"));
+        assert!(!size_up.matches_text("["));
+
+        let ctrl_z = Accelerator::parse("ctrl+z").unwrap();
+        assert_eq!(ctrl_z.character(), None);
+        assert!(!ctrl_z.matches_text("z"));
+    }
+}
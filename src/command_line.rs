@@ -7,7 +7,8 @@ pub struct CommandLine {
     #[arg(short, long)]
     pub config: Option<String>,
 
-    /// Path to input image or '-' to read from stdin
+    /// Path to input image or '-' to read from stdin.
+    /// Files ending in `.satty` are opened as project with editable annotations
     #[arg(short, long)]
     pub filename: String,
 
@@ -42,6 +43,10 @@ pub struct CommandLine {
     #[arg(long)]
     pub annotation_size_factor: Option<f32>,
 
+    /// Stroke widths to step through with the mouse wheel or `[` and `]`, comma separated
+    #[arg(long, value_delimiter = ',')]
+    pub size_steps: Option<Vec<f32>>,
+
     /// After copying the screenshot, save it to a file as well
     /// Preferably use the `action_on_copy` option instead.
     #[arg(long)]
@@ -71,6 +76,14 @@ pub struct CommandLine {
     #[arg(long)]
     pub default_fill_shapes: bool,
 
+    /// Give annotations a drop shadow by default
+    #[arg(long)]
+    pub default_shadow: bool,
+
+    /// Give annotations a contrasting outline by default
+    #[arg(long)]
+    pub default_outline: bool,
+
     /// Font family to use for text annotations
     #[arg(long)]
     pub font_family: Option<String>,
@@ -83,6 +96,28 @@ pub struct CommandLine {
     #[arg(long)]
     pub primary_highlighter: Option<Highlighters>,
 
+    /// The primary blur mode to use, pixelate is accessible with CTRL and black box with SHIFT
+    #[arg(long)]
+    pub primary_blur: Option<BlurModes>,
+
+    /// How dark the spotlight tool makes everything outside of its areas, between 0 and 1
+    /// (Defaults to 0.6)
+    #[arg(long)]
+    pub spotlight_opacity: Option<f32>,
+
+    /// How the head of arrows without fill is drawn, hold CTRL for a second head and ALT to bend
+    /// the arrow
+    #[arg(long)]
+    pub arrow_head: Option<ArrowHead>,
+
+    /// Line style of new annotations, can be changed in the style toolbar
+    #[arg(long)]
+    pub default_line_dash: Option<LineDash>,
+
+    /// How the ends of lines and dashes are drawn, dots are always round
+    #[arg(long)]
+    pub line_caps: Option<LineCaps>,
+
     /// Disable notifications
     #[arg(long)]
     pub disable_notifications: bool,
@@ -104,6 +139,38 @@ pub struct CommandLine {
     #[arg(long)]
     pub brush_smooth_history_size: Option<usize>,
 
+    /// Image format to use when it can't be derived from the filename, i.e. for stdout and the
+    /// copy command, for example `jpeg`, `webp` or `svg`. The default is png.
+    #[arg(long)]
+    pub output_format: Option<String>,
+
+    /// Quality of JPEG output, from 0 to 100
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub jpeg_quality: Option<u8>,
+
+    /// Quality of WebP output, from 0 to 100. Requires the webp-pixbuf-loader
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+    pub webp_quality: Option<u8>,
+
+    /// Compression level of PNG output, from 0 to 9
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=9))]
+    pub png_compression: Option<u8>,
+
+    /// Add the annotations of a script file, one command per line, for example
+    /// `rect 10,10 200x100 color=#ff0000 size=large`. See the README for all commands
+    #[arg(long, value_name = "SCRIPT")]
+    pub apply: Option<String>,
+
+    /// Save the image to the output filename without opening a window, e.g. together with
+    /// `--apply`. Renders with an offscreen OpenGL context, which also works without GPU
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Listen on a unix socket for line delimited JSON commands, e.g. `{"command": "save"}`.
+    /// See the README for all commands
+    #[arg(long, value_name = "PATH")]
+    pub ipc_socket: Option<String>,
+
     // --- deprecated options ---
     /// Right click to copy.
     /// Preferably use the `action_on_right_click` option instead.
@@ -130,12 +197,17 @@ pub enum Tools {
     Blur,
     Highlight,
     Brush,
+    Polygon,
+    Callout,
+    Spotlight,
+    Magnifier,
 }
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum Action {
     SaveToClipboard,
     SaveToFile,
+    SaveProject,
     Exit,
 }
 
@@ -146,6 +218,37 @@ pub enum Highlighters {
     Freehand,
 }
 
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum ArrowHead {
+    #[default]
+    Open,
+    Outlined,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LineDash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum LineCaps {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum BlurModes {
+    #[default]
+    Gaussian,
+    Pixelate,
+    Blackbox,
+}
+
 impl std::fmt::Display for Tools {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         use Tools::*;
@@ -161,6 +264,10 @@ impl std::fmt::Display for Tools {
             Blur => "blur",
             Highlight => "highlight",
             Brush => "brush",
+            Polygon => "polygon",
+            Callout => "callout",
+            Spotlight => "spotlight",
+            Magnifier => "magnifier",
         };
         f.write_str(s)
     }
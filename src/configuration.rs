@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    fmt::Display,
     fs,
     io::{self, Write},
     path::Path,
@@ -13,9 +14,11 @@ use thiserror::Error;
 use xdg::{BaseDirectories, BaseDirectoriesError};
 
 use crate::{
+    accelerator::Accelerator,
     command_line::{Action as CommandLineAction, CommandLine},
-    style::Color,
-    tools::{Highlighters, Tools},
+    sketch_board::KeyEventMsg,
+    style::{Color, LineCaps, LineDash, SizePreset},
+    tools::{ArrowHead, BlurModes, Highlighters, Tools},
 };
 
 pub static APP_CONFIG: SharedState<Configuration> = SharedState::new();
@@ -41,6 +44,7 @@ pub struct Configuration {
     initial_tool: Tools,
     copy_command: Option<String>,
     annotation_size_factor: f32,
+    size_steps: Vec<f32>,
     save_after_copy: bool,
     actions_on_enter: Vec<Action>,
     actions_on_escape: Vec<Action>,
@@ -49,17 +53,86 @@ pub struct Configuration {
     default_hide_toolbars: bool,
     focus_toggles_toolbars: bool,
     default_fill_shapes: bool,
+    default_shadow: bool,
+    default_outline: bool,
     font: FontConfiguration,
     primary_highlighter: Highlighters,
+    primary_blur: BlurModes,
+    spotlight_opacity: f32,
+    arrow_head: ArrowHead,
+    default_line_dash: LineDash,
+    line_caps: LineCaps,
     disable_notifications: bool,
     profile_startup: bool,
     no_window_decoration: bool,
     brush_smooth_history_size: usize,
     keybinds: Keybinds,
+    output_format: String,
+    jpeg_quality: Option<u8>,
+    webp_quality: Option<u8>,
+    png_compression: Option<u8>,
+    apply_script: Option<String>,
+    headless: bool,
+    ipc_socket: Option<String>,
+}
+
+/// Everything besides tool selection that can be bound to a key combination.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Undo,
+    Redo,
+    Save,
+    SaveAs,
+    SaveProject,
+    Copy,
+    ToggleToolbars,
+    Reset,
+    ToggleFill,
+    ToggleShadow,
+    ToggleOutline,
+    Size(SizePreset),
+    SizeUp,
+    SizeDown,
+    ZoomIn,
+    ZoomOut,
+    ZoomFit,
+    ZoomOriginal,
+    // index into the color palette
+    Color(usize),
+}
+
+impl Display for KeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Undo => write!(f, "undo"),
+            Self::Redo => write!(f, "redo"),
+            Self::Save => write!(f, "save"),
+            Self::SaveAs => write!(f, "save-as"),
+            Self::SaveProject => write!(f, "save-project"),
+            Self::Copy => write!(f, "copy"),
+            Self::ToggleToolbars => write!(f, "toggle-toolbars"),
+            Self::Reset => write!(f, "reset"),
+            Self::ToggleFill => write!(f, "toggle-fill"),
+            Self::ToggleShadow => write!(f, "toggle-shadow"),
+            Self::ToggleOutline => write!(f, "toggle-outline"),
+            Self::Size(SizePreset::Small) => write!(f, "size-small"),
+            Self::Size(SizePreset::Medium) => write!(f, "size-medium"),
+            Self::Size(SizePreset::Large) => write!(f, "size-large"),
+            Self::SizeUp => write!(f, "size-up"),
+            Self::SizeDown => write!(f, "size-down"),
+            Self::ZoomIn => write!(f, "zoom-in"),
+            Self::ZoomOut => write!(f, "zoom-out"),
+            Self::ZoomFit => write!(f, "zoom-fit"),
+            Self::ZoomOriginal => write!(f, "zoom-original"),
+            Self::Color(index) => write!(f, "colors[{index}]"),
+        }
+    }
 }
 
 pub struct Keybinds {
     shortcuts: HashMap<char, Tools>,
+    // in order of priority, the first match wins
+    actions: Vec<(KeyAction, Vec<Accelerator>)>,
 }
 
 impl Keybinds {
@@ -71,14 +144,90 @@ impl Keybinds {
         &self.shortcuts
     }
 
+    pub fn get_action(&self, event: &KeyEventMsg) -> Option<KeyAction> {
+        self.actions
+            .iter()
+            .find(|(_, accelerators)| accelerators.iter().any(|a| a.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    /// Action of a plain character, which is typed through the input method and arrives as text.
+    pub fn get_text_action(&self, text: &str) -> Option<KeyAction> {
+        self.actions
+            .iter()
+            .find(|(_, accelerators)| accelerators.iter().any(|a| a.matches_text(text)))
+            .map(|(action, _)| *action)
+    }
+
+    /// The first key combination of an action, e.g. to show it in a tooltip.
+    pub fn accelerator(&self, action: KeyAction) -> Option<&Accelerator> {
+        self.actions
+            .iter()
+            .find(|(a, _)| *a == action)
+            .and_then(|(_, accelerators)| accelerators.first())
+    }
+
     /// Update a single keybind, only if it is valid
     fn update_keybind(&mut self, key: Option<String>, tool: Tools) {
         if let Some(key_str) = key {
             if let Some(validated_key) = Self::validate_keybind(&key_str, tool) {
                 self.shortcuts.retain(|_, v| *v != tool);
-                self.shortcuts.insert(validated_key, tool);
+                if let Some(other) = self.shortcuts.insert(validated_key, tool) {
+                    eprintln!("Warning: Keybind conflict: '{validated_key}' is used for {tool} and {other}, only {tool} will work.");
+                }
+            }
+        }
+    }
+
+    /// Replace the key combinations of an action, only if all of them are valid
+    fn update_action(&mut self, accelerators: Option<Accelerators>, action: KeyAction) {
+        let Some(accelerators) = accelerators else {
+            return;
+        };
+
+        let parsed: Result<Vec<_>, _> = accelerators
+            .into_vec()
+            .iter()
+            .map(|a| Accelerator::parse(a).map_err(|e| format!("'{action} = {a}': {e}")))
+            .collect();
+        match parsed {
+            Ok(parsed) => match self.actions.iter_mut().find(|(a, _)| *a == action) {
+                Some((_, existing)) => *existing = parsed,
+                None => self.actions.push((action, parsed)),
+            },
+            Err(e) => {
+                eprintln!("Warning: Invalid keybind: {e}. Using default keybind instead.")
+            }
+        }
+    }
+
+    /// Warns about key combinations that are used for more than one action
+    fn check_conflicts(&self) {
+        for conflict in self.conflicts() {
+            eprintln!("Warning: Keybind conflict: {conflict}");
+        }
+    }
+
+    fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        let mut used: HashMap<Accelerator, KeyAction> = HashMap::new();
+        for (action, accelerators) in &self.actions {
+            for accelerator in accelerators {
+                if let Some(other) = used.get(accelerator) {
+                    if other != action {
+                        conflicts.push(format!("'{accelerator}' is used for {other} and {action}, only {other} will work."));
+                    }
+                    continue;
+                }
+                used.insert(*accelerator, *action);
+                if let Some(tool) = accelerator.character().and_then(|c| self.get_tool(c)) {
+                    conflicts.push(format!(
+                        "'{accelerator}' is used for {tool} and {action}, only {tool} will work."
+                    ));
+                }
             }
         }
+        conflicts
     }
 
     /// A shortcut keybinding is only valid if it is one char
@@ -107,6 +256,39 @@ impl Keybinds {
         self.update_keybind(file_keybinds.marker, Tools::Marker);
         self.update_keybind(file_keybinds.blur, Tools::Blur);
         self.update_keybind(file_keybinds.highlight, Tools::Highlight);
+        self.update_keybind(file_keybinds.polygon, Tools::Polygon);
+        self.update_keybind(file_keybinds.callout, Tools::Callout);
+        self.update_keybind(file_keybinds.spotlight, Tools::Spotlight);
+        self.update_keybind(file_keybinds.magnifier, Tools::Magnifier);
+
+        self.update_action(file_keybinds.undo, KeyAction::Undo);
+        self.update_action(file_keybinds.redo, KeyAction::Redo);
+        self.update_action(file_keybinds.save, KeyAction::Save);
+        self.update_action(file_keybinds.save_as, KeyAction::SaveAs);
+        self.update_action(file_keybinds.save_project, KeyAction::SaveProject);
+        self.update_action(file_keybinds.copy, KeyAction::Copy);
+        self.update_action(file_keybinds.toggle_toolbars, KeyAction::ToggleToolbars);
+        self.update_action(file_keybinds.reset, KeyAction::Reset);
+        self.update_action(file_keybinds.toggle_fill, KeyAction::ToggleFill);
+        self.update_action(file_keybinds.toggle_shadow, KeyAction::ToggleShadow);
+        self.update_action(file_keybinds.toggle_outline, KeyAction::ToggleOutline);
+        self.update_action(file_keybinds.size_small, KeyAction::Size(SizePreset::Small));
+        self.update_action(
+            file_keybinds.size_medium,
+            KeyAction::Size(SizePreset::Medium),
+        );
+        self.update_action(file_keybinds.size_large, KeyAction::Size(SizePreset::Large));
+        self.update_action(file_keybinds.size_up, KeyAction::SizeUp);
+        self.update_action(file_keybinds.size_down, KeyAction::SizeDown);
+        self.update_action(file_keybinds.zoom_in, KeyAction::ZoomIn);
+        self.update_action(file_keybinds.zoom_out, KeyAction::ZoomOut);
+        self.update_action(file_keybinds.zoom_fit, KeyAction::ZoomFit);
+        self.update_action(file_keybinds.zoom_original, KeyAction::ZoomOriginal);
+        for (index, color) in file_keybinds.colors.into_iter().flatten().enumerate() {
+            self.update_action(Some(color), KeyAction::Color(index));
+        }
+
+        self.check_conflicts();
     }
 }
 
@@ -124,8 +306,65 @@ impl Default for Keybinds {
         shortcuts.insert('m', Tools::Marker);
         shortcuts.insert('u', Tools::Blur);
         shortcuts.insert('g', Tools::Highlight);
+        shortcuts.insert('o', Tools::Polygon);
+        shortcuts.insert('a', Tools::Callout);
+        shortcuts.insert('s', Tools::Spotlight);
+        shortcuts.insert('n', Tools::Magnifier);
+
+        let actions = [
+            (KeyAction::Undo, &["ctrl+z"][..]),
+            (KeyAction::Redo, &["ctrl+y", "ctrl+shift+z"]),
+            (KeyAction::Save, &["ctrl+s"]),
+            (KeyAction::SaveAs, &["ctrl+shift+s"]),
+            (KeyAction::SaveProject, &["ctrl+alt+s"]),
+            (KeyAction::Copy, &["ctrl+c"]),
+            (KeyAction::ToggleToolbars, &["ctrl+t"]),
+            (KeyAction::Reset, &[]),
+            (KeyAction::ToggleFill, &[]),
+            (KeyAction::ToggleShadow, &[]),
+            (KeyAction::ToggleOutline, &[]),
+            (KeyAction::Size(SizePreset::Small), &[]),
+            (KeyAction::Size(SizePreset::Medium), &[]),
+            (KeyAction::Size(SizePreset::Large), &[]),
+            (KeyAction::SizeUp, &["bracketright"]),
+            (KeyAction::SizeDown, &["bracketleft"]),
+            // ctrl + plus arrives with shift on most layouts
+            (
+                KeyAction::ZoomIn,
+                &["ctrl+plus", "ctrl+equal", "ctrl+kp_add", "ctrl+shift+plus"],
+            ),
+            (KeyAction::ZoomOut, &["ctrl+minus", "ctrl+kp_subtract"]),
+            (KeyAction::ZoomFit, &["ctrl+0"]),
+            (KeyAction::ZoomOriginal, &["ctrl+1"]),
+        ]
+        .into_iter()
+        .map(|(action, accelerators)| {
+            let accelerators = accelerators
+                .iter()
+                .map(|a| Accelerator::parse(a).expect("default keybinds are valid"))
+                .collect();
+            (action, accelerators)
+        })
+        .collect();
+
+        Self { shortcuts, actions }
+    }
+}
+
+/// One key combination, or a list of alternatives.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Accelerators {
+    One(String),
+    Many(Vec<String>),
+}
 
-        Self { shortcuts }
+impl Accelerators {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Self::One(a) => vec![a],
+            Self::Many(a) => a,
+        }
     }
 }
 
@@ -182,6 +421,7 @@ pub enum Action {
     SaveToClipboard,
     SaveToFile,
     SaveToFileAs,
+    SaveProject,
     Exit,
 }
 
@@ -190,6 +430,7 @@ impl From<CommandLineAction> for Action {
         match action {
             CommandLineAction::SaveToClipboard => Self::SaveToClipboard,
             CommandLineAction::SaveToFile => Self::SaveToFile,
+            CommandLineAction::SaveProject => Self::SaveProject,
             CommandLineAction::Exit => Self::Exit,
         }
     }
@@ -246,6 +487,9 @@ impl Configuration {
         if let Some(v) = general.annotation_size_factor {
             self.annotation_size_factor = v;
         }
+        if let Some(v) = general.size_steps {
+            self.set_size_steps(v);
+        }
         if let Some(v) = general.save_after_copy {
             self.save_after_copy = v;
         }
@@ -267,9 +511,30 @@ impl Configuration {
         if let Some(v) = general.default_fill_shapes {
             self.default_fill_shapes = v;
         }
+        if let Some(v) = general.default_shadow {
+            self.default_shadow = v;
+        }
+        if let Some(v) = general.default_outline {
+            self.default_outline = v;
+        }
         if let Some(v) = general.primary_highlighter {
             self.primary_highlighter = v;
         }
+        if let Some(v) = general.primary_blur {
+            self.primary_blur = v;
+        }
+        if let Some(v) = general.spotlight_opacity {
+            self.spotlight_opacity = v.clamp(0.0, 1.0);
+        }
+        if let Some(v) = general.arrow_head {
+            self.arrow_head = v;
+        }
+        if let Some(v) = general.default_line_dash {
+            self.default_line_dash = v;
+        }
+        if let Some(v) = general.line_caps {
+            self.line_caps = v;
+        }
         if let Some(v) = general.disable_notifications {
             self.disable_notifications = v;
         }
@@ -279,6 +544,18 @@ impl Configuration {
         if let Some(v) = general.brush_smooth_history_size {
             self.brush_smooth_history_size = v;
         }
+        if let Some(v) = general.output_format {
+            self.output_format = v;
+        }
+        if let Some(v) = general.jpeg_quality {
+            self.jpeg_quality = Some(v);
+        }
+        if let Some(v) = general.webp_quality {
+            self.webp_quality = Some(v);
+        }
+        if let Some(v) = general.png_compression {
+            self.png_compression = Some(v);
+        }
 
         // --- deprecated options ---
         if let Some(v) = general.right_click_copy {
@@ -334,6 +611,12 @@ impl Configuration {
         if command_line.default_fill_shapes {
             self.default_fill_shapes = command_line.default_fill_shapes;
         }
+        if command_line.default_shadow {
+            self.default_shadow = command_line.default_shadow;
+        }
+        if command_line.default_outline {
+            self.default_outline = command_line.default_outline;
+        }
         if let Some(v) = command_line.initial_tool {
             self.initial_tool = v.into();
         }
@@ -346,6 +629,9 @@ impl Configuration {
         if let Some(v) = command_line.annotation_size_factor {
             self.annotation_size_factor = v;
         }
+        if let Some(v) = command_line.size_steps {
+            self.set_size_steps(v);
+        }
         if command_line.save_after_copy {
             self.save_after_copy = command_line.save_after_copy;
         }
@@ -367,6 +653,21 @@ impl Configuration {
         if let Some(v) = command_line.primary_highlighter {
             self.primary_highlighter = v.into();
         }
+        if let Some(v) = command_line.primary_blur {
+            self.primary_blur = v.into();
+        }
+        if let Some(v) = command_line.spotlight_opacity {
+            self.spotlight_opacity = v.clamp(0.0, 1.0);
+        }
+        if let Some(v) = command_line.arrow_head {
+            self.arrow_head = v.into();
+        }
+        if let Some(v) = command_line.default_line_dash {
+            self.default_line_dash = v.into();
+        }
+        if let Some(v) = command_line.line_caps {
+            self.line_caps = v.into();
+        }
         if command_line.disable_notifications {
             self.disable_notifications = command_line.disable_notifications;
         }
@@ -379,6 +680,27 @@ impl Configuration {
         if let Some(v) = command_line.brush_smooth_history_size {
             self.brush_smooth_history_size = v;
         }
+        if let Some(v) = command_line.output_format {
+            self.output_format = v;
+        }
+        if let Some(v) = command_line.jpeg_quality {
+            self.jpeg_quality = Some(v);
+        }
+        if let Some(v) = command_line.webp_quality {
+            self.webp_quality = Some(v);
+        }
+        if let Some(v) = command_line.png_compression {
+            self.png_compression = Some(v);
+        }
+        if let Some(v) = command_line.apply {
+            self.apply_script = Some(v);
+        }
+        if command_line.headless {
+            self.headless = command_line.headless;
+        }
+        if let Some(v) = command_line.ipc_socket {
+            self.ipc_socket = Some(v);
+        }
 
         // --- deprecated options ---
         if command_line.right_click_copy
@@ -427,6 +749,20 @@ impl Configuration {
         self.annotation_size_factor
     }
 
+    /// Stroke widths the size steps through with the mouse wheel or `[` and `]`, ascending.
+    pub fn size_steps(&self) -> &[f32] {
+        &self.size_steps
+    }
+
+    fn set_size_steps(&mut self, mut steps: Vec<f32>) {
+        steps.retain(|w| w.is_finite() && *w > 0.0);
+        steps.sort_by(f32::total_cmp);
+        steps.dedup();
+        if !steps.is_empty() {
+            self.size_steps = steps;
+        }
+    }
+
     pub fn save_after_copy(&self) -> bool {
         self.save_after_copy
     }
@@ -459,10 +795,38 @@ impl Configuration {
         self.default_fill_shapes
     }
 
+    pub fn default_shadow(&self) -> bool {
+        self.default_shadow
+    }
+
+    pub fn default_outline(&self) -> bool {
+        self.default_outline
+    }
+
     pub fn primary_highlighter(&self) -> Highlighters {
         self.primary_highlighter
     }
 
+    pub fn primary_blur(&self) -> BlurModes {
+        self.primary_blur
+    }
+
+    pub fn spotlight_opacity(&self) -> f32 {
+        self.spotlight_opacity
+    }
+
+    pub fn arrow_head(&self) -> ArrowHead {
+        self.arrow_head
+    }
+
+    pub fn default_line_dash(&self) -> LineDash {
+        self.default_line_dash
+    }
+
+    pub fn line_caps(&self) -> LineCaps {
+        self.line_caps
+    }
+
     pub fn disable_notifications(&self) -> bool {
         self.disable_notifications
     }
@@ -486,6 +850,34 @@ impl Configuration {
     pub fn keybinds(&self) -> &Keybinds {
         &self.keybinds
     }
+
+    pub fn output_format(&self) -> &str {
+        &self.output_format
+    }
+
+    pub fn jpeg_quality(&self) -> Option<u8> {
+        self.jpeg_quality
+    }
+
+    pub fn webp_quality(&self) -> Option<u8> {
+        self.webp_quality
+    }
+
+    pub fn png_compression(&self) -> Option<u8> {
+        self.png_compression
+    }
+
+    pub fn apply_script(&self) -> Option<&String> {
+        self.apply_script.as_ref()
+    }
+
+    pub fn headless(&self) -> bool {
+        self.headless
+    }
+
+    pub fn ipc_socket(&self) -> Option<&String> {
+        self.ipc_socket.as_ref()
+    }
 }
 
 impl Default for Configuration {
@@ -499,6 +891,7 @@ impl Default for Configuration {
             initial_tool: Tools::Pointer,
             copy_command: None,
             annotation_size_factor: 1.0,
+            size_steps: vec![1.0, 2.0, 3.0, 4.0, 5.0, 7.0, 10.0, 14.0, 20.0, 28.0, 40.0],
             save_after_copy: false,
             actions_on_enter: vec![],
             actions_on_escape: vec![Action::Exit],
@@ -507,13 +900,27 @@ impl Default for Configuration {
             default_hide_toolbars: false,
             focus_toggles_toolbars: false,
             default_fill_shapes: false,
+            default_shadow: false,
+            default_outline: false,
             font: FontConfiguration::default(),
             primary_highlighter: Highlighters::Block,
+            primary_blur: BlurModes::Gaussian,
+            spotlight_opacity: 0.6,
+            arrow_head: ArrowHead::Open,
+            default_line_dash: LineDash::Solid,
+            line_caps: LineCaps::Butt,
             disable_notifications: false,
             profile_startup: false,
             no_window_decoration: false,
             brush_smooth_history_size: 0, // default to 0, no history
             keybinds: Keybinds::default(),
+            output_format: String::from("png"),
+            jpeg_quality: None,
+            webp_quality: None,
+            png_compression: None,
+            apply_script: None,
+            headless: false,
+            ipc_socket: None,
         }
     }
 }
@@ -556,6 +963,32 @@ struct KeybindsFile {
     marker: Option<String>,
     blur: Option<String>,
     highlight: Option<String>,
+    polygon: Option<String>,
+    callout: Option<String>,
+    spotlight: Option<String>,
+    magnifier: Option<String>,
+    undo: Option<Accelerators>,
+    redo: Option<Accelerators>,
+    save: Option<Accelerators>,
+    save_as: Option<Accelerators>,
+    save_project: Option<Accelerators>,
+    copy: Option<Accelerators>,
+    toggle_toolbars: Option<Accelerators>,
+    reset: Option<Accelerators>,
+    toggle_fill: Option<Accelerators>,
+    toggle_shadow: Option<Accelerators>,
+    toggle_outline: Option<Accelerators>,
+    size_small: Option<Accelerators>,
+    size_medium: Option<Accelerators>,
+    size_large: Option<Accelerators>,
+    size_up: Option<Accelerators>,
+    size_down: Option<Accelerators>,
+    zoom_in: Option<Accelerators>,
+    zoom_out: Option<Accelerators>,
+    zoom_fit: Option<Accelerators>,
+    zoom_original: Option<Accelerators>,
+    // one key combination per palette color
+    colors: Option<Vec<Accelerators>>,
 }
 
 #[derive(Deserialize)]
@@ -574,6 +1007,7 @@ struct ConfigurationFileGeneral {
     initial_tool: Option<Tools>,
     copy_command: Option<String>,
     annotation_size_factor: Option<f32>,
+    size_steps: Option<Vec<f32>>,
     save_after_copy: Option<bool>,
     output_filename: Option<String>,
     actions_on_enter: Option<Vec<Action>>,
@@ -582,10 +1016,21 @@ struct ConfigurationFileGeneral {
     default_hide_toolbars: Option<bool>,
     focus_toggles_toolbars: Option<bool>,
     default_fill_shapes: Option<bool>,
+    default_shadow: Option<bool>,
+    default_outline: Option<bool>,
     primary_highlighter: Option<Highlighters>,
+    primary_blur: Option<BlurModes>,
+    spotlight_opacity: Option<f32>,
+    arrow_head: Option<ArrowHead>,
+    default_line_dash: Option<LineDash>,
+    line_caps: Option<LineCaps>,
     disable_notifications: Option<bool>,
     no_window_decoration: Option<bool>,
     brush_smooth_history_size: Option<usize>,
+    output_format: Option<String>,
+    jpeg_quality: Option<u8>,
+    webp_quality: Option<u8>,
+    png_compression: Option<u8>,
 
     // --- deprecated options ---
     right_click_copy: Option<bool>,
@@ -625,3 +1070,38 @@ impl ConfigurationFile {
         Ok(Some(toml::from_str::<ConfigurationFile>(&content)?))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use relm4::gtk::gdk::{Key, ModifierType};
+
+    use super::*;
+
+    fn merged(file: &str) -> Keybinds {
+        let mut keybinds = Keybinds::default();
+        keybinds.merge(toml::from_str::<KeybindsFile>(file).unwrap());
+        keybinds
+    }
+
+    #[test]
+    fn default_keybinds_have_no_conflicts() {
+        assert!(Keybinds::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicts_are_detected() {
+        let keybinds = merged(r#"copy = "ctrl+z""#);
+        assert_eq!(
+            keybinds.conflicts(),
+            ["'Ctrl+Z' is used for undo and copy, only undo will work."]
+        );
+        let ctrl_z = KeyEventMsg::new(Key::z, 0, ModifierType::CONTROL_MASK);
+        assert_eq!(keybinds.get_action(&ctrl_z), Some(KeyAction::Undo));
+
+        let keybinds = merged(r#"size-up = "p""#);
+        assert_eq!(
+            keybinds.conflicts(),
+            ["'P' is used for pointer and size-up, only pointer will work."]
+        );
+    }
+}
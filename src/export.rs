@@ -0,0 +1,249 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use gdk_pixbuf::{glib::Bytes, Colorspace, Pixbuf};
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::{self, Vec2D},
+    style::{Color, LineCaps, Style, SHADOW_OPACITY},
+    tools::{self, Drawable},
+};
+
+/// Finds the gdk-pixbuf writer responsible for a file extension (or format name), e.g. `jpg`
+/// resolves to `jpeg`.
+pub fn format_for_extension(extension: &str) -> Result<String> {
+    let extension = extension.to_lowercase();
+    if extension == SVG_FORMAT {
+        return Ok(SVG_FORMAT.to_string());
+    }
+
+    Pixbuf::formats()
+        .iter()
+        .filter(|f| f.is_writable())
+        .find_map(|f| {
+            let name = f.name()?;
+            if name.eq_ignore_ascii_case(&extension)
+                || f.extensions().iter().any(|e| e.as_str() == extension)
+            {
+                Some(name.to_string())
+            } else {
+                None
+            }
+        })
+        .ok_or(anyhow!("No image writer available for '{extension}'"))
+}
+
+pub fn format_for_filename(filename: &str) -> Result<String> {
+    match Path::new(filename).extension() {
+        Some(extension) => format_for_extension(&extension.to_string_lossy()),
+        None => Err(anyhow!(
+            "Cannot determine image format, '{filename}' has no extension"
+        )),
+    }
+}
+
+/// Format for outputs without a filename, e.g. stdout or the copy command.
+pub fn default_format() -> Result<String> {
+    format_for_extension(APP_CONFIG.read().output_format())
+}
+
+pub fn encode(image: &Pixbuf, format: &str) -> Result<Vec<u8>> {
+    let config = APP_CONFIG.read();
+    let option = match format {
+        "jpeg" => config
+            .jpeg_quality()
+            .map(|q| ("quality", q.min(100).to_string())),
+        "webp" => config
+            .webp_quality()
+            .map(|q| ("quality", q.min(100).to_string())),
+        "png" => config
+            .png_compression()
+            .map(|c| ("compression", c.min(9).to_string())),
+        _ => None,
+    };
+    let options: Vec<(&str, &str)> = option.iter().map(|(k, v)| (*k, v.as_str())).collect();
+
+    Ok(image.save_to_bufferv(format, &options)?)
+}
+
+pub const SVG_FORMAT: &str = "svg";
+
+pub fn is_svg(format: &str) -> bool {
+    format == SVG_FORMAT
+}
+
+/// Writes the annotations as native svg elements on top of the embedded background image.
+/// Redactions are applied to the embedded pixels and only the cropped part is embedded, so
+/// neither can be undone by editing the svg.
+pub fn svg(
+    background: &Pixbuf,
+    crop: Option<(Vec2D, Vec2D)>,
+    drawables: &[Box<dyn Drawable>],
+) -> Result<String> {
+    let (pos, size) = crop.unwrap_or((
+        Vec2D::zero(),
+        Vec2D::new(background.width() as f32, background.height() as f32),
+    ));
+    let (x, y, width, height) =
+        math::pixel_region((pos, size), background.width(), background.height())
+            .ok_or(anyhow!("Crop area is outside of the image"))?;
+    let pixels = tools::redacted_background(background, drawables, (x, y, width, height));
+    let bytes: Vec<u8> = pixels
+        .buf()
+        .iter()
+        .flat_map(|p| [p.r, p.g, p.b, p.a])
+        .collect();
+    let png = Pixbuf::from_bytes(
+        &Bytes::from_owned(bytes),
+        Colorspace::Rgb,
+        true,
+        8,
+        width,
+        height,
+        width * 4,
+    )
+    .save_to_bufferv("png", &[])?;
+
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        size.x, size.y, pos.x, pos.y, size.x, size.y
+    );
+    svg.push('\n');
+    svg.push_str(&format!(
+        r#"<image id="background" x="{x}" y="{y}" width="{width}" height="{height}" xlink:href="data:image/png;base64,{}"/>"#,
+        glib::base64_encode(&png)
+    ));
+    svg.push('\n');
+    svg.push_str(&svg_spotlights(background, drawables));
+
+    for (id, drawable) in drawables.iter().enumerate() {
+        if let Some(element) = drawable.to_svg(id) {
+            svg.push_str(&element);
+            svg.push('\n');
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+/// The dimmed overlay of all spotlights as a masked rectangle, empty if there are none.
+fn svg_spotlights(background: &Pixbuf, drawables: &[Box<dyn Drawable>]) -> String {
+    let cutouts: String = drawables
+        .iter()
+        .filter_map(|d| d.spotlight())
+        .filter_map(|s| s.svg_shape(r#"fill="black""#))
+        .collect();
+    if cutouts.is_empty() {
+        return String::new();
+    }
+
+    let (width, height) = (background.width(), background.height());
+    let opacity = APP_CONFIG.read().spotlight_opacity();
+    format!(
+        r#"<mask id="spotlight"><rect width="{width}" height="{height}" fill="white"/>{cutouts}</mask>
+<rect width="{width}" height="{height}" fill="black" fill-opacity="{opacity}" mask="url(#spotlight)"/>
+"#
+    )
+}
+
+/// Color without alpha, the alpha channel is written as separate opacity attribute.
+pub fn svg_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+/// Paint attributes of an svg element, matching the femtovg paint of the style.
+pub fn svg_paint(style: &Style, fill: bool) -> String {
+    let rgb = svg_color(style.color);
+    let opacity = style.color.a as f32 / 255.0;
+    if fill {
+        format!(r#"fill="{rgb}" fill-opacity="{opacity}" stroke="none""#)
+    } else {
+        format!(r#"fill="none" {}"#, svg_stroke(style))
+    }
+}
+
+/// Paint attributes of a shape, filled ones use the fill color and get an outline if it is set.
+pub fn svg_shape_paint(style: &Style, fill: bool) -> String {
+    if !fill {
+        return svg_paint(style, false);
+    }
+    let color = style.effective_fill_color();
+    let outline = match style.fill_color {
+        Some(_) => svg_stroke(style),
+        None => r#"stroke="none""#.to_string(),
+    };
+    format!(
+        r#"fill="{}" fill-opacity="{}" {outline}"#,
+        svg_color(color),
+        color.a as f32 / 255.0
+    )
+}
+
+fn svg_stroke(style: &Style) -> String {
+    let rgb = svg_color(style.color);
+    let opacity = style.color.a as f32 / 255.0;
+    let caps = match style.line_caps() {
+        LineCaps::Butt => "butt",
+        LineCaps::Round => "round",
+        LineCaps::Square => "square",
+    };
+    let dashes = style
+        .dash_pattern()
+        .map(|(dash, gap)| format!(r#" stroke-dasharray="{dash} {gap}""#))
+        .unwrap_or_default();
+    format!(
+        r#"stroke="{rgb}" stroke-opacity="{opacity}" stroke-width="{}" stroke-linecap="{caps}"{dashes}"#,
+        style.size.to_line_width(style.annotation_size_factor)
+    )
+}
+
+/// Wraps the element of an annotation in a filter painting its shadow and outline, if it has
+/// any. `id` keeps the filters of several annotations apart.
+pub fn svg_with_effects(style: &Style, id: usize, element: String) -> String {
+    if !style.shadow && !style.outline {
+        return element;
+    }
+
+    let mut filter = String::new();
+    let mut layers = String::new();
+    if style.shadow {
+        let offset = style.shadow_offset();
+        filter.push_str(&format!(
+            r#"<feGaussianBlur in="SourceAlpha" stdDeviation="{}"/><feOffset dx="{offset}" dy="{offset}"/><feComponentTransfer result="shadow"><feFuncA type="linear" slope="{SHADOW_OPACITY}"/></feComponentTransfer>"#,
+            offset / 3.0
+        ));
+        layers.push_str(r#"<feMergeNode in="shadow"/>"#);
+    }
+    if style.outline {
+        let color = style.color.contrasting();
+        filter.push_str(&format!(
+            r#"<feMorphology in="SourceAlpha" operator="dilate" radius="{}" result="grown"/><feFlood flood-color="{}" flood-opacity="{}"/><feComposite in2="grown" operator="in" result="outline"/>"#,
+            style.outline_width(),
+            svg_color(color),
+            style.color.a as f32 / 255.0
+        ));
+        layers.push_str(r#"<feMergeNode in="outline"/>"#);
+    }
+
+    // thin lines have no height to base the region of the filter on
+    format!(
+        r#"<defs><filter id="effects-{id}" filterUnits="userSpaceOnUse" x="-50%" y="-50%" width="200%" height="200%">{filter}<feMerge>{layers}<feMergeNode in="SourceGraphic"/></feMerge></filter></defs><g filter="url(#effects-{id})">{element}</g>"#
+    )
+}
+
+pub fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn svg_points(points: &[Vec2D]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -2,6 +2,7 @@ use anyhow::{anyhow, Error, Result};
 use glow::HasContext;
 use std::{
     cell::{RefCell, RefMut},
+    ffi::CString,
     num::NonZeroU32,
     rc::Rc,
 };
@@ -12,7 +13,7 @@ use femtovg::{
     rgb::{RGB, RGBA, RGBA8},
     Canvas, FontId, ImageFlags, ImageId, ImageSource, Paint, Path, PixelFormat, Transform2D,
 };
-use fontconfig::Fontconfig;
+use fontconfig::{Fontconfig, Pattern};
 use gdk_pixbuf::Pixbuf;
 use gtk::{glib, prelude::*, subclass::prelude::*};
 use relm4::{gtk, Sender};
@@ -22,14 +23,14 @@ use crate::{
     configuration::Action,
     math::{rect_ensure_in_bounds, rect_round, Vec2D},
     sketch_board::SketchBoardInput,
-    tools::{CropTool, Drawable, Tool},
+    tools::{CropTool, Drawable, DrawableStore, EditCommand, History, HistoryEntry, Tool},
     APP_CONFIG,
 };
 
 #[derive(Default)]
 pub struct FemtoVGArea {
     canvas: RefCell<Option<femtovg::Canvas<femtovg::renderer::OpenGl>>>,
-    font: RefCell<Option<FontId>>,
+    fonts: RefCell<Option<Fonts>>,
     inner: RefCell<Option<FemtoVgAreaMut>>,
     request_render: RefCell<Option<Vec<Action>>>,
     sender: RefCell<Option<Sender<SketchBoardInput>>>,
@@ -42,8 +43,12 @@ pub struct FemtoVgAreaMut {
     crop_tool: Rc<RefCell<CropTool>>,
     scale_factor: f32,
     offset: Vec2D,
-    drawables: Vec<Box<dyn Drawable>>,
-    redo_stack: Vec<Box<dyn Drawable>>,
+    canvas_size: Vec2D,
+    fit_to_window: bool,
+    drawables: DrawableStore,
+    undo_stack: Vec<(HistoryEntry, EditCommand)>,
+    redo_stack: Vec<(HistoryEntry, EditCommand)>,
+    history_changed: bool,
 }
 
 #[glib::object_subclass]
@@ -97,31 +102,32 @@ impl GLAreaImpl for FemtoVGArea {
 
         let mut bc = self.canvas.borrow_mut();
         let canvas = bc.as_mut().unwrap(); // this unwrap is safe as long as we call "ensure_canvas" before
-        let font = self.font.borrow().unwrap(); // this unwrap is safe as long as we call "ensure_canvas" before
+        let fonts = self.fonts.borrow().unwrap(); // this unwrap is safe as long as we call "ensure_canvas" before
         let mut actions = self.request_render.borrow_mut();
 
         // if we got requested to render a frame
         if let Some(a) = actions.take() {
             // render image
-            let image = match self
+            let image = self
                 .inner()
                 .as_mut()
                 .expect("Did you call init before using FemtoVgArea?")
-                .render_native_resolution(canvas, font)
-            {
-                Ok(t) => t,
-                Err(e) => {
-                    println!("Error while rendering image: {e}");
-                    return glib::Propagation::Stop;
-                }
-            };
+                .render_native_resolution(canvas, fonts)
+                .map_err(|e| e.to_string());
+            let failed = image.is_err();
+            if let Err(e) = &image {
+                println!("Error while rendering image: {e}");
+            }
 
-            // send result
+            // send result, failures too so that waiting requests are answered
             self.sender
                 .borrow()
                 .as_ref()
                 .expect("Did you call init before using FemtoVgArea?")
                 .emit(SketchBoardInput::RenderResult(image, a));
+            if failed {
+                return glib::Propagation::Stop;
+            }
 
             // reset request
             *actions = None;
@@ -130,7 +136,7 @@ impl GLAreaImpl for FemtoVGArea {
             .inner()
             .as_mut()
             .expect("Did you call init before using FemtoVgArea?")
-            .render_framebuffer(canvas, font)
+            .render_framebuffer(canvas, fonts)
         {
             println!("Error rendering to framebuffer: {e}");
         }
@@ -143,62 +149,25 @@ impl FemtoVGArea {
         sender: Sender<SketchBoardInput>,
         crop_tool: Rc<RefCell<CropTool>>,
         active_tool: Rc<RefCell<dyn Tool>>,
+        drawables: DrawableStore,
         background_image: Pixbuf,
     ) {
-        self.inner().replace(FemtoVgAreaMut {
+        self.inner().replace(FemtoVgAreaMut::new(
             background_image,
-            background_image_id: None,
-            active_tool,
             crop_tool,
-            scale_factor: 1.0,
-            offset: Vec2D::zero(),
-            drawables: Vec::new(),
-            redo_stack: Vec::new(),
-        });
+            active_tool,
+            drawables,
+        ));
         self.sender.borrow_mut().replace(sender);
     }
     fn ensure_canvas(&self) {
         if self.canvas.borrow().is_none() {
-            let c = self
+            let mut c = self
                 .setup_canvas()
                 .expect("Cannot setup renderer and canvas");
+            self.fonts.borrow_mut().replace(load_fonts(&mut c));
             self.canvas.borrow_mut().replace(c);
         }
-
-        let app_config = APP_CONFIG.read();
-        let font = app_config
-            .font()
-            .family()
-            .map(|font| {
-                let font = Fontconfig::new()
-                    .ok_or_else(|| anyhow!("Error while initializing fontconfig"))?
-                    .find(font, app_config.font().style())
-                    .ok_or_else(|| anyhow!("Can not find font"))?;
-                let font_id = self
-                    .canvas
-                    .borrow_mut()
-                    .as_mut()
-                    .unwrap() // this unwrap is safe because it gets placed above
-                    .add_font(font.path)?;
-                Ok(font_id)
-            })
-            .transpose()
-            .unwrap_or_else(|e: Error| {
-                println!("Error while loading font. Using default font: {e}");
-                None
-            });
-        if let Some(font) = font {
-            self.font.borrow_mut().replace(font);
-        } else {
-            self.font.borrow_mut().replace(
-                self.canvas
-                    .borrow_mut()
-                    .as_mut()
-                    .unwrap() // this unwrap is safe because it gets placed above
-                    .add_font_mem(&resource!("src/assets/Roboto-Regular.ttf"))
-                    .expect("Cannot add font"),
-            );
-        }
     }
 
     fn setup_canvas(&self) -> Result<femtovg::Canvas<femtovg::renderer::OpenGl>> {
@@ -227,8 +196,12 @@ impl FemtoVGArea {
     pub fn inner(&self) -> RefMut<'_, Option<FemtoVgAreaMut>> {
         self.inner.borrow_mut()
     }
+    /// Actions requested before the next frame are all handled by one render.
     pub fn request_render(&self, actions: &[Action]) {
-        self.request_render.borrow_mut().replace(actions.into());
+        self.request_render
+            .borrow_mut()
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(actions);
         self.obj().queue_render();
     }
     pub fn set_parent_sender(&self, sender: Sender<SketchBoardInput>) {
@@ -236,20 +209,180 @@ impl FemtoVGArea {
     }
 }
 
+/// The configured font and the variants rich text is formatted with.
+#[derive(Debug, Clone, Copy)]
+pub struct Fonts {
+    pub regular: FontId,
+    pub bold: FontId,
+    pub italic: FontId,
+    pub bold_italic: FontId,
+}
+
+impl Fonts {
+    pub fn get(&self, bold: bool, italic: bool) -> FontId {
+        match (bold, italic) {
+            (false, false) => self.regular,
+            (true, false) => self.bold,
+            (false, true) => self.italic,
+            (true, true) => self.bold_italic,
+        }
+    }
+}
+
+/// Loads the configured font into the canvas, falls back to the embedded Roboto.
+fn load_font(canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>) -> FontId {
+    let app_config = APP_CONFIG.read();
+    let font = app_config
+        .font()
+        .family()
+        .map(|family| find_font(canvas, family, app_config.font().style()))
+        .transpose()
+        .unwrap_or_else(|e: Error| {
+            println!("Error while loading font. Using default font: {e}");
+            None
+        });
+    match font {
+        Some(font) => font,
+        None => canvas
+            .add_font_mem(&resource!("src/assets/Roboto-Regular.ttf"))
+            .expect("Cannot add font"),
+    }
+}
+
+/// Loads the configured font and its bold and italic variants, variants that can not be found
+/// fall back to the regular font.
+pub fn load_fonts(canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>) -> Fonts {
+    let regular = load_font(canvas);
+    // the embedded Roboto only comes in regular, the system may have the rest of the family
+    let family = APP_CONFIG
+        .read()
+        .font()
+        .family()
+        .unwrap_or("Roboto")
+        .to_string();
+    let mut variant = |style: &str, bold: bool, italic: bool| {
+        find_font_variant(canvas, &family, style, bold, italic).unwrap_or_else(|e| {
+            println!("Error while loading {style} font. Using regular font: {e}");
+            regular
+        })
+    };
+    Fonts {
+        regular,
+        bold: variant("Bold", true, false),
+        italic: variant("Italic", false, true),
+        bold_italic: variant("Bold Italic", true, true),
+    }
+}
+
+/// Like `find_font`, but fontconfig's closest match is only taken if it belongs to `family` and
+/// really is bold or italic, a font of another family would not fit the regular one.
+fn find_font_variant(
+    canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    family: &str,
+    style: &str,
+    bold: bool,
+    italic: bool,
+) -> Result<FontId> {
+    let fontconfig =
+        Fontconfig::new().ok_or_else(|| anyhow!("Error while initializing fontconfig"))?;
+    let mut pattern = Pattern::new(&fontconfig);
+    pattern.add_string(fontconfig::FC_FAMILY, &CString::new(family)?);
+    pattern.add_string(fontconfig::FC_STYLE, &CString::new(style)?);
+    let font = pattern.font_match();
+
+    let matched_family = font.get_string(fontconfig::FC_FAMILY).unwrap_or_default();
+    if !matched_family.eq_ignore_ascii_case(family) {
+        return Err(anyhow!(
+            "'{family}' is not installed, found '{matched_family}'"
+        ));
+    }
+    if bold && font.weight().is_none_or(|w| w < fontconfig::FC_WEIGHT_BOLD)
+        || italic && font.slant().is_none_or(|s| s == fontconfig::FC_SLANT_ROMAN)
+    {
+        return Err(anyhow!("'{family}' has no {style} style"));
+    }
+
+    let path = font
+        .filename()
+        .ok_or_else(|| anyhow!("Can not find font"))?;
+    Ok(canvas.add_font(path)?)
+}
+
+fn find_font(
+    canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    family: &str,
+    style: Option<&str>,
+) -> Result<FontId> {
+    let font = Fontconfig::new()
+        .ok_or_else(|| anyhow!("Error while initializing fontconfig"))?
+        .find(family, style)
+        .ok_or_else(|| anyhow!("Can not find font"))?;
+    Ok(canvas.add_font(font.path)?)
+}
+
 impl FemtoVgAreaMut {
+    const MIN_ZOOM: f32 = 0.05;
+    const MAX_ZOOM: f32 = 32.0;
+
+    pub fn new(
+        background_image: Pixbuf,
+        crop_tool: Rc<RefCell<CropTool>>,
+        active_tool: Rc<RefCell<dyn Tool>>,
+        drawables: DrawableStore,
+    ) -> Self {
+        Self {
+            background_image,
+            background_image_id: None,
+            active_tool,
+            crop_tool,
+            scale_factor: 1.0,
+            offset: Vec2D::zero(),
+            canvas_size: Vec2D::zero(),
+            fit_to_window: true,
+            drawables,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            history_changed: false,
+        }
+    }
+
     pub fn commit(&mut self, drawable: Box<dyn Drawable>) {
-        self.drawables.push(drawable);
+        let index = self.drawables.borrow().len();
+        self.edit(EditCommand::Add { index, drawable });
+    }
+
+    pub fn edit(&mut self, command: EditCommand) {
+        let continues_adjustment = self.redo_stack.is_empty()
+            && self.undo_stack.last().is_some_and(|(_, undo)| {
+                command.continues_adjustment(undo, &self.drawables.borrow())
+            });
+        if continues_adjustment {
+            // the undo command of the first adjustment already restores the style from before
+            self.apply(command);
+            self.history_changed = true;
+            return;
+        }
+
+        let entry = command.describe(&self.drawables.borrow());
+        let undo = self.apply(command);
+        self.undo_stack.push((entry, undo));
         self.redo_stack.clear();
+        self.history_changed = true;
     }
 
-    pub fn undo(&mut self) -> bool {
-        match self.drawables.pop() {
-            Some(mut d) => {
-                // notify of the undo action
-                d.handle_undo();
+    fn apply(&mut self, command: EditCommand) -> EditCommand {
+        command.apply(
+            &mut self.drawables.borrow_mut(),
+            &mut self.crop_tool.borrow_mut(),
+        )
+    }
 
-                // push to redo stack
-                self.redo_stack.push(d);
+    pub fn undo(&mut self) -> bool {
+        match self.undo_stack.pop() {
+            Some((entry, command)) => {
+                let redo = self.apply(command);
+                self.redo_stack.push((entry, redo));
+                self.history_changed = true;
                 true
             }
             None => false,
@@ -257,30 +390,76 @@ impl FemtoVgAreaMut {
     }
     pub fn redo(&mut self) -> bool {
         match self.redo_stack.pop() {
-            Some(mut d) => {
-                // notify of the redo action
-                d.handle_redo();
-
-                // push to drawable stack
-                self.drawables.push(d);
-
+            Some((entry, command)) => {
+                let undo = self.apply(command);
+                self.undo_stack.push((entry, undo));
+                self.history_changed = true;
                 true
             }
             None => false,
         }
     }
+
+    /// Removes all drawables and the crop in a single step, that can be undone.
     pub fn reset(&mut self) -> bool {
-        let mut any_undone = false;
-        while let Some(mut d) = self.drawables.pop() {
-            // notify of the undo action
-            d.handle_undo();
+        // remove from the top, so that the indices stay valid
+        let mut commands: Vec<_> = (0..self.drawables.borrow().len())
+            .rev()
+            .map(|index| EditCommand::Remove { index })
+            .collect();
+        if self.crop_tool.borrow().get_crop().is_some() {
+            commands.push(EditCommand::Crop { crop: None });
+        }
+
+        if commands.is_empty() {
+            return false;
+        }
+        self.edit(EditCommand::Batch(commands));
+        true
+    }
+
+    /// Undoes or redoes single steps until `position` steps are applied.
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        let mut changed = false;
+        while self.undo_stack.len() > position && self.undo() {
+            changed = true;
+        }
+        while self.undo_stack.len() < position && self.redo() {
+            changed = true;
+        }
+        changed
+    }
 
-            // push to redo stack
-            self.redo_stack.push(d);
+    pub fn history(&self) -> History {
+        History {
+            entries: self
+                .undo_stack
+                .iter()
+                .chain(self.redo_stack.iter().rev())
+                .map(|(entry, _)| entry.clone())
+                .collect(),
+            position: self.undo_stack.len(),
+        }
+    }
 
-            any_undone = true;
+    /// The history, if it changed since the last call.
+    pub fn take_history_update(&mut self) -> Option<History> {
+        if std::mem::take(&mut self.history_changed) {
+            Some(self.history())
+        } else {
+            None
         }
-        any_undone
+    }
+
+    /// Starts over with a new image, the history of the old one is dropped.
+    pub fn load(&mut self, background_image: Pixbuf, crop_tool: Rc<RefCell<CropTool>>) {
+        self.background_image = background_image;
+        self.background_image_id = None;
+        self.crop_tool = crop_tool;
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.history_changed = true;
+        self.zoom_to_fit();
     }
 
     pub fn set_active_tool(&mut self, active_tool: Rc<RefCell<dyn Tool>>) {
@@ -290,7 +469,7 @@ impl FemtoVgAreaMut {
     pub fn render_native_resolution(
         &mut self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
+        fonts: Fonts,
     ) -> anyhow::Result<ImgVec<RGBA8>> {
         let bounds = (
             Vec2D::zero(),
@@ -326,7 +505,7 @@ impl FemtoVgAreaMut {
         canvas.set_transform(&transform);
 
         // render
-        self.render(canvas, font, false)?;
+        self.render(canvas, fonts, false)?;
 
         // return screenshot
         let result = canvas.screenshot();
@@ -341,7 +520,7 @@ impl FemtoVgAreaMut {
     pub fn render_framebuffer(
         &mut self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
+        fonts: Fonts,
     ) -> Result<()> {
         canvas.set_render_target(femtovg::RenderTarget::Screen);
 
@@ -353,7 +532,7 @@ impl FemtoVgAreaMut {
         canvas.reset_transform();
         canvas.set_transform(&transform);
 
-        self.render(canvas, font, true)?;
+        self.render(canvas, fonts, true)?;
 
         Ok(())
     }
@@ -361,8 +540,8 @@ impl FemtoVgAreaMut {
     fn render(
         &mut self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
-        render_crop: bool,
+        fonts: Fonts,
+        render_overlays: bool,
     ) -> Result<()> {
         // clear canvas
         canvas.clear_rect(
@@ -375,6 +554,7 @@ impl FemtoVgAreaMut {
 
         // render background
         self.render_background_image(canvas)?;
+        self.render_spotlights(canvas)?;
 
         let bounds = (
             Vec2D::zero(),
@@ -383,20 +563,28 @@ impl FemtoVgAreaMut {
                 self.background_image.height() as f32,
             ),
         );
-        // render the whole stack
-        for d in &mut self.drawables {
-            d.draw(canvas, font, bounds)?;
+        // render the whole stack, except what the active tool is editing
+        let hidden = self.active_tool.borrow().get_hidden_drawable();
+        for (index, d) in self.drawables.borrow().iter().enumerate() {
+            if Some(index) != hidden {
+                d.draw(canvas, fonts, bounds)?;
+            }
         }
 
         // render active tool
         if let Some(d) = self.active_tool.borrow().get_drawable() {
-            d.draw(canvas, font, bounds)?;
+            d.draw(canvas, fonts, bounds)?;
         }
 
-        // render crop tool
-        if render_crop {
+        if render_overlays {
+            // render crop tool
             if let Some(c) = self.crop_tool.borrow().get_crop() {
-                c.draw(canvas, font, bounds)?;
+                c.draw(canvas, fonts, bounds)?;
+            }
+
+            // render helpers of the active tool
+            if let Some(o) = self.active_tool.borrow().get_overlay() {
+                o.draw(canvas, fonts, bounds)?;
             }
         }
 
@@ -404,10 +592,10 @@ impl FemtoVgAreaMut {
         Ok(())
     }
 
-    fn render_background_image(
+    fn background_paint(
         &mut self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-    ) -> Result<()> {
+    ) -> Result<Paint> {
         let background_image_id = match self.background_image_id {
             Some(id) => id,
             None => {
@@ -417,7 +605,18 @@ impl FemtoVgAreaMut {
             }
         };
 
-        // render the image
+        Ok(Paint::image(
+            background_image_id,
+            0f32,
+            0f32,
+            self.background_image.width() as f32,
+            self.background_image.height() as f32,
+            0f32,
+            1f32,
+        ))
+    }
+
+    fn background_path(&self) -> Path {
         let mut path = Path::new();
         path.rect(
             0.0,
@@ -425,20 +624,53 @@ impl FemtoVgAreaMut {
             self.background_image.width() as f32,
             self.background_image.height() as f32,
         );
+        path
+    }
 
+    fn render_background_image(
+        &mut self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    ) -> Result<()> {
+        let paint = self.background_paint(canvas)?;
+        canvas.fill_path(&self.background_path(), &paint);
+
+        Ok(())
+    }
+
+    /// Dims the background outside of all spotlights, before any annotation is drawn.
+    fn render_spotlights(
+        &mut self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+    ) -> Result<()> {
+        let mut spotlights: Vec<_> = self
+            .drawables
+            .borrow()
+            .iter()
+            .filter_map(|d| d.spotlight())
+            .collect();
+        spotlights.extend(
+            self.active_tool
+                .borrow()
+                .get_drawable()
+                .and_then(|d| d.spotlight()),
+        );
+        if spotlights.is_empty() {
+            return Ok(());
+        }
+
+        // one overlay for the whole image, the spotlights are cut out by painting the
+        // background again, so overlapping spotlights merge
+        let opacity = APP_CONFIG.read().spotlight_opacity();
         canvas.fill_path(
-            &path,
-            &Paint::image(
-                background_image_id,
-                0f32,
-                0f32,
-                self.background_image.width() as f32,
-                self.background_image.height() as f32,
-                0f32,
-                1f32,
-            ),
+            &self.background_path(),
+            &Paint::color(femtovg::Color::rgbaf(0.0, 0.0, 0.0, opacity)),
         );
 
+        let paint = self.background_paint(canvas)?;
+        for path in spotlights.iter().filter_map(|s| s.path()) {
+            canvas.fill_path(&path, &paint);
+        }
+
         Ok(())
     }
 
@@ -516,13 +748,22 @@ impl FemtoVgAreaMut {
         &mut self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
     ) {
+        self.canvas_size = Vec2D::new(canvas.width() as f32, canvas.height() as f32);
+
+        // a manual zoom stays as it is when the window gets resized
+        if self.fit_to_window {
+            self.fit_transformation();
+        }
+    }
+
+    fn fit_transformation(&mut self) {
         // calculate scale
         let image_width = self.background_image.width() as f32;
         let image_height = self.background_image.height() as f32;
         let aspect_ratio = image_width / image_height;
 
-        let canvas_width = canvas.width() as f32;
-        let canvas_height = canvas.height() as f32;
+        let canvas_width = self.canvas_size.x;
+        let canvas_height = self.canvas_size.y;
 
         self.scale_factor = if canvas_width / aspect_ratio <= canvas_height {
             canvas_width / aspect_ratio / image_height
@@ -532,13 +773,42 @@ impl FemtoVgAreaMut {
 
         // calculate offset
         self.offset = Vec2D::new(
-            (canvas.width() as f32 - self.background_image.width() as f32 * self.scale_factor)
-                / 2.0,
-            (canvas.height() as f32 - self.background_image.height() as f32 * self.scale_factor)
-                / 2.0,
+            (canvas_width - image_width * self.scale_factor) / 2.0,
+            (canvas_height - image_height * self.scale_factor) / 2.0,
         );
     }
 
+    pub fn zoom_to_fit(&mut self) {
+        self.fit_to_window = true;
+        self.fit_transformation();
+    }
+
+    /// Sets the zoom, the image point below `center` (in canvas coordinates) stays in place.
+    pub fn zoom_to(&mut self, center: Vec2D, scale_factor: f32) {
+        let scale_factor = scale_factor.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.offset = center - (center - self.offset) * (scale_factor / self.scale_factor);
+        self.scale_factor = scale_factor;
+        self.fit_to_window = false;
+    }
+
+    pub fn zoom_at(&mut self, center: Vec2D, factor: f32) {
+        self.zoom_to(center, self.scale_factor * factor);
+    }
+
+    pub fn pan(&mut self, delta: Vec2D) {
+        self.offset += delta;
+        self.fit_to_window = false;
+    }
+
+    /// The current zoom, or `None` if the image is fit into the window.
+    pub fn zoom_level(&self) -> Option<f32> {
+        if self.fit_to_window {
+            None
+        } else {
+            Some(self.scale_factor)
+        }
+    }
+
     pub fn abs_canvas_to_image_coordinates(&self, input: Vec2D, dpi_scale_factor: f32) -> Vec2D {
         Vec2D::new(
             (input.x * dpi_scale_factor - self.offset.x) / self.scale_factor,
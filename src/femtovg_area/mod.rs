@@ -1,5 +1,7 @@
 mod imp;
 
+pub use imp::{load_fonts, FemtoVgAreaMut, Fonts};
+
 use std::{cell::RefCell, rc::Rc};
 
 use gdk_pixbuf::{glib::subclass::types::ObjectSubclassIsExt, Pixbuf};
@@ -13,7 +15,7 @@ use crate::{
     configuration::Action,
     math::Vec2D,
     sketch_board::SketchBoardInput,
-    tools::{CropTool, Drawable, Tool},
+    tools::{CropTool, Drawable, DrawableStore, EditCommand, History, Tool},
 };
 
 glib::wrapper! {
@@ -44,6 +46,13 @@ impl FemtoVGArea {
             .expect("Did you call init before using FemtoVgArea?")
             .commit(drawable);
     }
+    pub fn edit(&mut self, operation: EditCommand) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .edit(operation);
+    }
     pub fn undo(&mut self) -> bool {
         self.imp()
             .inner()
@@ -58,6 +67,27 @@ impl FemtoVGArea {
             .expect("Did you call init before using FemtoVgArea?")
             .redo()
     }
+    pub fn jump_to(&mut self, position: usize) -> bool {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .jump_to(position)
+    }
+    pub fn take_history_update(&self) -> Option<History> {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .take_history_update()
+    }
+    pub fn load(&self, background_image: Pixbuf, crop_tool: Rc<RefCell<CropTool>>) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .load(background_image, crop_tool);
+    }
     pub fn request_render(&self, actions: &[Action]) {
         self.imp().request_render(actions);
     }
@@ -84,14 +114,64 @@ impl FemtoVGArea {
             .expect("Did you call init before using FemtoVgArea?")
             .rel_canvas_to_image_coordinates(input, self.scale_factor() as f32)
     }
+    pub fn zoom_to_fit(&self) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .zoom_to_fit();
+    }
+
+    /// Zooms by `factor` around `center` in widget coordinates, or around the middle of the widget.
+    pub fn zoom_at(&self, center: Option<Vec2D>, factor: f32) {
+        let center = self.canvas_position(center);
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .zoom_at(center, factor);
+    }
+
+    pub fn zoom_to(&self, center: Option<Vec2D>, scale_factor: f32) {
+        let center = self.canvas_position(center);
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .zoom_to(center, scale_factor);
+    }
+
+    pub fn pan(&self, delta: Vec2D) {
+        self.imp()
+            .inner()
+            .as_mut()
+            .expect("Did you call init before using FemtoVgArea?")
+            .pan(delta * self.scale_factor() as f32);
+    }
+
+    pub fn zoom_level(&self) -> Option<f32> {
+        self.imp()
+            .inner()
+            .as_ref()
+            .expect("Did you call init before using FemtoVgArea?")
+            .zoom_level()
+    }
+
+    fn canvas_position(&self, widget_position: Option<Vec2D>) -> Vec2D {
+        let position = widget_position
+            .unwrap_or_else(|| Vec2D::new(self.width() as f32 / 2.0, self.height() as f32 / 2.0));
+        position * self.scale_factor() as f32
+    }
+
     pub fn init(
         &mut self,
         sender: Sender<SketchBoardInput>,
         crop_tool: Rc<RefCell<CropTool>>,
         active_tool: Rc<RefCell<dyn Tool>>,
+        drawables: DrawableStore,
         background_image: Pixbuf,
     ) {
         self.imp()
-            .init(sender, crop_tool, active_tool, background_image);
+            .init(sender, crop_tool, active_tool, drawables, background_image);
     }
 }
@@ -0,0 +1,204 @@
+use std::{
+    ffi::{c_char, c_void, CString},
+    ptr,
+};
+
+use anyhow::{anyhow, Result};
+use libloading::Library;
+
+type EglDisplay = *mut c_void;
+type EglConfig = *mut c_void;
+type EglContext = *mut c_void;
+type EglSurface = *mut c_void;
+type EglBoolean = u32;
+type EglInt = i32;
+
+const EGL_NONE: EglInt = 0x3038;
+const EGL_SURFACE_TYPE: EglInt = 0x3033;
+const EGL_RENDERABLE_TYPE: EglInt = 0x3040;
+const EGL_OPENGL_BIT: EglInt = 0x0008;
+const EGL_OPENGL_ES3_BIT: EglInt = 0x0040;
+const EGL_OPENGL_API: u32 = 0x30A2;
+const EGL_OPENGL_ES_API: u32 = 0x30A0;
+const EGL_CONTEXT_MAJOR_VERSION: EglInt = 0x3098;
+const EGL_CONTEXT_MINOR_VERSION: EglInt = 0x30FB;
+const EGL_CONTEXT_OPENGL_PROFILE_MASK: EglInt = 0x30FD;
+const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EglInt = 0x0001;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+
+type GetProcAddressFn = unsafe extern "C" fn(*const c_char) -> *const c_void;
+type GetDisplayFn = unsafe extern "C" fn(*mut c_void) -> EglDisplay;
+type GetPlatformDisplayFn = unsafe extern "C" fn(u32, *mut c_void, *const EglInt) -> EglDisplay;
+type InitializeFn = unsafe extern "C" fn(EglDisplay, *mut EglInt, *mut EglInt) -> EglBoolean;
+type TerminateFn = unsafe extern "C" fn(EglDisplay) -> EglBoolean;
+type BindApiFn = unsafe extern "C" fn(u32) -> EglBoolean;
+type ChooseConfigFn = unsafe extern "C" fn(
+    EglDisplay,
+    *const EglInt,
+    *mut EglConfig,
+    EglInt,
+    *mut EglInt,
+) -> EglBoolean;
+type CreateContextFn =
+    unsafe extern "C" fn(EglDisplay, EglConfig, EglContext, *const EglInt) -> EglContext;
+type DestroyContextFn = unsafe extern "C" fn(EglDisplay, EglContext) -> EglBoolean;
+type MakeCurrentFn =
+    unsafe extern "C" fn(EglDisplay, EglSurface, EglSurface, EglContext) -> EglBoolean;
+
+/// OpenGL context without any window or surface, rendering happens into framebuffer objects only.
+/// With Mesa this also works without a GPU (llvmpipe), e.g. on CI machines.
+pub struct OffscreenContext {
+    library: Library,
+    display: EglDisplay,
+    context: EglContext,
+}
+
+impl OffscreenContext {
+    pub fn new() -> Result<Self> {
+        // SAFETY: libEGL is a plain C library, all symbols are called with their documented
+        // signatures and the returned handles are checked before they are used.
+        unsafe {
+            let library = Library::new("libEGL.so.1")
+                .or_else(|_| Library::new("libEGL.so"))
+                .map_err(|e| anyhow!("Cannot load libEGL: {e}"))?;
+
+            let get_proc_address: GetProcAddressFn = *library.get(b"eglGetProcAddress\0")?;
+            let get_display: GetDisplayFn = *library.get(b"eglGetDisplay\0")?;
+            let initialize: InitializeFn = *library.get(b"eglInitialize\0")?;
+            let bind_api: BindApiFn = *library.get(b"eglBindAPI\0")?;
+            let choose_config: ChooseConfigFn = *library.get(b"eglChooseConfig\0")?;
+            let create_context: CreateContextFn = *library.get(b"eglCreateContext\0")?;
+            let make_current: MakeCurrentFn = *library.get(b"eglMakeCurrent\0")?;
+
+            // prefer the surfaceless platform, it doesn't need a running display server
+            let get_platform_display = get_proc_address(c"eglGetPlatformDisplayEXT".as_ptr());
+            let mut display = ptr::null_mut();
+            if !get_platform_display.is_null() {
+                let get_platform_display: GetPlatformDisplayFn =
+                    std::mem::transmute(get_platform_display);
+                display = get_platform_display(
+                    EGL_PLATFORM_SURFACELESS_MESA,
+                    ptr::null_mut(),
+                    ptr::null(),
+                );
+            }
+            if display.is_null() {
+                display = get_display(ptr::null_mut());
+            }
+            if display.is_null() || initialize(display, ptr::null_mut(), ptr::null_mut()) == 0 {
+                return Err(anyhow!("Cannot initialize EGL display"));
+            }
+
+            // desktop OpenGL first, GLES as fallback
+            let candidates: [(u32, EglInt, &[EglInt]); 2] = [
+                (
+                    EGL_OPENGL_API,
+                    EGL_OPENGL_BIT,
+                    &[
+                        EGL_CONTEXT_MAJOR_VERSION,
+                        3,
+                        EGL_CONTEXT_MINOR_VERSION,
+                        2,
+                        EGL_CONTEXT_OPENGL_PROFILE_MASK,
+                        EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                        EGL_NONE,
+                    ],
+                ),
+                (
+                    EGL_OPENGL_ES_API,
+                    EGL_OPENGL_ES3_BIT,
+                    &[EGL_CONTEXT_MAJOR_VERSION, 3, EGL_NONE],
+                ),
+            ];
+
+            for (api, renderable_type, context_attributes) in candidates {
+                if bind_api(api) == 0 {
+                    continue;
+                }
+
+                let config_attributes = [
+                    EGL_SURFACE_TYPE,
+                    0,
+                    EGL_RENDERABLE_TYPE,
+                    renderable_type,
+                    EGL_NONE,
+                ];
+                let mut config = ptr::null_mut();
+                let mut num_configs = 0;
+                if choose_config(
+                    display,
+                    config_attributes.as_ptr(),
+                    &mut config,
+                    1,
+                    &mut num_configs,
+                ) == 0
+                    || num_configs == 0
+                {
+                    continue;
+                }
+
+                let context = create_context(
+                    display,
+                    config,
+                    ptr::null_mut(),
+                    context_attributes.as_ptr(),
+                );
+                if context.is_null() {
+                    continue;
+                }
+
+                // surfaceless: no draw and read surface
+                if make_current(display, ptr::null_mut(), ptr::null_mut(), context) == 0 {
+                    let destroy_context: DestroyContextFn = *library.get(b"eglDestroyContext\0")?;
+                    destroy_context(display, context);
+                    continue;
+                }
+
+                return Ok(Self {
+                    library,
+                    display,
+                    context,
+                });
+            }
+
+            let terminate: TerminateFn = *library.get(b"eglTerminate\0")?;
+            terminate(display);
+            Err(anyhow!("Cannot create an offscreen OpenGL context"))
+        }
+    }
+
+    pub fn get_proc_address(&self, name: &str) -> *const c_void {
+        let Ok(name) = CString::new(name) else {
+            return ptr::null();
+        };
+        // SAFETY: see above, the context is current as long as self is alive
+        unsafe {
+            match self.library.get::<GetProcAddressFn>(b"eglGetProcAddress\0") {
+                Ok(get_proc_address) => get_proc_address(name.as_ptr()),
+                Err(_) => ptr::null(),
+            }
+        }
+    }
+}
+
+impl Drop for OffscreenContext {
+    fn drop(&mut self) {
+        // SAFETY: display and context were created by this library in new()
+        unsafe {
+            if let (Ok(make_current), Ok(destroy_context), Ok(terminate)) = (
+                self.library.get::<MakeCurrentFn>(b"eglMakeCurrent\0"),
+                self.library.get::<DestroyContextFn>(b"eglDestroyContext\0"),
+                self.library.get::<TerminateFn>(b"eglTerminate\0"),
+            ) {
+                make_current(
+                    self.display,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                );
+                destroy_context(self.display, self.context);
+                terminate(self.display);
+            }
+        }
+    }
+}
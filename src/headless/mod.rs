@@ -0,0 +1,253 @@
+use std::{
+    cell::RefCell,
+    fs,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use anyhow::{anyhow, Result};
+use femtovg::{renderer::OpenGl, Canvas};
+use gdk_pixbuf::Pixbuf;
+
+use crate::{
+    export,
+    femtovg_area::{self, FemtoVgAreaMut},
+    project::Project,
+    sketch_board::SketchBoard,
+    tools::{DrawableStore, Tools, ToolsManager},
+};
+
+mod egl;
+mod script;
+
+pub use script::apply;
+
+/// Renders the project without opening a window and writes it to the output file, like the save
+/// action does.
+pub fn run(mut project: Project) -> Result<()> {
+    let output_filename = SketchBoard::resolve_output_filename()
+        .ok_or(anyhow!("Headless mode needs an output filename"))?;
+    let format = if output_filename == "-" {
+        export::default_format()
+    } else {
+        export::format_for_filename(&output_filename)
+    }?;
+
+    let (tools, drawables) = restore(&mut project);
+    let crop = tools
+        .get_crop_tool()
+        .borrow()
+        .get_crop()
+        .map(|c| c.get_rectangle());
+
+    let data = if export::is_svg(&format) {
+        // svg doesn't need any rendering
+        export::svg(&project.background, crop, &drawables.borrow())?.into_bytes()
+    } else {
+        let image = render(project.background, &tools, drawables)?;
+        export::encode(&image, &format)?
+    };
+
+    if output_filename == "-" {
+        io::stdout().lock().write_all(&data)?;
+    } else {
+        fs::write(&output_filename, data)?;
+        println!("File saved to '{output_filename}'.");
+    }
+    Ok(())
+}
+
+/// Turns the drawables of the project into committed drawables of a new set of tools.
+fn restore(project: &mut Project) -> (ToolsManager, DrawableStore) {
+    let drawables: DrawableStore = Rc::new(RefCell::new(Vec::new()));
+    let tools = ToolsManager::new(drawables.clone(), project.background.clone());
+
+    drawables.borrow_mut().extend(
+        project
+            .drawables
+            .drain(..)
+            .map(|d| tools.restore_drawable(d)),
+    );
+    if let Some(crop) = project.crop {
+        tools.get_crop_tool().borrow_mut().set_crop(crop);
+    }
+
+    (tools, drawables)
+}
+
+fn render(background: Pixbuf, tools: &ToolsManager, drawables: DrawableStore) -> Result<Pixbuf> {
+    let context = egl::OffscreenContext::new()?;
+
+    // SAFETY: the context stays current until it is dropped, after the canvas
+    let renderer = unsafe { OpenGl::new_from_function(|s| context.get_proc_address(s)) }?;
+    let mut canvas = Canvas::new(renderer)?;
+    canvas.set_size(background.width() as u32, background.height() as u32, 1.0);
+    let fonts = femtovg_area::load_fonts(&mut canvas);
+
+    // the same rendering as for the save action in the window, without the on screen overlays
+    let mut area = FemtoVgAreaMut::new(
+        background,
+        tools.get_crop_tool(),
+        tools.get(&Tools::Pointer),
+        drawables,
+    );
+    let image = area.render_native_resolution(&mut canvas, fonts)?;
+
+    Ok(SketchBoard::image_to_pixbuf(image))
+}
+
+#[cfg(test)]
+mod tests {
+    use gdk_pixbuf::{glib::Bytes, prelude::PixbufLoaderExt, Colorspace, PixbufLoader};
+
+    use super::*;
+    use crate::math::Vec2D;
+
+    const SIZE: i32 = 64;
+
+    /// Random noise, every pixel is independent of its neighbours.
+    fn noise(width: i32, height: i32) -> Pixbuf {
+        let mut state = 0x2545_f491_u32;
+        let bytes: Vec<u8> = (0..width * height * 4)
+            .map(|i| {
+                if i % 4 == 3 {
+                    return 255;
+                }
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect();
+        Pixbuf::from_bytes(
+            &Bytes::from_owned(bytes),
+            Colorspace::Rgb,
+            true,
+            8,
+            width,
+            height,
+            width * 4,
+        )
+    }
+
+    /// Pearson correlation of the red channel of both images in the given square.
+    fn correlation(a: &Pixbuf, b: &Pixbuf, x: i32) -> f64 {
+        let channel = |image: &Pixbuf| -> Vec<f64> {
+            let bytes = image.read_pixel_bytes();
+            let (stride, channels) = (image.rowstride(), image.n_channels());
+            (0..SIZE)
+                .flat_map(|row| (x..x + SIZE).map(move |column| (row, column)))
+                .map(|(row, column)| bytes[(row * stride + column * channels) as usize] as f64)
+                .collect()
+        };
+        let (a, b) = (channel(a), channel(b));
+
+        let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+        let (mean_a, mean_b) = (mean(&a), mean(&b));
+        let covariance: f64 = a
+            .iter()
+            .zip(&b)
+            .map(|(a, b)| (a - mean_a) * (b - mean_b))
+            .sum();
+        let variance_a: f64 = a.iter().map(|a| (a - mean_a).powi(2)).sum();
+        let variance_b: f64 = b.iter().map(|b| (b - mean_b).powi(2)).sum();
+        if variance_b == 0.0 {
+            // uniform output, e.g. a black box
+            return 0.0;
+        }
+        covariance / (variance_a * variance_b).sqrt()
+    }
+
+    #[test]
+    fn script_adds_text_without_gtk() {
+        let mut project = Project::from_image(noise(SIZE, SIZE));
+        apply(
+            &mut project,
+            "text 10,20 \"first\\nsecond\" size=large\ncallout 5,5 30,40 \"note\"",
+        )
+        .unwrap();
+
+        let (_, drawables) = restore(&mut project);
+        let svgs: Vec<String> = drawables
+            .borrow()
+            .iter()
+            .map(|d| d.to_svg(0).unwrap_or_default())
+            .collect();
+        assert_eq!(svgs.len(), 2);
+        assert!(svgs[0].contains(">first<") && svgs[0].contains(">second<"));
+        assert!(svgs[1].contains(">note<"));
+    }
+
+    #[test]
+    fn redaction_removes_original_pixels() {
+        // CI installs Mesa and sets SATTY_REQUIRE_EGL, so that the test cannot be skipped there
+        if let Err(e) = egl::OffscreenContext::new() {
+            assert!(
+                std::env::var_os("SATTY_REQUIRE_EGL").is_none(),
+                "no offscreen OpenGL available: {e}"
+            );
+            eprintln!("skipping, no offscreen OpenGL available: {e}");
+            return;
+        }
+
+        let background = noise(SIZE * 4, SIZE);
+        let mut project = Project::from_image(background.clone());
+        apply(
+            &mut project,
+            "blur 0,0 64x64 size=large\npixelate 64,0 64x64 size=large\nblackbox 128,0 64x64",
+        )
+        .unwrap();
+
+        let (tools, drawables) = restore(&mut project);
+        let output = render(background.clone(), &tools, drawables).unwrap();
+        assert_eq!(output.width(), background.width());
+
+        // the last square is not redacted and has to match the original
+        let unredacted = correlation(&background, &output, SIZE * 3);
+        assert!(unredacted > 0.99, "unredacted correlation {unredacted}");
+
+        for (mode, x) in [("blur", 0), ("pixelate", SIZE), ("blackbox", SIZE * 2)] {
+            let redacted = correlation(&background, &output, x);
+            assert!(
+                redacted.abs() < 0.1,
+                "{mode} correlates with the original pixels: {redacted}"
+            );
+        }
+    }
+
+    #[test]
+    fn svg_embeds_redacted_and_cropped_background() {
+        let background = noise(SIZE * 5, SIZE);
+        let mut project = Project::from_image(background.clone());
+        apply(
+            &mut project,
+            "blur 0,0 64x64 size=large\npixelate 64,0 64x64 size=large\nblackbox 128,0 64x64",
+        )
+        .unwrap();
+
+        let (_, drawables) = restore(&mut project);
+        let crop = (Vec2D::zero(), Vec2D::new((SIZE * 4) as f32, SIZE as f32));
+        let svg = export::svg(&background, Some(crop), &drawables.borrow()).unwrap();
+
+        let (_, data) = svg
+            .split_once(r#"id="background""#)
+            .and_then(|(_, image)| image.split_once("base64,"))
+            .unwrap();
+        let png = glib::base64_decode(&data[..data.find('"').unwrap()]);
+        let loader = PixbufLoader::new();
+        loader.write(&png).unwrap();
+        loader.close().unwrap();
+        let embedded = loader.pixbuf().unwrap();
+        assert_eq!(embedded.width(), SIZE * 4, "the cropped area is embedded");
+
+        let unredacted = correlation(&background, &embedded, SIZE * 3);
+        assert!(unredacted > 0.99, "unredacted correlation {unredacted}");
+        for (mode, x) in [("blur", 0), ("pixelate", SIZE), ("blackbox", SIZE * 2)] {
+            let redacted = correlation(&background, &embedded, x);
+            assert!(
+                redacted.abs() < 0.1,
+                "{mode} correlates with the original pixels: {redacted}"
+            );
+        }
+    }
+}
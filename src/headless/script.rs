@@ -0,0 +1,681 @@
+use std::iter;
+
+use anyhow::{anyhow, Context, Result};
+use hex_color::HexColor;
+use relm4::gtk::gdk::{Key, ModifierType};
+
+use crate::{
+    configuration::APP_CONFIG,
+    math::Vec2D,
+    project::Project,
+    sketch_board::{
+        InputEvent, KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, TextEventMsg,
+    },
+    style::{Color, LineDash, Size, SizePreset, Style},
+    tools::{BlurModes, Highlighters, ToolEvent, ToolUpdateResult, Tools},
+};
+
+/// A single line of an annotation script.
+enum Command {
+    /// Changes the style of all following annotations.
+    Style(Style),
+    Crop((Vec2D, Vec2D)),
+    /// Input that is sent to a tool, as if the user was drawing the annotation.
+    Annotate {
+        tool: Tools,
+        style: Style,
+        events: Vec<InputEvent>,
+    },
+}
+
+struct Token {
+    text: String,
+    quoted: bool,
+}
+
+/// Adds the annotations of a script to the project, one command per line, e.g.
+/// `rect 10,10 200x100 color=#ff0000 size=large`.
+pub fn apply(project: &mut Project, script: &str) -> Result<()> {
+    let (tools, drawables) = super::restore(project);
+
+    let mut style = Style::default();
+    for (number, line) in script.lines().enumerate() {
+        let command = parse_line(line, style)
+            .with_context(|| format!("line {}: '{}'", number + 1, line.trim()))?;
+
+        match command {
+            None => (),
+            Some(Command::Style(s)) => style = s,
+            Some(Command::Crop(rect)) => tools.get_crop_tool().borrow_mut().set_crop(rect),
+            Some(Command::Annotate {
+                tool,
+                style,
+                events,
+            }) => {
+                let tool = tools.get(&tool);
+                let mut tool = tool.borrow_mut();
+                tool.handle_event(ToolEvent::StyleChanged(style));
+                tool.handle_event(ToolEvent::Activated);
+
+                let events = events
+                    .into_iter()
+                    .map(ToolEvent::Input)
+                    .chain(iter::once(ToolEvent::Deactivated));
+                for event in events {
+                    if let ToolUpdateResult::Commit(drawable) = tool.handle_event(event) {
+                        drawables.borrow_mut().push(drawable);
+                    }
+                }
+            }
+        }
+    }
+
+    project.drawables = drawables
+        .borrow()
+        .iter()
+        .filter_map(|d| d.to_data())
+        .collect();
+    project.crop = tools
+        .get_crop_tool()
+        .borrow()
+        .get_crop()
+        .map(|c| c.get_rectangle());
+    Ok(())
+}
+
+fn parse_line(line: &str, default_style: Style) -> Result<Option<Command>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let tokens = tokenize(line)?;
+    let (name, arguments) = tokens.split_first().ok_or(anyhow!("empty command"))?;
+
+    // options are the trailing `key=value` pairs, everything before is positional
+    let options_start = arguments
+        .iter()
+        .position(|t| !t.quoted && (t.text.contains('=') || t.text == "fill"))
+        .unwrap_or(arguments.len());
+    let (positional, options) = arguments.split_at(options_start);
+
+    let mut style = default_style;
+    for option in options {
+        apply_option(&mut style, option)?;
+    }
+
+    let command = match name.text.as_str() {
+        "style" => {
+            expect_arguments(positional, 0)?;
+            Command::Style(style)
+        }
+        "crop" => {
+            expect_arguments(positional, 2)?;
+            Command::Crop((parse_point(&positional[0])?, parse_size(&positional[1])?))
+        }
+        "rect" | "rectangle" | "ellipse" => {
+            expect_arguments(positional, 2)?;
+            let tool = if name.text == "ellipse" {
+                Tools::Ellipse
+            } else {
+                Tools::Rectangle
+            };
+            let pos = parse_point(&positional[0])?;
+            let size = parse_size(&positional[1])?;
+            Command::Annotate {
+                tool,
+                style,
+                events: drag(pos, &[pos + size], ModifierType::empty()),
+            }
+        }
+        "blur" | "pixelate" | "blackbox" => {
+            expect_arguments(positional, 2)?;
+            let mode = match name.text.as_str() {
+                "pixelate" => BlurModes::Pixelate,
+                "blackbox" => BlurModes::Blackbox,
+                _ => BlurModes::Gaussian,
+            };
+            // the modifier the user would hold for this mode
+            let modifier = [
+                ModifierType::empty(),
+                ModifierType::CONTROL_MASK,
+                ModifierType::SHIFT_MASK,
+            ]
+            .into_iter()
+            .find(|m| BlurModes::for_modifier(*m) == mode)
+            .unwrap_or(ModifierType::empty());
+
+            let pos = parse_point(&positional[0])?;
+            let size = parse_size(&positional[1])?;
+            Command::Annotate {
+                tool: Tools::Blur,
+                style,
+                events: drag(pos, &[pos + size], modifier),
+            }
+        }
+        "spotlight" => {
+            // `spotlight x,y wxh [ellipse]`, ctrl switches the tool to an ellipse
+            let modifier = match positional {
+                [_, _] => ModifierType::empty(),
+                [_, _, shape] if !shape.quoted && shape.text == "ellipse" => {
+                    ModifierType::CONTROL_MASK
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "expected a position, a size and optionally 'ellipse'"
+                    ))
+                }
+            };
+
+            let pos = parse_point(&positional[0])?;
+            let size = parse_size(&positional[1])?;
+            Command::Annotate {
+                tool: Tools::Spotlight,
+                style,
+                events: drag(pos, &[pos + size], modifier),
+            }
+        }
+        "magnify" => {
+            // `magnify x,y wxh lens_x,lens_y [ellipse]`, the lens is centered on its position
+            let modifier = match positional {
+                [_, _, _] => ModifierType::empty(),
+                [_, _, _, shape] if !shape.quoted && shape.text == "ellipse" => {
+                    ModifierType::CONTROL_MASK
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "expected a position, a size, the lens position and optionally 'ellipse'"
+                    ))
+                }
+            };
+
+            let pos = parse_point(&positional[0])?;
+            let size = parse_size(&positional[1])?;
+            let lens = parse_point(&positional[2])?;
+            Command::Annotate {
+                tool: Tools::Magnifier,
+                style,
+                events: drag(pos, &[pos + size], modifier)
+                    .into_iter()
+                    .chain([
+                        mouse(MouseEventType::Click, lens, ModifierType::empty()),
+                        InputEvent::Key(KeyEventMsg {
+                            key: Key::Return,
+                            code: 0,
+                            modifier: ModifierType::empty(),
+                        }),
+                    ])
+                    .collect(),
+            }
+        }
+        "line" => {
+            expect_arguments(positional, 2)?;
+            let start = parse_point(&positional[0])?;
+            let end = parse_point(&positional[1])?;
+            Command::Annotate {
+                tool: Tools::Line,
+                style,
+                events: drag(start, &[end], ModifierType::empty()),
+            }
+        }
+        "arrow" => {
+            // `arrow start end [through] [double]`, the shaft bends through the third point
+            let (points, double) = match positional.split_last() {
+                Some((last, points)) if !last.quoted && last.text == "double" => (points, true),
+                _ => (positional, false),
+            };
+            let points = parse_points(points)?;
+            if !(2..=3).contains(&points.len()) {
+                return Err(anyhow!("expected 2 or 3 points"));
+            }
+
+            // ctrl adds the second head, alt bends the shaft
+            let modifier = if double {
+                ModifierType::CONTROL_MASK
+            } else {
+                ModifierType::empty()
+            };
+            let start = points[0];
+            let mut events = drag(start, &points[1..2], modifier);
+            if let Some(through) = points.get(2) {
+                // the end is placed first, then the drag continues with alt held
+                events.pop();
+                events.push(mouse(
+                    MouseEventType::UpdateDrag,
+                    points[1] - start,
+                    modifier,
+                ));
+                events.push(mouse(
+                    MouseEventType::EndDrag,
+                    *through - start,
+                    modifier | ModifierType::ALT_MASK,
+                ));
+            }
+            Command::Annotate {
+                tool: Tools::Arrow,
+                style,
+                events,
+            }
+        }
+        "highlight" => {
+            // a rectangle is a block highlight, a list of points a freehand highlight
+            let block = positional.len() == 2 && positional[1].text.contains('x');
+            let primary_block = APP_CONFIG.read().primary_highlighter() == Highlighters::Block;
+            let modifier = if block == primary_block {
+                ModifierType::empty()
+            } else {
+                ModifierType::CONTROL_MASK
+            };
+
+            let start = parse_point(positional.first().ok_or(anyhow!("missing position"))?)?;
+            let points = if block {
+                vec![start + parse_size(&positional[1])?]
+            } else {
+                parse_points(&positional[1..])?
+            };
+            Command::Annotate {
+                tool: Tools::Highlight,
+                style,
+                events: drag(start, &points, modifier),
+            }
+        }
+        "brush" => {
+            let start = parse_point(positional.first().ok_or(anyhow!("missing position"))?)?;
+            let points = parse_points(&positional[1..])?;
+
+            // the brush starts a new stroke on click
+            let events = iter::once(mouse(MouseEventType::Click, start, ModifierType::empty()))
+                .chain(drag(start, &points, ModifierType::empty()))
+                .collect();
+            Command::Annotate {
+                tool: Tools::Brush,
+                style,
+                events,
+            }
+        }
+        "polygon" | "polyline" => {
+            let points = parse_points(positional)?;
+            if points.len() < 2 {
+                return Err(anyhow!("expected at least 2 points"));
+            }
+
+            // a click per vertex, enter closes the polygon and ctrl+enter keeps it open
+            let modifier = if name.text == "polyline" {
+                ModifierType::CONTROL_MASK
+            } else {
+                ModifierType::empty()
+            };
+            let events = points
+                .into_iter()
+                .map(|p| mouse(MouseEventType::Click, p, ModifierType::empty()))
+                .chain(iter::once(InputEvent::Key(KeyEventMsg {
+                    key: Key::Return,
+                    code: 0,
+                    modifier,
+                })))
+                .collect();
+            Command::Annotate {
+                tool: Tools::Polygon,
+                style,
+                events,
+            }
+        }
+        "marker" => {
+            expect_arguments(positional, 1)?;
+            Command::Annotate {
+                tool: Tools::Marker,
+                style,
+                events: vec![mouse(
+                    MouseEventType::Click,
+                    parse_point(&positional[0])?,
+                    ModifierType::empty(),
+                )],
+            }
+        }
+        "callout" => {
+            expect_arguments(positional, 3)?;
+            let anchor = parse_point(&positional[0])?;
+            let text_pos = parse_point(&positional[1])?;
+            Command::Annotate {
+                tool: Tools::Callout,
+                style,
+                events: drag(anchor, &[text_pos], ModifierType::empty())
+                    .into_iter()
+                    .chain([
+                        InputEvent::Text(TextEventMsg::Commit(positional[2].text.clone())),
+                        InputEvent::Key(KeyEventMsg {
+                            key: Key::Return,
+                            code: 0,
+                            modifier: ModifierType::empty(),
+                        }),
+                    ])
+                    .collect(),
+            }
+        }
+        "text" => {
+            expect_arguments(positional, 2)?;
+            Command::Annotate {
+                tool: Tools::Text,
+                style,
+                events: vec![
+                    mouse(
+                        MouseEventType::Click,
+                        parse_point(&positional[0])?,
+                        ModifierType::empty(),
+                    ),
+                    InputEvent::Text(TextEventMsg::Commit(positional[1].text.clone())),
+                    InputEvent::Key(KeyEventMsg {
+                        key: Key::Return,
+                        code: 0,
+                        modifier: ModifierType::empty(),
+                    }),
+                ],
+            }
+        }
+        other => return Err(anyhow!("unknown command '{other}'")),
+    };
+
+    Ok(Some(command))
+}
+
+/// Splits a line at whitespace, double quoted strings can contain spaces and the escapes
+/// `\n`, `\"` and `\\`.
+fn tokenize(line: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+
+        if c == '"' {
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => text.push('\n'),
+                        Some(c) => text.push(c),
+                        None => return Err(anyhow!("unterminated string")),
+                    },
+                    Some(c) => text.push(c),
+                    None => return Err(anyhow!("unterminated string")),
+                }
+            }
+            tokens.push(Token { text, quoted: true });
+        } else {
+            let mut text = String::from(c);
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                text.push(c);
+            }
+            tokens.push(Token {
+                text,
+                quoted: false,
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn apply_option(style: &mut Style, option: &Token) -> Result<()> {
+    let (key, value) = option
+        .text
+        .split_once('=')
+        .unwrap_or((option.text.as_str(), "true"));
+
+    match key {
+        "color" => {
+            style.color = HexColor::parse(value)
+                .map(Color::from)
+                .map_err(|e| anyhow!("invalid color '{value}': {e}"))?
+        }
+        "size" => {
+            style.size = match value {
+                "small" => SizePreset::Small.into(),
+                "medium" => SizePreset::Medium.into(),
+                "large" => SizePreset::Large.into(),
+                _ => value
+                    .parse()
+                    .map(Size::new)
+                    .map_err(|_| anyhow!("invalid size '{value}'"))?,
+            }
+        }
+        "dash" => {
+            style.dash = match value {
+                "solid" => LineDash::Solid,
+                "dashed" => LineDash::Dashed,
+                "dotted" => LineDash::Dotted,
+                _ => return Err(anyhow!("invalid dash '{value}'")),
+            }
+        }
+        "fill" => match value.parse() {
+            Ok(fill) => style.fill = fill,
+            // a color fills with it and keeps the outline in the stroke color
+            Err(_) => {
+                style.fill = true;
+                style.fill_color = Some(
+                    HexColor::parse(value)
+                        .map(Color::from)
+                        .map_err(|_| anyhow!("invalid fill '{value}'"))?,
+                );
+            }
+        },
+        "fill-opacity" => {
+            style.fill_opacity = value
+                .parse::<f32>()
+                .map_err(|_| anyhow!("invalid fill opacity '{value}'"))?
+                .clamp(0.0, 1.0)
+        }
+        "shadow" => {
+            style.shadow = value
+                .parse()
+                .map_err(|_| anyhow!("invalid shadow '{value}'"))?
+        }
+        "outline" => {
+            style.outline = value
+                .parse()
+                .map_err(|_| anyhow!("invalid outline '{value}'"))?
+        }
+        _ => return Err(anyhow!("unknown option '{key}'")),
+    }
+    Ok(())
+}
+
+fn expect_arguments(arguments: &[Token], count: usize) -> Result<()> {
+    if arguments.len() != count {
+        return Err(anyhow!(
+            "expected {count} arguments, got {}",
+            arguments.len()
+        ));
+    }
+    Ok(())
+}
+
+fn parse_pair(token: &Token, separator: char) -> Result<Vec2D> {
+    let (x, y) = token
+        .text
+        .split_once(separator)
+        .ok_or(anyhow!("expected '{}'", token.text))?;
+    let x = x
+        .parse()
+        .map_err(|_| anyhow!("invalid number '{x}' in '{}'", token.text))?;
+    let y = y
+        .parse()
+        .map_err(|_| anyhow!("invalid number '{y}' in '{}'", token.text))?;
+    Ok(Vec2D::new(x, y))
+}
+
+/// `x,y` in image coordinates
+fn parse_point(token: &Token) -> Result<Vec2D> {
+    parse_pair(token, ',').with_context(|| "position needs to be 'x,y'")
+}
+
+/// `widthxheight` in pixels
+fn parse_size(token: &Token) -> Result<Vec2D> {
+    parse_pair(token, 'x').with_context(|| "size needs to be 'widthxheight'")
+}
+
+fn parse_points(tokens: &[Token]) -> Result<Vec<Vec2D>> {
+    if tokens.is_empty() {
+        return Err(anyhow!("expected at least two points"));
+    }
+    tokens.iter().map(parse_point).collect()
+}
+
+fn mouse(type_: MouseEventType, pos: Vec2D, modifier: ModifierType) -> InputEvent {
+    InputEvent::Mouse(MouseEventMsg {
+        type_,
+        button: MouseButton::Primary,
+        modifier,
+        pos,
+    })
+}
+
+/// Drag from `start` through `points`, like the drag gesture the positions of updates are
+/// relative to the start.
+fn drag(start: Vec2D, points: &[Vec2D], modifier: ModifierType) -> Vec<InputEvent> {
+    let mut events = vec![mouse(MouseEventType::BeginDrag, start, modifier)];
+    if let Some((last, updates)) = points.split_last() {
+        events.extend(
+            updates
+                .iter()
+                .map(|p| mouse(MouseEventType::UpdateDrag, *p - start, modifier)),
+        );
+        events.push(mouse(MouseEventType::EndDrag, *last - start, modifier));
+    }
+    events
+}
+
+#[cfg(test)]
+mod tests {
+    use gdk_pixbuf::{Colorspace, Pixbuf};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn tokens(line: &str) -> Vec<(String, bool)> {
+        tokenize(line)
+            .unwrap()
+            .into_iter()
+            .map(|t| (t.text, t.quoted))
+            .collect()
+    }
+
+    fn drawables(script: &str) -> Vec<Value> {
+        let background = Pixbuf::new(Colorspace::Rgb, true, 8, 200, 200).unwrap();
+        let mut project = Project::from_image(background);
+        apply(&mut project, script).unwrap();
+        project
+            .drawables
+            .iter()
+            .map(|d| serde_json::to_value(d).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn quoted_text_is_one_token() {
+        assert_eq!(
+            tokens(r#"text 1,2 "say \"hi\"\nback\\slash"  size=large"#),
+            [
+                ("text".to_string(), false),
+                ("1,2".to_string(), false),
+                ("say \"hi\"\nback\\slash".to_string(), true),
+                ("size=large".to_string(), false),
+            ]
+        );
+        assert_eq!(
+            tokens(r#""" x"#),
+            [(String::new(), true), ("x".to_string(), false)]
+        );
+        assert!(tokenize(r#"text 1,2 "open"#).is_err());
+        assert!(tokenize(r#"text 1,2 "escape\"#).is_err());
+    }
+
+    #[test]
+    fn bad_options_are_rejected() {
+        for line in [
+            "rect 1,2 3x4 size=huge",
+            "rect 1,2 3x4 color=nope",
+            "rect 1,2 3x4 dash=wavy",
+            "rect 1,2 3x4 fill=nope",
+            "rect 1,2 3x4 shadow=maybe",
+            "rect 1,2 3x4 fill-opacity=half",
+            "rect 1,2 3x4 unknown=1",
+        ] {
+            assert!(
+                parse_line(line, Style::default()).is_err(),
+                "'{line}' was accepted"
+            );
+        }
+
+        let mut style = Style::default();
+        for option in ["size=2.5", "dash=dotted", "fill=#00ff00", "fill-opacity=2"] {
+            apply_option(
+                &mut style,
+                &Token {
+                    text: option.to_string(),
+                    quoted: false,
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(style.size, Size::new(2.5));
+        assert_eq!(style.dash, LineDash::Dotted);
+        assert!(style.fill);
+        assert_eq!(style.fill_color, Some(Color::new(0, 255, 0, 255)));
+        assert_eq!(style.fill_opacity, 1.0);
+    }
+
+    #[test]
+    fn bad_points_and_arguments_are_rejected() {
+        for line in [
+            "rect 1 3x4",
+            "rect 1,2 3,4",
+            "rect a,2 3x4",
+            "rect 1,2",
+            "line 1,2 3,4 5,6",
+            "arrow 1,2",
+            "arrow 1,2 3,4 5,6 7,8",
+            "polygon 1,2",
+            "text 1,2",
+            "callout 1,2 3,4",
+            "frobnicate 1,2",
+        ] {
+            assert!(
+                parse_line(line, Style::default()).is_err(),
+                "'{line}' was accepted"
+            );
+        }
+        assert!(parse_points(&[]).is_err());
+    }
+
+    #[test]
+    fn commands_create_drawables() {
+        let drawables = drawables(
+            "rect 10,20 30x40 color=#ff0000\n\
+             arrow 10,10 110,10 60,60 double\n\
+             text 5,6 \"hello\"",
+        );
+        assert_eq!(drawables.len(), 3);
+
+        let rect = &drawables[0];
+        assert_eq!(rect["type"], "rectangle");
+        assert_eq!(rect["data"]["top_left"], json!({"x": 10.0, "y": 20.0}));
+        assert_eq!(rect["data"]["size"], json!({"x": 30.0, "y": 40.0}));
+
+        let arrow = &drawables[1];
+        assert_eq!(arrow["type"], "arrow");
+        assert_eq!(arrow["data"]["start"], json!({"x": 10.0, "y": 10.0}));
+        assert_eq!(arrow["data"]["end"], json!({"x": 110.0, "y": 10.0}));
+        assert_eq!(arrow["data"]["double"], true);
+        // the middle of the bent shaft passes through the third point
+        assert_eq!(arrow["data"]["control"], json!({"x": 60.0, "y": 110.0}));
+
+        let text = &drawables[2];
+        assert_eq!(text["type"], "text");
+        assert_eq!(text["data"]["pos"], json!({"x": 5.0, "y": 6.0}));
+        assert_eq!(text["data"]["text"], "hello");
+    }
+}
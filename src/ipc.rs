@@ -0,0 +1,223 @@
+use std::{fs, os::unix::fs::FileTypeExt, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use gdk_pixbuf::gio::{
+    self,
+    prelude::{
+        DataInputStreamExtManual, IOStreamExt, OutputStreamExtManual, SocketListenerExt,
+        SocketServiceExt,
+    },
+    SocketConnection, SocketProtocol, SocketService, SocketType, UnixSocketAddress,
+};
+use hex_color::HexColor;
+use relm4::Sender;
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::Action,
+    tools::{DrawableData, Tools},
+};
+
+/// A command sent by another process, one JSON object per line, e.g.
+/// `{"command": "select-tool", "tool": "arrow"}`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum IpcCommand {
+    SelectTool { tool: Tools },
+    SetColor { color: HexColor },
+    LoadImage { path: String },
+    Save,
+    Copy,
+    Undo,
+    Redo,
+    ListAnnotations,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct IpcResponse {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    annotations: Option<Vec<DrawableData>>,
+}
+
+impl IpcResponse {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn annotations(annotations: Vec<DrawableData>) -> Self {
+        Self {
+            ok: true,
+            annotations: Some(annotations),
+            ..Default::default()
+        }
+    }
+}
+
+/// A command together with the way back to the client that sent it.
+#[derive(Debug, Clone)]
+pub struct IpcRequest {
+    pub command: IpcCommand,
+    responder: Sender<IpcResponse>,
+}
+
+impl IpcRequest {
+    pub fn respond(self, response: IpcResponse) {
+        self.responder.emit(response);
+    }
+}
+
+/// Requests that wait for an action of the next render, e.g. saving.
+#[derive(Debug, Default)]
+pub struct PendingRequests(Vec<(Action, IpcRequest)>);
+
+impl PendingRequests {
+    pub fn push(&mut self, action: Action, request: IpcRequest) {
+        self.0.push((action, request));
+    }
+
+    /// Answers the request that waits longest for `action`, if any.
+    pub fn respond(&mut self, action: Action, result: &Result<()>) {
+        if let Some(index) = self.0.iter().position(|(a, _)| *a == action) {
+            let (_, request) = self.0.remove(index);
+            request.respond(match result {
+                Ok(()) => IpcResponse::ok(),
+                Err(e) => IpcResponse::error(e.to_string()),
+            });
+        }
+    }
+}
+
+/// Unix socket that passes the commands of all clients to `handler`, removes the socket file when dropped.
+pub struct IpcServer {
+    service: SocketService,
+    path: PathBuf,
+}
+
+impl IpcServer {
+    pub fn listen(path: &str, handler: impl Fn(IpcRequest) + Clone + 'static) -> Result<Self> {
+        let path = PathBuf::from(path);
+
+        // a socket left behind by a previous instance would make the bind fail
+        if fs::symlink_metadata(&path).is_ok_and(|m| m.file_type().is_socket()) {
+            fs::remove_file(&path)?;
+        }
+
+        let service = SocketService::new();
+        service
+            .add_address(
+                &UnixSocketAddress::new(&path),
+                SocketType::Stream,
+                SocketProtocol::Default,
+                None::<&gio::glib::Object>,
+            )
+            .map_err(|e| anyhow!("Cannot listen on '{}': {e}", path.display()))?;
+
+        service.connect_incoming(move |_, connection, _| {
+            let connection = connection.clone();
+            let handler = handler.clone();
+            relm4::spawn_local(async move {
+                if let Err(e) = serve(connection, handler).await {
+                    eprintln!("Error in IPC connection: {e}");
+                }
+            });
+            true
+        });
+        service.start();
+
+        Ok(Self { service, path })
+    }
+}
+
+impl Drop for IpcServer {
+    fn drop(&mut self) {
+        self.service.stop();
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Answers the commands of one client, in the order they arrive.
+async fn serve(connection: SocketConnection, handler: impl Fn(IpcRequest)) -> Result<()> {
+    let input = gio::DataInputStream::new(&connection.input_stream());
+    let output = connection.output_stream();
+
+    while let Some(line) = input
+        .read_line_utf8_future(gio::glib::Priority::DEFAULT)
+        .await?
+    {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<IpcCommand>(&line) {
+            Ok(command) => {
+                let (responder, receiver) = relm4::channel();
+                handler(IpcRequest { command, responder });
+                receiver
+                    .recv()
+                    .await
+                    .unwrap_or_else(|| IpcResponse::error("Command was not handled"))
+            }
+            Err(e) => IpcResponse::error(format!("Invalid command: {e}")),
+        };
+
+        let mut data = serde_json::to_vec(&response)?;
+        data.push(b'\n');
+        let (_, _, error) = output
+            .write_all_future(data, gio::glib::Priority::DEFAULT)
+            .await
+            .map_err(|(_, e)| e)?;
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use relm4::Receiver;
+
+    use super::*;
+
+    fn request(command: IpcCommand) -> (IpcRequest, Receiver<IpcResponse>) {
+        let (responder, receiver) = relm4::channel();
+        (IpcRequest { command, responder }, receiver)
+    }
+
+    #[test]
+    fn requests_queued_before_one_render_are_all_answered() {
+        let mut pending = PendingRequests::default();
+        let (first_save, first_save_response) = request(IpcCommand::Save);
+        let (copy, copy_response) = request(IpcCommand::Copy);
+        let (second_save, second_save_response) = request(IpcCommand::Save);
+        pending.push(Action::SaveToFile, first_save);
+        pending.push(Action::SaveToClipboard, copy);
+        pending.push(Action::SaveToFile, second_save);
+
+        // the render handles the actions of all three requests
+        pending.respond(Action::SaveToFile, &Ok(()));
+        pending.respond(Action::SaveToClipboard, &Err(anyhow!("no clipboard")));
+        pending.respond(Action::SaveToFile, &Ok(()));
+
+        assert!(pending.0.is_empty());
+        assert!(first_save_response.recv_sync().unwrap().ok);
+        assert!(second_save_response.recv_sync().unwrap().ok);
+        let copied = copy_response.recv_sync().unwrap();
+        assert!(!copied.ok);
+        assert_eq!(copied.error.as_deref(), Some("no clipboard"));
+    }
+}
@@ -5,7 +5,7 @@ use std::{io, time::Duration};
 
 use configuration::{Configuration, APP_CONFIG};
 use gdk_pixbuf::gio::ApplicationFlags;
-use gdk_pixbuf::{Pixbuf, PixbufLoader};
+use gdk_pixbuf::PixbufLoader;
 use gtk::prelude::*;
 
 use relm4::gtk::gdk::Rectangle;
@@ -18,23 +18,30 @@ use relm4::{
 use anyhow::{anyhow, Context, Result};
 
 use sketch_board::SketchBoardOutput;
+use ui::history_panel::{HistoryPanel, HistoryPanelInput};
 use ui::toolbars::{StyleToolbar, StyleToolbarInput, ToolsToolbar, ToolsToolbarInput};
 use xdg::BaseDirectories;
 
+mod accelerator;
 mod command_line;
 mod configuration;
+mod export;
 mod femtovg_area;
+mod headless;
 mod icons;
 mod ime;
+mod ipc;
 mod math;
 mod notification;
+mod project;
 mod sketch_board;
 mod style;
 mod tools;
 mod ui;
 
+use crate::project::Project;
 use crate::sketch_board::{SketchBoard, SketchBoardInput};
-use crate::tools::Tools;
+use crate::tools::{History, Tools};
 
 pub static START_TIME: LazyLock<chrono::DateTime<chrono::Local>> =
     LazyLock::new(chrono::Local::now);
@@ -56,6 +63,7 @@ struct App {
     sketch_board: Controller<SketchBoard>,
     tools_toolbar: Controller<ToolsToolbar>,
     style_toolbar: Controller<StyleToolbar>,
+    history_panel: Controller<HistoryPanel>,
 }
 
 #[derive(Debug)]
@@ -64,6 +72,8 @@ enum AppInput {
     SetToolbarsDisplay(bool),
     ToggleToolbarsDisplay,
     ToolSwitchShortcut(Tools),
+    StyleShortcut(StyleToolbarInput),
+    HistoryChanged(History),
 }
 
 #[derive(Debug)]
@@ -153,6 +163,7 @@ impl App {
             }
             .toolbar-bottom {border-radius: 6px 6px 0px 0px;}
             .toolbar-top {border-radius: 0px 0px 6px 6px;}
+            .toolbar-right {border-radius: 6px 0px 0px 6px;}
             ",
         );
         if let Some(overrides) = read_css_overrides() {
@@ -169,7 +180,7 @@ impl App {
 
 #[relm4::component]
 impl Component for App {
-    type Init = Pixbuf;
+    type Init = Project;
     type Input = AppInput;
     type Output = ();
     type CommandOutput = AppCommandOutput;
@@ -190,6 +201,8 @@ impl Component for App {
 
                 add_overlay = model.style_toolbar.widget(),
 
+                add_overlay = model.history_panel.widget(),
+
                 model.sketch_board.widget(),
             }
         }
@@ -205,6 +218,9 @@ impl Component for App {
                 self.style_toolbar
                     .sender()
                     .emit(StyleToolbarInput::SetVisibility(visible));
+                self.history_panel
+                    .sender()
+                    .emit(HistoryPanelInput::SetVisibility(visible));
             }
             AppInput::ToggleToolbarsDisplay => {
                 self.tools_toolbar
@@ -213,12 +229,20 @@ impl Component for App {
                 self.style_toolbar
                     .sender()
                     .emit(StyleToolbarInput::ToggleVisibility);
+                self.history_panel
+                    .sender()
+                    .emit(HistoryPanelInput::ToggleVisibility);
             }
             AppInput::ToolSwitchShortcut(tool) => {
                 self.tools_toolbar
                     .sender()
                     .emit(ToolsToolbarInput::SwitchSelectedTool(tool));
             }
+            AppInput::StyleShortcut(input) => self.style_toolbar.sender().emit(input),
+            AppInput::HistoryChanged(history) => self
+                .history_panel
+                .sender()
+                .emit(HistoryPanelInput::Update(history)),
         }
     }
 
@@ -234,23 +258,25 @@ impl Component for App {
     }
 
     fn init(
-        image: Self::Init,
+        project: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         Self::apply_style();
 
-        let image_dimensions = (image.width(), image.height());
+        let image_dimensions = (project.background.width(), project.background.height());
 
         // SketchBoard
         let sketch_board =
             SketchBoard::builder()
-                .launch(image)
+                .launch(project)
                 .forward(sender.input_sender(), |t| match t {
                     SketchBoardOutput::ToggleToolbarsDisplay => AppInput::ToggleToolbarsDisplay,
                     SketchBoardOutput::ToolSwitchShortcut(tool) => {
                         AppInput::ToolSwitchShortcut(tool)
                     }
+                    SketchBoardOutput::StyleShortcut(input) => AppInput::StyleShortcut(input),
+                    SketchBoardOutput::HistoryChanged(history) => AppInput::HistoryChanged(history),
                 });
 
         // Toolbars
@@ -262,11 +288,16 @@ impl Component for App {
             .launch(())
             .forward(sketch_board.sender(), SketchBoardInput::ToolbarEvent);
 
+        let history_panel = HistoryPanel::builder()
+            .launch(())
+            .forward(sketch_board.sender(), SketchBoardInput::ToolbarEvent);
+
         // Model
         let model = App {
             sketch_board,
             tools_toolbar,
             style_toolbar,
+            history_panel,
             image_dimensions,
         };
 
@@ -350,19 +381,31 @@ fn run_satty() -> Result<()> {
 
     generate_profile_output!("loading image");
     // load input image
-    let image = if config.input_filename() == "-" {
+    let mut project = if config.input_filename() == "-" {
         let mut buf = Vec::<u8>::new();
         io::stdin().lock().read_to_end(&mut buf)?;
         let pb_loader = PixbufLoader::new();
         pb_loader.write(&buf)?;
         pb_loader.close()?;
-        pb_loader
-            .pixbuf()
-            .ok_or(anyhow!("Conversion to Pixbuf failed"))?
+        Project::from_image(
+            pb_loader
+                .pixbuf()
+                .ok_or(anyhow!("Conversion to Pixbuf failed"))?,
+        )
     } else {
-        Pixbuf::from_file(config.input_filename()).context("couldn't load image")?
+        Project::open(config.input_filename())?
     };
 
+    if let Some(script) = config.apply_script() {
+        let script = fs::read_to_string(script).context("couldn't read script")?;
+        headless::apply(&mut project, &script).context("couldn't apply script")?;
+        generate_profile_output!("script applied");
+    }
+
+    if config.headless() {
+        return headless::run(project);
+    }
+
     generate_profile_output!("image loaded, starting gui");
     // start GUI
     let app = relm4::main_application();
@@ -375,7 +418,7 @@ fn run_satty() -> Result<()> {
         icons::icon_names::GRESOURCE_BYTES,
         icons::icon_names::RESOURCE_PREFIX,
     );
-    app.run::<App>(image);
+    app.run::<App>(project);
     Ok(())
 }
 
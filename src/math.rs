@@ -4,7 +4,9 @@ use std::{
     ops::{Add, AddAssign, Mul, Sub, SubAssign},
 };
 
-#[derive(Default, Debug, Copy, Clone, PartialEq)]
+use serde_derive::{Deserialize, Serialize};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Vec2D {
     pub x: f32,
     pub y: f32,
@@ -214,3 +216,92 @@ pub fn rect_round(rect: (Vec2D, Vec2D)) -> (Vec2D, Vec2D) {
 
     (pos, size)
 }
+
+pub fn rect_contains(rect: (Vec2D, Vec2D), point: Vec2D, margin: f32) -> bool {
+    let (pos, size) = rect_ensure_positive_size(rect.0, rect.1);
+
+    pos.x - margin <= point.x
+        && point.x <= pos.x + size.x + margin
+        && pos.y - margin <= point.y
+        && point.y <= pos.y + size.y + margin
+}
+
+/// Whole pixels touched by `rect`, as `(x, y, width, height)` within an image of the given
+/// dimensions. None if nothing of the image is covered.
+pub fn pixel_region(rect: (Vec2D, Vec2D), width: i32, height: i32) -> Option<(i32, i32, i32, i32)> {
+    let (pos, size) = rect_ensure_positive_size(rect.0, rect.1);
+    let x = (pos.x.floor() as i32).clamp(0, width);
+    let y = (pos.y.floor() as i32).clamp(0, height);
+    let right = ((pos.x + size.x).ceil() as i32).clamp(0, width);
+    let bottom = ((pos.y + size.y).ceil() as i32).clamp(0, height);
+    if right <= x || bottom <= y {
+        return None;
+    }
+    Some((x, y, right - x, bottom - y))
+}
+
+/// Maps a point from the coordinate space of the rectangle `from` to the rectangle `to`.
+/// Degenerated source dimensions keep the offset instead of scaling it.
+pub fn rect_map_point(point: Vec2D, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) -> Vec2D {
+    let scale_x = if from.1.x.abs() > f32::EPSILON {
+        to.1.x / from.1.x
+    } else {
+        1.0
+    };
+    let scale_y = if from.1.y.abs() > f32::EPSILON {
+        to.1.y / from.1.y
+    } else {
+        1.0
+    };
+
+    Vec2D::new(
+        to.0.x + (point.x - from.0.x) * scale_x,
+        to.0.y + (point.y - from.0.y) * scale_y,
+    )
+}
+
+/// Computes the bounding box (position and size) of a set of points.
+pub fn points_bounding_box(points: impl IntoIterator<Item = Vec2D>) -> Option<(Vec2D, Vec2D)> {
+    let mut points = points.into_iter();
+    let first = points.next()?;
+    let (min, max) = points.fold((first, first), |(min, max), p| {
+        (
+            Vec2D::new(min.x.min(p.x), min.y.min(p.y)),
+            Vec2D::new(max.x.max(p.x), max.y.max(p.y)),
+        )
+    });
+
+    Some((min, max - min))
+}
+
+pub fn distance_to_segment(point: Vec2D, start: Vec2D, end: Vec2D) -> f32 {
+    let segment = end - start;
+    let length2 = segment.norm2();
+    if length2 < f32::EPSILON {
+        return point.distance_to(&start);
+    }
+
+    let offset = point - start;
+    let t = ((offset.x * segment.x + offset.y * segment.y) / length2).clamp(0.0, 1.0);
+    point.distance_to(&(start + segment * t))
+}
+
+/// Even-odd test whether `point` lies inside the closed polygon through `points`.
+pub fn polygon_contains(points: &[Vec2D], point: Vec2D) -> bool {
+    let mut inside = false;
+    let mut previous = match points.last() {
+        Some(p) => *p,
+        None => return false,
+    };
+    for current in points {
+        if (current.y > point.y) != (previous.y > point.y)
+            && point.x
+                < (previous.x - current.x) * (point.y - current.y) / (previous.y - current.y)
+                    + current.x
+        {
+            inside = !inside;
+        }
+        previous = *current;
+    }
+    inside
+}
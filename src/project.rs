@@ -0,0 +1,102 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Context, Result};
+use gdk_pixbuf::{prelude::*, Pixbuf, PixbufLoader};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    math::Vec2D,
+    tools::{Drawable, DrawableData},
+};
+
+pub const PROJECT_EXTENSION: &str = "satty";
+const PROJECT_VERSION: u32 = 1;
+
+/// Everything needed to continue editing: the original image and all annotations.
+pub struct Project {
+    pub background: Pixbuf,
+    pub crop: Option<(Vec2D, Vec2D)>,
+    pub drawables: Vec<DrawableData>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProjectFile {
+    version: u32,
+    // base64 encoded png
+    background: String,
+    crop: Option<(Vec2D, Vec2D)>,
+    #[serde(default)]
+    drawables: Vec<DrawableData>,
+}
+
+impl Project {
+    pub fn from_image(background: Pixbuf) -> Self {
+        Self {
+            background,
+            crop: None,
+            drawables: Vec::new(),
+        }
+    }
+
+    /// Opens a project file, or any image as a new project.
+    pub fn open(filename: &str) -> Result<Self> {
+        if Self::is_project_file(filename) {
+            Self::load(filename)
+        } else {
+            Ok(Self::from_image(
+                Pixbuf::from_file(filename).context("couldn't load image")?,
+            ))
+        }
+    }
+
+    pub fn is_project_file(filename: &str) -> bool {
+        Path::new(filename)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(PROJECT_EXTENSION))
+    }
+
+    pub fn load(filename: &str) -> Result<Self> {
+        let content = fs::read_to_string(filename).context("couldn't read project file")?;
+        let file: ProjectFile = toml::from_str(&content).context("couldn't parse project file")?;
+
+        if file.version > PROJECT_VERSION {
+            return Err(anyhow!(
+                "project file version {} is not supported, update Satty",
+                file.version
+            ));
+        }
+
+        let data = glib::base64_decode(&file.background);
+        let loader = PixbufLoader::new();
+        loader.write(&data)?;
+        loader.close()?;
+        let background = loader
+            .pixbuf()
+            .ok_or(anyhow!("Conversion of project background to Pixbuf failed"))?;
+
+        Ok(Self {
+            background,
+            crop: file.crop,
+            drawables: file.drawables,
+        })
+    }
+
+    pub fn save(
+        filename: &str,
+        background: &Pixbuf,
+        crop: Option<(Vec2D, Vec2D)>,
+        drawables: &[Box<dyn Drawable>],
+    ) -> Result<()> {
+        let png = background.save_to_bufferv("png", &[])?;
+        let file = ProjectFile {
+            version: PROJECT_VERSION,
+            background: glib::base64_encode(&png).to_string(),
+            crop,
+            // drawables without serialization support are dropped
+            drawables: drawables.iter().filter_map(|d| d.to_data()).collect(),
+        };
+
+        fs::write(filename, toml::to_string(&file)?)?;
+        Ok(())
+    }
+}
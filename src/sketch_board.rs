@@ -4,8 +4,7 @@ use femtovg::imgref::Img;
 use femtovg::rgb::{ComponentBytes, RGBA};
 use gdk_pixbuf::glib::Bytes;
 use gdk_pixbuf::Pixbuf;
-use keycode::{KeyMap, KeyMappingId};
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::io::Write;
 use std::panic;
 use std::process::{Command, Stdio};
@@ -17,14 +16,19 @@ use gtk::prelude::*;
 use relm4::gtk::gdk::{DisplayManager, Key, ModifierType, Texture};
 use relm4::{gtk, Component, ComponentParts, ComponentSender, RelmWidgetExt};
 
-use crate::configuration::{Action, APP_CONFIG};
+use crate::configuration::{Action, KeyAction, APP_CONFIG};
+use crate::export;
 use crate::femtovg_area::FemtoVGArea;
 use crate::ime::pango_adapter::spans_from_pango_attrs;
+use crate::ipc::{IpcCommand, IpcRequest, IpcResponse, IpcServer, PendingRequests};
 use crate::math::Vec2D;
 use crate::notification::log_result;
-use crate::style::Style;
-use crate::tools::{Tool, ToolEvent, ToolUpdateResult, Tools, ToolsManager};
-use crate::ui::toolbars::ToolbarEvent;
+use crate::project::{Project, PROJECT_EXTENSION};
+use crate::style::{Color, Style};
+use crate::tools::{
+    DrawableStore, History, Tool, ToolEvent, ToolUpdateResult, Tools, ToolsManager,
+};
+use crate::ui::toolbars::{ColorButtons, StyleToolbarInput, ToolbarEvent};
 
 type RenderedImage = Img<Vec<RGBA<u8>>>;
 
@@ -32,14 +36,22 @@ type RenderedImage = Img<Vec<RGBA<u8>>>;
 pub enum SketchBoardInput {
     InputEvent(InputEvent),
     ToolbarEvent(ToolbarEvent),
-    RenderResult(RenderedImage, Vec<Action>),
+    RenderResult(Result<RenderedImage, String>, Vec<Action>),
     CommitEvent(TextEventMsg),
+    // zoom by a factor around a position in widget coordinates
+    Zoom(Option<Vec2D>, f32),
+    // move through the configured size steps, negative steps make it smaller
+    StepSize(i32),
+    Ipc(IpcRequest),
 }
 
 #[derive(Debug, Clone)]
 pub enum SketchBoardOutput {
     ToggleToolbarsDisplay,
     ToolSwitchShortcut(Tools),
+    // changes the style through the style toolbar
+    StyleShortcut(StyleToolbarInput),
+    HistoryChanged(History),
 }
 
 #[derive(Debug, Clone)]
@@ -82,6 +94,8 @@ pub enum MouseEventType {
     EndDrag,
     UpdateDrag,
     Click,
+    /// Sent after the `Click` of the second press.
+    DoubleClick,
     //Motion(Vec2D),
 }
 
@@ -148,7 +162,7 @@ impl InputEvent {
                         None
                     }
                 }
-                MouseEventType::BeginDrag => {
+                MouseEventType::BeginDrag | MouseEventType::DoubleClick => {
                     me.pos = renderer.abs_canvas_to_image_coordinates(me.pos);
                     None
                 }
@@ -169,14 +183,24 @@ pub struct SketchBoard {
     tools: ToolsManager,
     style: Style,
     im_context: gtk::IMMulticontext,
+    drawables: DrawableStore,
+    background_image: Pixbuf,
+    zoom_level: Option<f32>,
+    space_pressed: bool,
+    pan_position: Option<Vec2D>,
+    _ipc_server: Option<IpcServer>,
+    // answered once the rendered image was saved or copied
+    pending_ipc: PendingRequests,
 }
 
 impl SketchBoard {
+    const ZOOM_STEP: f32 = 1.25;
+
     fn refresh_screen(&mut self) {
         self.renderer.queue_render();
     }
 
-    fn image_to_pixbuf(image: RenderedImage) -> Pixbuf {
+    pub fn image_to_pixbuf(image: RenderedImage) -> Pixbuf {
         let (buf, w, h) = image.into_contiguous_buf();
 
         Pixbuf::from_bytes(
@@ -190,13 +214,72 @@ impl SketchBoard {
         )
     }
 
+    /// Whether typed characters go to the active tool instead of selecting tools.
+    fn text_input_enabled(&self) -> bool {
+        matches!(self.active_tool_type(), Tools::Text | Tools::Callout)
+            && self.active_tool.borrow().input_enabled()
+    }
+
+    /// Shortcuts of the text being typed come before the keybinds, e.g. `ctrl+c` copies the
+    /// selected text instead of the image. Keys the text does not use are left to the keybinds.
+    fn handle_text_shortcut(
+        &mut self,
+        event: &InputEvent,
+        sender: &ComponentSender<Self>,
+    ) -> Option<ToolUpdateResult> {
+        match event {
+            InputEvent::Key(ke)
+                if ke.modifier == ModifierType::CONTROL_MASK
+                    && ke.key.to_lower() == Key::v
+                    && self.text_input_enabled() =>
+            {
+                self.paste_text(sender);
+                Some(ToolUpdateResult::Unmodified)
+            }
+            InputEvent::Key(ke) if !ke.modifier.is_empty() && self.text_input_enabled() => {
+                match self
+                    .active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::Input(event.clone()))
+                {
+                    ToolUpdateResult::Unmodified => None,
+                    result => Some(result),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// Pastes the clipboard into the text being typed. It arrives asynchronously like typed text,
+    /// so the tool drops it if the text was finished in the meantime.
+    fn paste_text(&self, sender: &ComponentSender<Self>) {
+        let Some(display) = DisplayManager::get().default_display() else {
+            return;
+        };
+        let sender = sender.input_sender().clone();
+        display
+            .clipboard()
+            .read_text_async(None::<&gtk::gio::Cancellable>, move |result| {
+                if let Ok(Some(pasted)) = result {
+                    sender.emit(SketchBoardInput::new_text_event(TextEventMsg::Commit(
+                        pasted.to_string(),
+                    )));
+                }
+            });
+    }
+
     fn deactivate_active_tool(&mut self) -> bool {
         if self.active_tool.borrow().active() {
-            if let ToolUpdateResult::Commit(result) =
-                self.active_tool.borrow_mut().handle_deactivated()
-            {
-                self.renderer.commit(result);
-                return true;
+            match self.active_tool.borrow_mut().handle_deactivated() {
+                ToolUpdateResult::Commit(result) => {
+                    self.renderer.commit(result);
+                    return true;
+                }
+                ToolUpdateResult::Edit(operation) => {
+                    self.renderer.edit(operation);
+                    return true;
+                }
+                _ => (),
             }
         }
         false
@@ -212,7 +295,18 @@ impl SketchBoard {
         rv
     }
 
-    fn handle_render_result(&self, image: RenderedImage, actions: Vec<Action>) {
+    fn handle_render_result(&mut self, image: Result<RenderedImage, String>, actions: Vec<Action>) {
+        let image = match image {
+            Ok(image) => image,
+            Err(e) => {
+                for action in actions {
+                    self.pending_ipc
+                        .respond(action, &Err(anyhow!("Error while rendering image: {e}")));
+                }
+                return;
+            }
+        };
+
         let needs_pixbuf = actions.iter().any(|action| {
             matches!(
                 action,
@@ -227,23 +321,23 @@ impl SketchBoard {
         };
 
         for action in actions {
-            match action {
-                Action::SaveToClipboard => {
-                    if let Some(ref pix_buf) = pix_buf {
-                        self.handle_copy_clipboard(pix_buf);
-                    }
-                }
-                Action::SaveToFile => {
-                    if let Some(ref pix_buf) = pix_buf {
-                        self.handle_save(pix_buf);
-                    }
-                }
+            let result = match action {
+                Action::SaveToClipboard => pix_buf.as_ref().map(|p| self.handle_copy_clipboard(p)),
+                Action::SaveToFile => pix_buf.as_ref().map(|p| self.handle_save(p)),
                 Action::SaveToFileAs => {
                     if let Some(ref pix_buf) = pix_buf {
                         self.handle_save_as(pix_buf);
                     }
+                    None
                 }
-                _ => (),
+                Action::SaveProject => {
+                    self.handle_save_project();
+                    None
+                }
+                _ => None,
+            };
+            if let Some(result) = result {
+                self.pending_ipc.respond(action, &result);
             }
 
             if APP_CONFIG.read().early_exit() || action == Action::Exit {
@@ -257,11 +351,124 @@ impl SketchBoard {
         relm4::main_application().quit();
     }
 
-    fn handle_save(&self, image: &Pixbuf) {
+    fn handle_save(&self, image: &Pixbuf) -> anyhow::Result<()> {
+        let output_filename =
+            Self::resolve_output_filename().ok_or(anyhow!("No output filename specified"))?;
+
+        let format = if output_filename == "-" {
+            export::default_format()
+        } else {
+            export::format_for_filename(&output_filename)
+        };
+        let format = match format {
+            Ok(f) => f,
+            Err(e) => {
+                log_result(
+                    &format!("Unsupported image format: {e}"),
+                    !APP_CONFIG.read().disable_notifications(),
+                );
+                return Err(e);
+            }
+        };
+
+        let data = match self.encode(image, &format) {
+            Ok(d) => d,
+            Err(e) => {
+                println!("Error serializing image: {e}");
+                return Err(e);
+            }
+        };
+
+        if output_filename == "-" {
+            // "-" means stdout
+            let stdout = io::stdout();
+            let mut handle = stdout.lock();
+            if let Err(e) = handle.write_all(&data) {
+                eprintln!("Error writing image to stdout: {e}");
+                return Err(e.into());
+            }
+            return Ok(());
+        }
+        match fs::write(&output_filename, data) {
+            Err(e) => {
+                log_result(
+                    &format!("Error while saving file: {e}"),
+                    !APP_CONFIG.read().disable_notifications(),
+                );
+                Err(e.into())
+            }
+            Ok(_) => {
+                log_result(
+                    &format!("File saved to '{}'.", &output_filename),
+                    !APP_CONFIG.read().disable_notifications(),
+                );
+                Ok(())
+            }
+        }
+    }
+
+    fn handle_save_project(&self) {
+        // the project is stored next to the output file, or overwrites the opened project
+        let project_filename = match Self::resolve_output_filename() {
+            Some(o) if o != "-" => std::path::Path::new(&o)
+                .with_extension(PROJECT_EXTENSION)
+                .to_string_lossy()
+                .into_owned(),
+            _ if Project::is_project_file(APP_CONFIG.read().input_filename()) => {
+                APP_CONFIG.read().input_filename().to_string()
+            }
+            _ => {
+                log_result(
+                    "Cannot save project, no output filename specified",
+                    !APP_CONFIG.read().disable_notifications(),
+                );
+                return;
+            }
+        };
+
+        match Project::save(
+            &project_filename,
+            &self.background_image,
+            self.crop_rectangle(),
+            &self.drawables.borrow(),
+        ) {
+            Err(e) => log_result(
+                &format!("Error while saving project: {e}"),
+                !APP_CONFIG.read().disable_notifications(),
+            ),
+            Ok(_) => log_result(
+                &format!("Project saved to '{}'.", &project_filename),
+                !APP_CONFIG.read().disable_notifications(),
+            ),
+        };
+    }
+
+    fn crop_rectangle(&self) -> Option<(Vec2D, Vec2D)> {
+        self.tools
+            .get_crop_tool()
+            .borrow()
+            .get_crop()
+            .map(|c| c.get_rectangle())
+    }
+
+    fn encode(&self, image: &Pixbuf, format: &str) -> anyhow::Result<Vec<u8>> {
+        if export::is_svg(format) {
+            let svg = export::svg(
+                &self.background_image,
+                self.crop_rectangle(),
+                &self.drawables.borrow(),
+            )?;
+            Ok(svg.into_bytes())
+        } else {
+            export::encode(image, format)
+        }
+    }
+
+    pub fn resolve_output_filename() -> Option<String> {
         let mut output_filename = match APP_CONFIG.read().output_filename() {
             None => {
                 println!("No Output filename specified!");
-                return;
+                return None;
             }
             Some(o) => o.clone(),
         };
@@ -280,15 +487,6 @@ impl SketchBoard {
             output_filename = format!("{delayed_format}");
         }
 
-        // TODO: we could support more data types
-        if output_filename != "-" && !output_filename.ends_with(".png") {
-            log_result(
-                "The only supported format is png, but the filename does not end in png",
-                !APP_CONFIG.read().disable_notifications(),
-            );
-            return;
-        }
-
         if let Some(tilde_stripped) =
             output_filename.strip_prefix(&format!("~{}", std::path::MAIN_SEPARATOR_STR))
         {
@@ -301,48 +499,24 @@ impl SketchBoard {
                     "~ found but could not determine homedir",
                     !APP_CONFIG.read().disable_notifications(),
                 );
-                return;
+                return None;
             }
         }
 
-        let data = match image.save_to_bufferv("png", &Vec::new()) {
-            Ok(d) => d,
-            Err(e) => {
-                println!("Error serializing image: {e}");
-                return;
-            }
-        };
-
-        if output_filename == "-" {
-            // "-" means stdout
-            let stdout = io::stdout();
-            let mut handle = stdout.lock();
-            if let Err(e) = handle.write_all(&data) {
-                eprintln!("Error writing image to stdout: {e}");
-            }
-            return;
-        }
-        match fs::write(&output_filename, data) {
-            Err(e) => log_result(
-                &format!("Error while saving file: {e}"),
-                !APP_CONFIG.read().disable_notifications(),
-            ),
-            Ok(_) => log_result(
-                &format!("File saved to '{}'.", &output_filename),
-                !APP_CONFIG.read().disable_notifications(),
-            ),
-        };
+        Some(output_filename)
     }
 
     fn handle_save_as(&self, image: &Pixbuf) {
-        let data = match image.save_to_bufferv("png", &Vec::new()) {
-            Ok(d) => d,
-            Err(e) => {
-                println!("Error serializing image: {e}");
-                return;
-            }
-        };
-
+        let image = image.clone();
+        // svg needs the annotations as they are now, not when the dialog returns
+        let background = self.background_image.clone();
+        let crop = self.crop_rectangle();
+        let drawables: Vec<_> = self
+            .drawables
+            .borrow()
+            .iter()
+            .map(|d| d.clone_box())
+            .collect();
         let root = self.renderer.toplevel_window();
 
         relm4::spawn_local(async move {
@@ -370,6 +544,26 @@ impl SketchBoard {
                             None => return,
                         };
 
+                        // the format follows the chosen extension
+                        let data =
+                            match export::format_for_filename(&output_filename).and_then(|format| {
+                                if export::is_svg(&format) {
+                                    export::svg(&background, crop, &drawables)
+                                        .map(String::into_bytes)
+                                } else {
+                                    export::encode(&image, &format)
+                                }
+                            }) {
+                                Ok(d) => d,
+                                Err(e) => {
+                                    log_result(
+                                        &format!("Error serializing image: {e}"),
+                                        !APP_CONFIG.read().disable_notifications(),
+                                    );
+                                    return;
+                                }
+                            };
+
                         match fs::write(&output_filename, &data) {
                             Err(e) => log_result(
                                 &format!("Error while saving file: {e}"),
@@ -398,11 +592,9 @@ impl SketchBoard {
         Ok(())
     }
 
-    fn save_to_external_process(
-        &self,
-        texture: &impl IsA<Texture>,
-        command: &str,
-    ) -> anyhow::Result<()> {
+    fn save_to_external_process(&self, image: &Pixbuf, command: &str) -> anyhow::Result<()> {
+        let data = self.encode(image, &export::default_format()?)?;
+
         let mut child = Command::new("sh")
             .arg("-c")
             .arg(command)
@@ -411,7 +603,7 @@ impl SketchBoard {
             .spawn()?;
 
         let child_stdin = child.stdin.as_mut().unwrap();
-        child_stdin.write_all(texture.save_to_png_bytes().as_ref())?;
+        child_stdin.write_all(&data)?;
 
         if !child.wait()?.success() {
             return Err(anyhow!("Writing to process '{command}' failed."));
@@ -420,17 +612,18 @@ impl SketchBoard {
         Ok(())
     }
 
-    fn handle_copy_clipboard(&self, image: &Pixbuf) {
-        let texture = Texture::for_pixbuf(image);
-
+    fn handle_copy_clipboard(&self, image: &Pixbuf) -> anyhow::Result<()> {
         let result = if let Some(command) = APP_CONFIG.read().copy_command() {
-            self.save_to_external_process(&texture, command)
+            self.save_to_external_process(image, command)
         } else {
-            self.save_to_clipboard(&texture)
+            self.save_to_clipboard(&Texture::for_pixbuf(image))
         };
 
         match result {
-            Err(e) => println!("Error saving {e}"),
+            Err(e) => {
+                println!("Error saving {e}");
+                Err(e)
+            }
             Ok(()) => {
                 log_result(
                     "Copied to clipboard.",
@@ -439,8 +632,10 @@ impl SketchBoard {
 
                 // TODO: rethink order and messaging patterns
                 if APP_CONFIG.read().save_after_copy() {
-                    self.handle_save(image);
-                };
+                    self.handle_save(image)
+                } else {
+                    Ok(())
+                }
             }
         }
     }
@@ -449,6 +644,7 @@ impl SketchBoard {
         if self.active_tool.borrow().active() {
             self.active_tool.borrow_mut().handle_undo()
         } else if self.renderer.undo() {
+            self.active_tool.borrow_mut().handle_drawables_changed();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -459,6 +655,7 @@ impl SketchBoard {
         if self.active_tool.borrow().active() {
             self.active_tool.borrow_mut().handle_redo()
         } else if self.renderer.redo() {
+            self.active_tool.borrow_mut().handle_drawables_changed();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -468,12 +665,278 @@ impl SketchBoard {
     fn handle_reset(&mut self) -> ToolUpdateResult {
         // can't use lazy || here
         if self.deactivate_active_tool() | self.renderer.reset() {
+            self.active_tool.borrow_mut().handle_drawables_changed();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
         }
     }
 
+    fn handle_history_jump(&mut self, position: usize) -> ToolUpdateResult {
+        // a pending drawable is committed first, like with every other change of the history
+        let committed = self.deactivate_active_tool();
+        if self.renderer.jump_to(position) | committed {
+            self.active_tool.borrow_mut().handle_drawables_changed();
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    /// Zooming and panning of the view, returns `None` if the event is meant for the tool.
+    fn handle_navigation_event(&mut self, event: &InputEvent) -> Option<ToolUpdateResult> {
+        match event {
+            // space + drag pans, unless the tool is busy, e.g. typing text
+            InputEvent::Key(ke)
+                if ke.key == Key::space
+                    && ke.modifier.is_empty()
+                    && !self.active_tool.borrow().active() =>
+            {
+                self.space_pressed = true;
+                Some(ToolUpdateResult::Unmodified)
+            }
+            InputEvent::KeyRelease(ke) if ke.key == Key::space => {
+                self.space_pressed = false;
+                None
+            }
+            InputEvent::Mouse(me)
+                if me.button == MouseButton::Middle
+                    || self.space_pressed
+                    || self.pan_position.is_some() =>
+            {
+                match me.type_ {
+                    MouseEventType::Click | MouseEventType::DoubleClick => {
+                        Some(ToolUpdateResult::Unmodified)
+                    }
+                    MouseEventType::BeginDrag => {
+                        self.pan_position = Some(Vec2D::zero());
+                        Some(ToolUpdateResult::Unmodified)
+                    }
+                    MouseEventType::UpdateDrag | MouseEventType::EndDrag => {
+                        // drag positions are relative to the start of the drag
+                        if let Some(last) = self.pan_position {
+                            self.renderer.pan(me.pos - last);
+                            self.zoom_level = self.renderer.zoom_level();
+                        }
+                        self.pan_position = if me.type_ == MouseEventType::EndDrag {
+                            None
+                        } else {
+                            Some(me.pos)
+                        };
+                        Some(ToolUpdateResult::Redraw)
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn handle_ipc(
+        &mut self,
+        request: IpcRequest,
+        sender: ComponentSender<Self>,
+    ) -> ToolUpdateResult {
+        let (result, response) = match &request.command {
+            IpcCommand::SelectTool { tool } => {
+                // same as a tool shortcut, the toolbar follows
+                sender.input(SketchBoardInput::ToolbarEvent(ToolbarEvent::ToolSelected(
+                    *tool,
+                )));
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::ToolSwitchShortcut(*tool));
+                (ToolUpdateResult::Unmodified, IpcResponse::ok())
+            }
+            IpcCommand::SetColor { color } => {
+                // the style toolbar shows the color and sends it back as a toolbar event
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::StyleShortcut(
+                        StyleToolbarInput::ColorDialogFinished(Some(Color::from(*color))),
+                    ));
+                (ToolUpdateResult::Unmodified, IpcResponse::ok())
+            }
+            IpcCommand::LoadImage { path } => match Project::open(path) {
+                Ok(project) => (self.load_project(project), IpcResponse::ok()),
+                Err(e) => (
+                    ToolUpdateResult::Unmodified,
+                    IpcResponse::error(format!("Cannot load '{path}': {e}")),
+                ),
+            },
+            IpcCommand::Save if APP_CONFIG.read().output_filename().is_none() => (
+                ToolUpdateResult::Unmodified,
+                IpcResponse::error("No output filename specified"),
+            ),
+            IpcCommand::Save => return self.handle_ipc_render(Action::SaveToFile, request),
+            IpcCommand::Copy => return self.handle_ipc_render(Action::SaveToClipboard, request),
+            IpcCommand::Undo => match self.handle_undo() {
+                ToolUpdateResult::Unmodified => (
+                    ToolUpdateResult::Unmodified,
+                    IpcResponse::error("Nothing to undo"),
+                ),
+                result => (result, IpcResponse::ok()),
+            },
+            IpcCommand::Redo => match self.handle_redo() {
+                ToolUpdateResult::Unmodified => (
+                    ToolUpdateResult::Unmodified,
+                    IpcResponse::error("Nothing to redo"),
+                ),
+                result => (result, IpcResponse::ok()),
+            },
+            IpcCommand::ListAnnotations => (
+                ToolUpdateResult::Unmodified,
+                IpcResponse::annotations(
+                    self.drawables
+                        .borrow()
+                        .iter()
+                        .filter_map(|d| d.to_data())
+                        .collect(),
+                ),
+            ),
+        };
+
+        request.respond(response);
+        result
+    }
+
+    /// Requests the render for saving or copying, the request is answered with the outcome.
+    fn handle_ipc_render(&mut self, action: Action, request: IpcRequest) -> ToolUpdateResult {
+        self.pending_ipc.push(action, request);
+        self.handle_action(&[action])
+    }
+
+    /// Replaces image, annotations and history, e.g. to annotate the next screenshot.
+    fn load_project(&mut self, project: Project) -> ToolUpdateResult {
+        // the pending drawable belongs to the old image
+        self.active_tool
+            .borrow_mut()
+            .handle_event(ToolEvent::Deactivated);
+
+        // fresh tools, so that marker numbers, crop and blur start over for the new image
+        self.tools = ToolsManager::new(self.drawables.clone(), project.background.clone());
+        {
+            let mut drawables = self.drawables.borrow_mut();
+            drawables.clear();
+            drawables.extend(
+                project
+                    .drawables
+                    .into_iter()
+                    .map(|d| self.tools.restore_drawable(d)),
+            );
+        }
+        if let Some(crop) = project.crop {
+            self.tools.get_crop_tool().borrow_mut().set_crop(crop);
+        }
+
+        self.renderer
+            .load(project.background.clone(), self.tools.get_crop_tool());
+        self.background_image = project.background;
+        self.zoom_level = None;
+
+        // activate the same tool again, now from the new set of tools
+        let tool = self.active_tool_type();
+        self.handle_toolbar_event(ToolbarEvent::ToolSelected(tool));
+        ToolUpdateResult::Redraw
+    }
+
+    fn handle_key_action(
+        &mut self,
+        action: KeyAction,
+        sender: ComponentSender<Self>,
+    ) -> ToolUpdateResult {
+        match action {
+            KeyAction::Undo => self.handle_undo(),
+            KeyAction::Redo => self.handle_redo(),
+            KeyAction::ToggleToolbars => self.handle_toggle_toolbars_display(sender),
+            KeyAction::Save => {
+                self.renderer.request_render(&[Action::SaveToFile]);
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::SaveAs => {
+                self.renderer.request_render(&[Action::SaveToFileAs]);
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::SaveProject => self.handle_action(&[Action::SaveProject]),
+            KeyAction::Copy => {
+                self.renderer.request_render(&[Action::SaveToClipboard]);
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::Reset => self.handle_reset(),
+            // style changes go through the toolbar, so that it shows the new style
+            KeyAction::ToggleFill => {
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::StyleShortcut(
+                        StyleToolbarInput::ToggleFill,
+                    ));
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::ToggleShadow => {
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::StyleShortcut(
+                        StyleToolbarInput::ToggleShadow,
+                    ));
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::ToggleOutline => {
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::StyleShortcut(
+                        StyleToolbarInput::ToggleOutline,
+                    ));
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::Size(size) => {
+                sender
+                    .output_sender()
+                    .emit(SketchBoardOutput::StyleShortcut(
+                        StyleToolbarInput::SwitchSize(size.into()),
+                    ));
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::SizeUp => self.handle_step_size(1, sender),
+            KeyAction::SizeDown => self.handle_step_size(-1, sender),
+            KeyAction::Color(index) => {
+                if index < APP_CONFIG.read().color_palette().palette().len() {
+                    sender
+                        .output_sender()
+                        .emit(SketchBoardOutput::StyleShortcut(
+                            StyleToolbarInput::SwitchColor(ColorButtons::Palette(index as u64)),
+                        ));
+                }
+                ToolUpdateResult::Unmodified
+            }
+            KeyAction::ZoomIn => self.handle_zoom(None, Self::ZOOM_STEP),
+            KeyAction::ZoomOut => self.handle_zoom(None, 1.0 / Self::ZOOM_STEP),
+            KeyAction::ZoomFit => {
+                self.renderer.zoom_to_fit();
+                self.zoom_level = self.renderer.zoom_level();
+                ToolUpdateResult::Redraw
+            }
+            KeyAction::ZoomOriginal => {
+                self.renderer.zoom_to(None, 1.0);
+                self.zoom_level = self.renderer.zoom_level();
+                ToolUpdateResult::Redraw
+            }
+        }
+    }
+
+    fn handle_zoom(&mut self, center: Option<Vec2D>, factor: f32) -> ToolUpdateResult {
+        self.renderer.zoom_at(center, factor);
+        self.zoom_level = self.renderer.zoom_level();
+        ToolUpdateResult::Redraw
+    }
+
+    fn handle_step_size(&self, steps: i32, sender: ComponentSender<Self>) -> ToolUpdateResult {
+        sender
+            .output_sender()
+            .emit(SketchBoardOutput::StyleShortcut(
+                StyleToolbarInput::SwitchSize(self.style.size.step(steps)),
+            ));
+        ToolUpdateResult::Unmodified
+    }
+
     // Toolbars = Tools Toolbar + Style Toolbar
     fn handle_toggle_toolbars_display(
         &mut self,
@@ -495,10 +958,17 @@ impl SketchBoard {
 
                 old_tool.borrow_mut().set_im_context(None);
 
-                if let ToolUpdateResult::Commit(d) = deactivate_result {
-                    self.renderer.commit(d);
-                    // we handle commit directly and "downgrade" to a simple redraw result
-                    deactivate_result = ToolUpdateResult::Redraw;
+                // we handle commit directly and "downgrade" to a simple redraw result
+                match deactivate_result {
+                    ToolUpdateResult::Commit(d) => {
+                        self.renderer.commit(d);
+                        deactivate_result = ToolUpdateResult::Redraw;
+                    }
+                    ToolUpdateResult::Edit(operation) => {
+                        self.renderer.edit(operation);
+                        deactivate_result = ToolUpdateResult::Redraw;
+                    }
+                    _ => (),
                 }
 
                 // change active tool
@@ -540,17 +1010,48 @@ impl SketchBoard {
                     .borrow_mut()
                     .handle_event(ToolEvent::StyleChanged(self.style))
             }
+            ToolbarEvent::LineDashSelected(dash) => {
+                self.style.dash = dash;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::FillColorSelected(color) => {
+                self.style.fill_color = color;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::FillOpacityChanged(opacity) => {
+                self.style.fill_opacity = opacity;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
             ToolbarEvent::SaveFile => self.handle_action(&[Action::SaveToFile]),
             ToolbarEvent::CopyClipboard => self.handle_action(&[Action::SaveToClipboard]),
             ToolbarEvent::Undo => self.handle_undo(),
             ToolbarEvent::Redo => self.handle_redo(),
             ToolbarEvent::Reset => self.handle_reset(),
+            ToolbarEvent::JumpToHistory(position) => self.handle_history_jump(position),
             ToolbarEvent::ToggleFill => {
                 self.style.fill = !self.style.fill;
                 self.active_tool
                     .borrow_mut()
                     .handle_event(ToolEvent::StyleChanged(self.style))
             }
+            ToolbarEvent::ToggleShadow => {
+                self.style.shadow = !self.style.shadow;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
+            ToolbarEvent::ToggleOutline => {
+                self.style.outline = !self.style.outline;
+                self.active_tool
+                    .borrow_mut()
+                    .handle_event(ToolEvent::StyleChanged(self.style))
+            }
             ToolbarEvent::AnnotationSizeChanged(value) => {
                 self.style.annotation_size_factor = value;
                 self.active_tool
@@ -562,7 +1063,7 @@ impl SketchBoard {
     }
 
     fn handle_text_commit(
-        &self,
+        &mut self,
         event: TextEventMsg,
         sender: ComponentSender<Self>,
     ) -> ToolUpdateResult {
@@ -576,9 +1077,7 @@ impl SketchBoard {
                 // Here we're basically bypassing the IMMulticontext. If the text tool is active
                 // and wants text inputs, we're interested in the single-letter keypress as a text character.
                 // If not, we parse it as a shortcut event.
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_enabled() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::Commit(
                         txt.to_string(),
                     )));
@@ -593,6 +1092,8 @@ impl SketchBoard {
                     sender
                         .output_sender()
                         .emit(SketchBoardOutput::ToolSwitchShortcut(tool));
+                } else if let Some(action) = APP_CONFIG.read().keybinds().get_text_action(&txt) {
+                    return self.handle_key_action(action, sender);
                 }
             }
             TextEventMsg::Preedit {
@@ -600,9 +1101,7 @@ impl SketchBoard {
                 cursor_chars,
                 spans,
             } => {
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_enabled() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::Preedit {
                         text,
                         cursor_chars,
@@ -611,9 +1110,7 @@ impl SketchBoard {
                 }
             }
             TextEventMsg::PreeditEnd => {
-                if self.active_tool_type() == Tools::Text
-                    && self.active_tool.borrow().input_enabled()
-                {
+                if self.text_input_enabled() {
                     sender.input(SketchBoardInput::new_text_event(TextEventMsg::PreeditEnd));
                 }
             }
@@ -631,10 +1128,21 @@ impl Component for SketchBoard {
     type CommandOutput = ();
     type Input = SketchBoardInput;
     type Output = SketchBoardOutput;
-    type Init = Pixbuf;
+    type Init = Project;
 
     view! {
-        gtk::Box {
+        gtk::Overlay {
+            add_overlay = &gtk::Label {
+                add_css_class: "toast",
+                set_halign: gtk::Align::Center,
+                set_valign: gtk::Align::Start,
+                set_can_target: false,
+                #[watch]
+                set_visible: model.zoom_level.is_some(),
+                #[watch]
+                set_label: &format!("{:.0}%", model.zoom_level.unwrap_or(1.0) * 100.0),
+            },
+
             #[local_ref]
             area -> FemtoVGArea {
                 set_vexpand: true,
@@ -671,12 +1179,19 @@ impl Component for SketchBoard {
                 },
                 add_controller = gtk::GestureClick {
                     set_button: 0,
-                    connect_pressed[sender] => move |controller, _, x, y| {
+                    connect_pressed[sender] => move |controller, n_press, x, y| {
                         sender.input(SketchBoardInput::new_mouse_event(
                             MouseEventType::Click,
                             controller.current_button(),
                             controller.current_event_state(),
                             Vec2D::new(x as f32, y as f32)));
+                        if n_press == 2 {
+                            sender.input(SketchBoardInput::new_mouse_event(
+                                MouseEventType::DoubleClick,
+                                controller.current_button(),
+                                controller.current_event_state(),
+                                Vec2D::new(x as f32, y as f32)));
+                        }
                     }
                 },
 
@@ -710,37 +1225,26 @@ impl Component for SketchBoard {
     }
 
     fn update(&mut self, msg: SketchBoardInput, sender: ComponentSender<Self>, _root: &Self::Root) {
+        let output = sender.output_sender().clone();
+
         // handle resize ourselves, pass everything else to tool
         let result = match msg {
             SketchBoardInput::InputEvent(mut ie) => {
-                if let InputEvent::Key(ke) = ie {
-                    if ke.is_one_of(Key::z, KeyMappingId::UsZ)
-                        && ke.modifier == ModifierType::CONTROL_MASK
-                    {
-                        self.handle_undo()
-                    } else if ke.is_one_of(Key::y, KeyMappingId::UsY)
-                        && ke.modifier == ModifierType::CONTROL_MASK
-                    {
-                        self.handle_redo()
-                    } else if ke.is_one_of(Key::t, KeyMappingId::UsT)
-                        && ke.modifier == ModifierType::CONTROL_MASK
-                    {
-                        self.handle_toggle_toolbars_display(sender)
-                    } else if ke.is_one_of(Key::s, KeyMappingId::UsS)
-                        && ke.modifier == ModifierType::CONTROL_MASK
+                if let Some(result) = self.handle_navigation_event(&ie) {
+                    result
+                } else if let Some(result) = self.handle_text_shortcut(&ie, &sender) {
+                    result
+                } else if let InputEvent::Key(ke) = ie {
+                    // keys without modifier belong to the tool while it is busy, e.g. typing text
+                    let key_action = if ke.modifier.is_empty() && self.active_tool.borrow().active()
                     {
-                        self.renderer.request_render(&[Action::SaveToFile]);
-                        ToolUpdateResult::Unmodified
-                    } else if ke.is_one_of(Key::s, KeyMappingId::UsS)
-                        && ke.modifier == (ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK)
-                    {
-                        self.renderer.request_render(&[Action::SaveToFileAs]);
-                        ToolUpdateResult::Unmodified
-                    } else if ke.is_one_of(Key::c, KeyMappingId::UsC)
-                        && ke.modifier == ModifierType::CONTROL_MASK
-                    {
-                        self.renderer.request_render(&[Action::SaveToClipboard]);
-                        ToolUpdateResult::Unmodified
+                        None
+                    } else {
+                        APP_CONFIG.read().keybinds().get_action(&ke)
+                    };
+
+                    if let Some(key_action) = key_action {
+                        self.handle_key_action(key_action, sender)
                     } else if ke.modifier.is_empty()
                         && (ke.key == Key::Escape
                             || ke.key == Key::Return
@@ -780,10 +1284,10 @@ impl Component for SketchBoard {
                 self.handle_render_result(img, action);
                 ToolUpdateResult::Unmodified
             }
-            SketchBoardInput::CommitEvent(txt) => {
-                self.handle_text_commit(txt, sender);
-                ToolUpdateResult::Unmodified
-            }
+            SketchBoardInput::CommitEvent(txt) => self.handle_text_commit(txt, sender),
+            SketchBoardInput::Zoom(center, factor) => self.handle_zoom(center, factor),
+            SketchBoardInput::StepSize(steps) => self.handle_step_size(steps, sender),
+            SketchBoardInput::Ipc(request) => self.handle_ipc(request, sender),
         };
 
         //println!("Event={:?} Result={:?}", msg, result);
@@ -792,27 +1296,64 @@ impl Component for SketchBoard {
                 self.renderer.commit(drawable);
                 self.refresh_screen();
             }
+            ToolUpdateResult::Edit(operation) => {
+                self.renderer.edit(operation);
+                self.refresh_screen();
+            }
             ToolUpdateResult::Unmodified => (),
             ToolUpdateResult::Redraw => self.refresh_screen(),
         };
+
+        if let Some(history) = self.renderer.take_history_update() {
+            output.emit(SketchBoardOutput::HistoryChanged(history));
+        }
     }
 
     fn init(
-        image: Self::Init,
+        project: Self::Init,
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let config = APP_CONFIG.read();
-        let tools = ToolsManager::new();
+        let drawables: DrawableStore = Rc::new(RefCell::new(Vec::new()));
+        let tools = ToolsManager::new(drawables.clone(), project.background.clone());
+
+        // restore a previously saved project, this is not part of the undo history
+        drawables.borrow_mut().extend(
+            project
+                .drawables
+                .into_iter()
+                .map(|d| tools.restore_drawable(d)),
+        );
+        if let Some(crop) = project.crop {
+            tools.get_crop_tool().borrow_mut().set_crop(crop);
+        }
 
         let im_context = gtk::IMMulticontext::new();
 
+        // other processes can send commands through the socket
+        let ipc_server = config.ipc_socket().and_then(|path| {
+            let input = sender.input_sender().clone();
+            IpcServer::listen(path, move |request| {
+                input.emit(SketchBoardInput::Ipc(request))
+            })
+            .inspect_err(|e| eprintln!("Error: {e}"))
+            .ok()
+        });
+
         let mut model = Self {
             renderer: FemtoVGArea::default(),
             active_tool: tools.get(&config.initial_tool()),
             style: Style::default(),
             tools,
             im_context,
+            drawables: drawables.clone(),
+            background_image: project.background.clone(),
+            zoom_level: None,
+            space_pressed: false,
+            pan_position: None,
+            _ipc_server: ipc_server,
+            pending_ipc: PendingRequests::default(),
         };
 
         let area = &mut model.renderer;
@@ -820,7 +1361,8 @@ impl Component for SketchBoard {
             sender.input_sender().clone(),
             model.tools.get_crop_tool(),
             model.active_tool.clone(),
-            image,
+            drawables,
+            project.background,
         );
 
         let widgets = view_output!();
@@ -883,6 +1425,75 @@ impl Component for SketchBoard {
         }
         model.renderer.add_controller(focus_controller);
 
+        // ctrl + scroll and pinch zoom around the pointer
+        let pointer_position = Rc::new(Cell::new(None));
+        let motion_controller = gtk::EventControllerMotion::new();
+        {
+            let pointer_position = pointer_position.clone();
+            motion_controller.connect_motion(move |_, x, y| {
+                pointer_position.set(Some(Vec2D::new(x as f32, y as f32)));
+            });
+        }
+        model.renderer.add_controller(motion_controller);
+
+        let scroll_controller =
+            gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        {
+            let sender = sender.input_sender().clone();
+            scroll_controller.connect_scroll(move |controller, _, dy| {
+                if !controller
+                    .current_event_state()
+                    .contains(ModifierType::CONTROL_MASK)
+                {
+                    return glib::Propagation::Proceed;
+                }
+                sender.emit(SketchBoardInput::Zoom(
+                    pointer_position.get(),
+                    Self::ZOOM_STEP.powf(-dy as f32),
+                ));
+                glib::Propagation::Stop
+            });
+        }
+        model.renderer.add_controller(scroll_controller);
+
+        // plain scrolling changes the size, also while drawing
+        let size_scroll_controller = gtk::EventControllerScroll::new(
+            gtk::EventControllerScrollFlags::VERTICAL | gtk::EventControllerScrollFlags::DISCRETE,
+        );
+        {
+            let sender = sender.input_sender().clone();
+            size_scroll_controller.connect_scroll(move |_, _, dy| {
+                let steps = -dy.round() as i32;
+                if steps != 0 {
+                    sender.emit(SketchBoardInput::StepSize(steps));
+                }
+                glib::Propagation::Stop
+            });
+        }
+        model.renderer.add_controller(size_scroll_controller);
+
+        let zoom_gesture = gtk::GestureZoom::new();
+        {
+            let sender = sender.input_sender().clone();
+            let last_scale = Rc::new(Cell::new(1.0));
+            {
+                let last_scale = last_scale.clone();
+                zoom_gesture.connect_begin(move |_, _| last_scale.set(1.0));
+            }
+            zoom_gesture.connect_scale_changed(move |gesture, scale| {
+                // the scale is relative to the start of the gesture
+                let center = gesture
+                    .bounding_box_center()
+                    .map(|(x, y)| Vec2D::new(x as f32, y as f32));
+                sender.emit(SketchBoardInput::Zoom(
+                    center,
+                    (scale / last_scale.get()) as f32,
+                ));
+                last_scale.set(scale);
+            });
+        }
+        model.renderer.add_controller(zoom_gesture);
+
         let widget_ref: gtk::Widget = model.renderer.clone().upcast();
         model
             .active_tool
@@ -904,15 +1515,4 @@ impl KeyEventMsg {
             modifier,
         }
     }
-
-    /// Matches one of providen keys. The modifier is not considered.
-    /// And the key has more priority over keycode.
-    fn is_one_of(&self, key: Key, code: KeyMappingId) -> bool {
-        // INFO: on linux the keycode from gtk4 is evdev keycode, so need to match by him if need
-        // to use layout-independent shortcuts. And notice that there is substraction by 8, it's
-        // because of x11 compatibility in which the keycodes are in range [8,255]. So need shift
-        // them to get correct evdev keycode.
-        let keymap = KeyMap::from(code);
-        self.key == key || self.code as u16 - 8 == keymap.evdev
-    }
 }
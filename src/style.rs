@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
-use femtovg::Paint;
+use anyhow::Result;
+use femtovg::{renderer::OpenGl, Canvas, LineCap, LineJoin, Paint, Path, Verb};
 use gdk_pixbuf::{
     glib::{Variant, VariantTy},
     prelude::{StaticVariantType, ToVariant},
@@ -8,18 +9,35 @@ use gdk_pixbuf::{
 use glib::variant::FromVariant;
 use hex_color::HexColor;
 use relm4::gtk::gdk::RGBA;
+use serde_derive::{Deserialize, Serialize};
 
-use crate::configuration::APP_CONFIG;
+use crate::{command_line, configuration::APP_CONFIG};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Style {
     pub color: Color,
     pub size: Size,
     pub fill: bool,
     pub annotation_size_factor: f32,
+    #[serde(default)]
+    pub dash: LineDash,
+    /// Inside of filled shapes, `None` fills them with `color` and leaves out the outline.
+    #[serde(default)]
+    pub fill_color: Option<Color>,
+    #[serde(default = "opaque")]
+    pub fill_opacity: f32,
+    #[serde(default)]
+    pub shadow: bool,
+    /// Contrasting halo around the annotation, keeps it readable on busy backgrounds.
+    #[serde(default)]
+    pub outline: bool,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+fn opaque() -> f32 {
+    1.0
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -27,12 +45,45 @@ pub struct Color {
     pub a: u8,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default)]
-pub enum Size {
-    Small = 0,
+/// Stroke width of an annotation before the annotation size factor is applied, all other
+/// measures like the text size or the blur strength grow along with it.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize)]
+#[serde(from = "SizeValue", into = "f32")]
+pub struct Size(f32);
+
+/// The former fixed sizes, still available as shortcuts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SizePreset {
+    Small,
+    Medium,
+    Large,
+}
+
+// projects saved before sizes were continuous contain the name of a preset
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SizeValue {
+    Width(f32),
+    Preset(SizePreset),
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineDash {
+    #[default]
+    Solid = 0,
+    Dashed = 1,
+    Dotted = 2,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineCaps {
     #[default]
-    Medium = 1,
-    Large = 2,
+    Butt,
+    Round,
+    Square,
 }
 
 impl Default for Style {
@@ -42,8 +93,359 @@ impl Default for Style {
             size: Size::default(),
             fill: APP_CONFIG.read().default_fill_shapes(),
             annotation_size_factor: APP_CONFIG.read().annotation_size_factor(),
+            dash: APP_CONFIG.read().default_line_dash(),
+            fill_color: None,
+            fill_opacity: opaque(),
+            shadow: APP_CONFIG.read().default_shadow(),
+            outline: APP_CONFIG.read().default_outline(),
+        }
+    }
+}
+
+impl Style {
+    /// Color of the inside of filled shapes and markers, with the fill opacity applied.
+    pub fn effective_fill_color(&self) -> Color {
+        let color = self.fill_color.unwrap_or(self.color);
+        Color {
+            a: (color.a as f32 * self.fill_opacity.clamp(0.0, 1.0)).round() as u8,
+            ..color
+        }
+    }
+
+    /// Fills the path without shadow and outline, see [`Style::layers`] for those.
+    pub fn fill_path(&self, canvas: &mut Canvas<OpenGl>, path: &Path) {
+        Layer::annotation(*self).fill_path(canvas, path);
+    }
+
+    pub fn line_caps(&self) -> LineCaps {
+        // dots are round caps on dashes of (almost) no length
+        match self.dash {
+            LineDash::Dotted => LineCaps::Round,
+            _ => APP_CONFIG.read().line_caps(),
+        }
+    }
+
+    /// Length of the dashes and of the gaps between them along the path, `None` for solid lines.
+    pub fn dash_pattern(&self) -> Option<(f32, f32)> {
+        let width = self.size.to_line_width(self.annotation_size_factor);
+        // e.g. an annotation size factor of 0, the pattern would never advance
+        if width <= 0.0 {
+            return None;
+        }
+        let (dash, gap) = match self.dash {
+            LineDash::Solid => return None,
+            LineDash::Dashed => (width * 3.0, width * 2.0),
+            LineDash::Dotted => (width, width * 1.5),
+        };
+
+        // round and square caps reach half the line width beyond both ends of a dash
+        let caps = match self.line_caps() {
+            LineCaps::Butt => 0.0,
+            LineCaps::Round | LineCaps::Square => width,
+        };
+        Some(((dash - caps).max(width * 0.05), gap + caps))
+    }
+
+    /// Strokes the path without shadow and outline, see [`Style::layers`] for those.
+    pub fn stroke_path(&self, canvas: &mut Canvas<OpenGl>, path: &Path) {
+        Layer::annotation(*self).stroke_path(canvas, path);
+    }
+
+    /// The passes an annotation is painted in, bottom to top: the soft steps of the shadow,
+    /// the outline and the annotation itself.
+    pub fn layers(&self) -> impl Iterator<Item = Layer> {
+        let style = *self;
+        let shadow = (0..SHADOW_STEPS)
+            .filter(move |_| style.shadow)
+            .map(LayerKind::Shadow);
+        let outline = style.outline.then_some(LayerKind::Outline);
+        shadow
+            .chain(outline)
+            .chain([LayerKind::Annotation])
+            .map(move |kind| Layer { style, kind })
+    }
+
+    /// Distance of the drop shadow to the annotation, in both directions.
+    pub fn shadow_offset(&self) -> f32 {
+        (self.size.to_line_width(self.annotation_size_factor) * 0.6)
+            .max(2.0 * self.annotation_size_factor)
+    }
+
+    /// How far the outline reaches beyond the annotation.
+    pub fn outline_width(&self) -> f32 {
+        (self.size.to_line_width(self.annotation_size_factor) * 0.5)
+            .max(1.5 * self.annotation_size_factor)
+    }
+}
+
+// the shadow is painted several times with shrinking spread, which softens its edge
+const SHADOW_STEPS: u8 = 3;
+pub const SHADOW_OPACITY: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayerKind {
+    Shadow(u8),
+    Outline,
+    Annotation,
+}
+
+/// One pass of painting an annotation. Effect layers paint the same shapes recolored, grown by
+/// their spread and, for the shadow, offset.
+#[derive(Debug, Clone, Copy)]
+pub struct Layer {
+    style: Style,
+    kind: LayerKind,
+}
+
+impl Layer {
+    fn annotation(style: Style) -> Self {
+        Self {
+            style,
+            kind: LayerKind::Annotation,
+        }
+    }
+
+    pub fn is_annotation(&self) -> bool {
+        self.kind == LayerKind::Annotation
+    }
+
+    /// How far the layer reaches beyond the shapes of the annotation.
+    fn spread(&self) -> f32 {
+        match self.kind {
+            LayerKind::Shadow(step) => {
+                self.style.shadow_offset() * (SHADOW_STEPS - step) as f32 / SHADOW_STEPS as f32
+            }
+            LayerKind::Outline => self.style.outline_width(),
+            LayerKind::Annotation => 0.0,
+        }
+    }
+
+    fn offset(&self) -> f32 {
+        match self.kind {
+            LayerKind::Shadow(_) => self.style.shadow_offset(),
+            _ => 0.0,
+        }
+    }
+
+    /// The color the layer paints instead of `color`.
+    pub fn color(&self, color: Color) -> Color {
+        match self.kind {
+            LayerKind::Shadow(_) => Color::new(
+                0,
+                0,
+                0,
+                (color.a as f32 * SHADOW_OPACITY / SHADOW_STEPS as f32).round() as u8,
+            ),
+            LayerKind::Outline => Color {
+                a: color.a,
+                ..color.contrasting()
+            },
+            LayerKind::Annotation => color,
+        }
+    }
+
+    fn paint_offset(&self, canvas: &mut Canvas<OpenGl>, paint: impl FnOnce(&mut Canvas<OpenGl>)) {
+        let offset = self.offset();
+        canvas.save();
+        canvas.translate(offset, offset);
+        paint(canvas);
+        canvas.restore();
+    }
+
+    /// Strokes the path in the style of the annotation, femtovg has no dashes so they are cut
+    /// out of the path beforehand.
+    pub fn stroke_path(&self, canvas: &mut Canvas<OpenGl>, path: &Path) {
+        let width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor);
+        self.paint_offset(canvas, |canvas| self.stroke(canvas, path, width, true));
+    }
+
+    /// Strokes the path with a width of its own and without dashes, e.g. arrow heads.
+    pub fn stroke_solid(&self, canvas: &mut Canvas<OpenGl>, path: &Path, width: f32) {
+        self.paint_offset(canvas, |canvas| self.stroke(canvas, path, width, false));
+    }
+
+    fn stroke(&self, canvas: &mut Canvas<OpenGl>, path: &Path, width: f32, dashed: bool) {
+        let mut paint: Paint = Style {
+            color: self.color(self.style.color),
+            ..self.style
+        }
+        .into();
+        paint.set_line_width(width + 2.0 * self.spread());
+        if !dashed {
+            paint.set_line_cap(APP_CONFIG.read().line_caps().into());
+        }
+        match self.style.dash_pattern().filter(|_| dashed) {
+            Some(pattern) => canvas.stroke_path(&dash_path(path, pattern), &paint),
+            None => canvas.stroke_path(path, &paint),
+        }
+    }
+
+    /// Fills the path in `color`, effect layers grow it by their spread.
+    pub fn fill_solid(&self, canvas: &mut Canvas<OpenGl>, path: &Path, color: Color) {
+        self.paint_offset(canvas, |canvas| self.fill(canvas, path, color, true));
+    }
+
+    fn fill(&self, canvas: &mut Canvas<OpenGl>, path: &Path, color: Color, inside: bool) {
+        let color = self.color(color);
+        if inside {
+            canvas.fill_path(path, &Paint::color(color.into()).with_anti_alias(true));
+        }
+        let spread = self.spread();
+        if spread > 0.0 {
+            let rim = Paint::color(color.into())
+                .with_anti_alias(true)
+                .with_line_width(2.0 * spread)
+                .with_line_join(LineJoin::Round);
+            canvas.stroke_path(path, &rim);
+        }
+    }
+
+    /// Fills the path, shapes with a fill color of their own get an outline as well.
+    pub fn fill_path(&self, canvas: &mut Canvas<OpenGl>, path: &Path) {
+        let width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor);
+        self.paint_offset(canvas, |canvas| {
+            // the outline only surrounds the shape, translucent fills would show it inside
+            let inside = self.kind != LayerKind::Outline;
+            self.fill(canvas, path, self.style.effective_fill_color(), inside);
+            if self.style.fill_color.is_some() {
+                self.stroke(canvas, path, width, true);
+            }
+        });
+    }
+
+    /// Fills the text in the color of `paint`, effect layers stroke the glyphs by their spread.
+    pub fn fill_text(
+        &self,
+        canvas: &mut Canvas<OpenGl>,
+        x: f32,
+        y: f32,
+        text: &str,
+        paint: &Paint,
+    ) -> Result<()> {
+        let mut paint = paint.clone();
+        paint.set_color(self.color(self.style.color).into());
+        let spread = self.spread();
+
+        let offset = self.offset();
+        canvas.save();
+        canvas.translate(offset, offset);
+        let result = canvas.fill_text(x, y, text, &paint).and_then(|_| {
+            if spread > 0.0 {
+                paint.set_line_width(2.0 * spread);
+                paint.set_line_join(LineJoin::Round);
+                canvas.stroke_text(x, y, text, &paint)?;
+            }
+            Ok(())
+        });
+        canvas.restore();
+        Ok(result?)
+    }
+}
+
+// upper limit of straight segments a curve is flattened into
+const MAX_CURVE_SEGMENTS: usize = 64;
+
+fn flatten(path: &Path) -> Vec<Vec<(f32, f32)>> {
+    let mut contours: Vec<Vec<(f32, f32)>> = Vec::new();
+    for verb in path.verbs() {
+        match verb {
+            Verb::MoveTo(x, y) => contours.push(vec![(x, y)]),
+            Verb::LineTo(x, y) => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.push((x, y));
+                }
+            }
+            Verb::BezierTo(c1x, c1y, c2x, c2y, x, y) => {
+                let Some(contour) = contours.last_mut() else {
+                    continue;
+                };
+                let (x0, y0) = contour[contour.len() - 1];
+                // the control polygon is at least as long as the curve
+                let length: f32 = [(x0, y0), (c1x, c1y), (c2x, c2y), (x, y)]
+                    .windows(2)
+                    .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+                    .sum();
+                let segments = ((length / 4.0).ceil() as usize).clamp(1, MAX_CURVE_SEGMENTS);
+                for i in 1..=segments {
+                    let t = i as f32 / segments as f32;
+                    let u = 1.0 - t;
+                    let bezier = |p0: f32, p1: f32, p2: f32, p3: f32| {
+                        u * u * u * p0
+                            + 3.0 * u * u * t * p1
+                            + 3.0 * u * t * t * p2
+                            + t * t * t * p3
+                    };
+                    contour.push((bezier(x0, c1x, c2x, x), bezier(y0, c1y, c2y, y)));
+                }
+            }
+            Verb::Close => {
+                if let Some(contour) = contours.last_mut() {
+                    contour.push(contour[0]);
+                }
+            }
+            Verb::Solid | Verb::Hole => (),
         }
     }
+    contours
+}
+
+fn dash_path(path: &Path, (dash, gap): (f32, f32)) -> Path {
+    if dash + gap <= f32::EPSILON {
+        return path.clone();
+    }
+
+    let mut dashed = Path::new();
+    for contour in flatten(path) {
+        // every contour starts with a dash
+        let mut drawing = true;
+        let mut remaining = dash;
+        let mut open = false;
+
+        for w in contour.windows(2) {
+            let ((x0, y0), (x1, y1)) = (w[0], w[1]);
+            let length = (x1 - x0).hypot(y1 - y0);
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let point_at = |distance: f32| {
+                let t = distance / length;
+                (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t)
+            };
+
+            let mut position = 0.0;
+            while length - position > remaining {
+                if drawing {
+                    if !open {
+                        let (x, y) = point_at(position);
+                        dashed.move_to(x, y);
+                    }
+                    let (x, y) = point_at(position + remaining);
+                    dashed.line_to(x, y);
+                    open = false;
+                }
+                position += remaining;
+                drawing = !drawing;
+                remaining = if drawing { dash } else { gap };
+            }
+
+            remaining -= length - position;
+            if drawing {
+                if !open {
+                    let (x, y) = point_at(position);
+                    dashed.move_to(x, y);
+                    open = true;
+                }
+                dashed.line_to(x1, y1);
+            }
+        }
+    }
+    dashed
 }
 
 impl Default for Color {
@@ -109,6 +511,18 @@ impl Color {
         Self::new(200, 37, 184, 255)
     }
 
+    /// Black or white, whichever is easier to read on top of this color.
+    pub fn contrasting(self) -> Self {
+        // https://en.wikipedia.org/wiki/Luma_(video)
+        let luminance =
+            (0.2126 * self.r as f32 + 0.7152 * self.g as f32 + 0.0722 * self.b as f32) / 255.0;
+        if luminance > 0.5 {
+            Self::new(0, 0, 0, 255)
+        } else {
+            Self::new(255, 255, 255, 255)
+        }
+    }
+
     pub fn to_rgba_f64(self) -> (f64, f64, f64, f64) {
         (
             (self.r as f64) / 255.0,
@@ -168,78 +582,218 @@ impl From<Style> for Paint {
             .with_font_size(value.size.to_text_size(value.annotation_size_factor) as f32)
             .with_color(value.color.into())
             .with_line_width(value.size.to_line_width(value.annotation_size_factor))
+            .with_line_cap(value.line_caps().into())
+    }
+}
+
+impl From<LineCaps> for LineCap {
+    fn from(caps: LineCaps) -> Self {
+        match caps {
+            LineCaps::Butt => LineCap::Butt,
+            LineCaps::Round => LineCap::Round,
+            LineCaps::Square => LineCap::Square,
+        }
+    }
+}
+
+impl From<command_line::LineCaps> for LineCaps {
+    fn from(caps: command_line::LineCaps) -> Self {
+        match caps {
+            command_line::LineCaps::Butt => Self::Butt,
+            command_line::LineCaps::Round => Self::Round,
+            command_line::LineCaps::Square => Self::Square,
+        }
     }
 }
 
-impl StaticVariantType for Size {
+impl From<command_line::LineDash> for LineDash {
+    fn from(dash: command_line::LineDash) -> Self {
+        match dash {
+            command_line::LineDash::Solid => Self::Solid,
+            command_line::LineDash::Dashed => Self::Dashed,
+            command_line::LineDash::Dotted => Self::Dotted,
+        }
+    }
+}
+
+impl StaticVariantType for LineDash {
     fn static_variant_type() -> Cow<'static, VariantTy> {
         Cow::Borrowed(VariantTy::UINT32)
     }
 }
 
-impl ToVariant for Size {
+impl ToVariant for LineDash {
     fn to_variant(&self) -> Variant {
         Variant::from(*self as u32)
     }
 }
 
-impl FromVariant for Size {
+impl FromVariant for LineDash {
     fn from_variant(variant: &Variant) -> Option<Self> {
         variant.get::<u32>().and_then(|v| match v {
-            0 => Some(Size::Small),
-            1 => Some(Size::Medium),
-            2 => Some(Size::Large),
+            0 => Some(LineDash::Solid),
+            1 => Some(LineDash::Dashed),
+            2 => Some(LineDash::Dotted),
             _ => None,
         })
     }
 }
 
 impl Size {
-    pub fn to_text_size(self, size_factor: f32) -> i32 {
-        match self {
-            Size::Small => (36.0 * size_factor) as i32,
-            Size::Medium => (54.0 * size_factor) as i32,
-            Size::Large => (96.0 * size_factor) as i32,
+    // stroke widths of the presets, the other measures match the former fixed sizes at these
+    const SMALL_WIDTH: f32 = 3.0;
+    const MEDIUM_WIDTH: f32 = 5.0;
+    const LARGE_WIDTH: f32 = 7.0;
+
+    const MIN_WIDTH: f32 = 0.5;
+
+    pub fn new(width: f32) -> Self {
+        Self(width.max(Self::MIN_WIDTH))
+    }
+
+    pub fn width(self) -> f32 {
+        self.0
+    }
+
+    /// Next of the configured size steps, or the previous one for negative `steps`.
+    pub fn step(self, steps: i32) -> Self {
+        let config = APP_CONFIG.read();
+        let widths = config.size_steps();
+        let mut width = self.0;
+        for _ in 0..steps.unsigned_abs() {
+            let next = if steps > 0 {
+                widths.iter().copied().find(|&w| w > width)
+            } else {
+                widths.iter().rev().copied().find(|&w| w < width)
+            };
+            match next {
+                Some(w) => width = w,
+                None => break,
+            }
         }
+        Self::new(width)
     }
 
-    pub fn to_line_width(self, size_factor: f32) -> f32 {
-        match self {
-            Size::Small => 3.0 * size_factor,
-            Size::Medium => 5.0 * size_factor,
-            Size::Large => 7.0 * size_factor,
+    /// Maps the width onto a measure that has the given values at the small, medium and large
+    /// widths. In between the value is interpolated, outside of them it grows proportionally.
+    fn interpolate(self, [small, medium, large]: [f32; 3]) -> f32 {
+        let width = self.0;
+        if width <= Self::SMALL_WIDTH {
+            small * width / Self::SMALL_WIDTH
+        } else if width <= Self::MEDIUM_WIDTH {
+            let t = (width - Self::SMALL_WIDTH) / (Self::MEDIUM_WIDTH - Self::SMALL_WIDTH);
+            small + (medium - small) * t
+        } else if width <= Self::LARGE_WIDTH {
+            let t = (width - Self::MEDIUM_WIDTH) / (Self::LARGE_WIDTH - Self::MEDIUM_WIDTH);
+            medium + (large - medium) * t
+        } else {
+            large * width / Self::LARGE_WIDTH
         }
     }
 
+    pub fn to_text_size(self, size_factor: f32) -> i32 {
+        (self.interpolate([36.0, 54.0, 96.0]) * size_factor) as i32
+    }
+
+    pub fn to_line_width(self, size_factor: f32) -> f32 {
+        self.0 * size_factor
+    }
+
     pub fn to_arrow_tail_width(self, size_factor: f32) -> f32 {
-        match self {
-            Size::Small => 3.0 * size_factor,
-            Size::Medium => 10.0 * size_factor,
-            Size::Large => 25.0 * size_factor,
-        }
+        self.interpolate([3.0, 10.0, 25.0]) * size_factor
     }
 
     pub fn to_arrow_head_length(self, size_factor: f32) -> f32 {
-        match self {
-            Size::Small => 15.0 * size_factor,
-            Size::Medium => 30.0 * size_factor,
-            Size::Large => 60.0 * size_factor,
-        }
+        self.interpolate([15.0, 30.0, 60.0]) * size_factor
     }
 
     pub fn to_blur_factor(self, size_factor: f32) -> f32 {
-        match self {
-            Size::Small => 10.0 * size_factor,
-            Size::Medium => 20.0 * size_factor,
-            Size::Large => 30.0 * size_factor,
-        }
+        self.interpolate([10.0, 20.0, 30.0]) * size_factor
+    }
+
+    pub fn to_pixelate_size(self, size_factor: f32) -> f32 {
+        self.interpolate([8.0, 16.0, 32.0]) * size_factor
     }
 
     pub fn to_highlight_width(self, size_factor: f32) -> f32 {
-        match self {
-            Size::Small => 15.0 * size_factor,
-            Size::Medium => 30.0 * size_factor,
-            Size::Large => 45.0 * size_factor,
+        self.interpolate([15.0, 30.0, 45.0]) * size_factor
+    }
+
+    /// How much the magnifier enlarges, independent of the annotation size factor.
+    pub fn to_zoom_factor(self) -> f32 {
+        self.interpolate([1.5, 2.0, 3.0]).clamp(1.25, 8.0)
+    }
+}
+
+impl Default for Size {
+    fn default() -> Self {
+        SizePreset::Medium.into()
+    }
+}
+
+impl From<SizePreset> for Size {
+    fn from(preset: SizePreset) -> Self {
+        Self(match preset {
+            SizePreset::Small => Self::SMALL_WIDTH,
+            SizePreset::Medium => Self::MEDIUM_WIDTH,
+            SizePreset::Large => Self::LARGE_WIDTH,
+        })
+    }
+}
+
+impl From<SizeValue> for Size {
+    fn from(value: SizeValue) -> Self {
+        match value {
+            SizeValue::Width(width) => Self::new(width),
+            SizeValue::Preset(preset) => preset.into(),
+        }
+    }
+}
+
+impl From<Size> for f32 {
+    fn from(size: Size) -> Self {
+        size.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Stored {
+        size: Size,
+    }
+
+    fn parse(size: &str) -> Size {
+        toml::from_str::<Stored>(&format!("size = {size}"))
+            .unwrap()
+            .size
+    }
+
+    #[test]
+    fn size_reads_presets_of_old_projects() {
+        assert_eq!(parse(r#""small""#), Size::from(SizePreset::Small));
+        assert_eq!(parse(r#""medium""#), Size::from(SizePreset::Medium));
+        assert_eq!(parse(r#""large""#), Size::from(SizePreset::Large));
+        assert_eq!(parse("4.5"), Size::new(4.5));
+    }
+
+    #[test]
+    fn lines_without_width_are_not_dashed() {
+        for dash in [LineDash::Dashed, LineDash::Dotted] {
+            let style = Style {
+                dash,
+                annotation_size_factor: 0.0,
+                ..Style::default()
+            };
+            assert_eq!(style.dash_pattern(), None);
         }
+
+        let mut path = Path::new();
+        path.move_to(0.0, 0.0);
+        path.line_to(10.0, 0.0);
+        // returns instead of looping forever
+        dash_path(&path, (0.0, 0.0));
     }
 }
@@ -1,19 +1,59 @@
 use anyhow::Result;
-use femtovg::{FontId, Path};
+use femtovg::Path;
 use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::{Angle, Vec2D},
+    command_line,
+    configuration::APP_CONFIG,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Angle, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
-    style::Style,
+    style::{LineDash, Style},
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
+
+// number of straight segments a curved shaft is made of
+const CURVE_SEGMENTS: usize = 32;
+
+/// How the head of an arrow without fill is drawn, filled arrows always have a solid head.
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrowHead {
+    /// Two lines meeting at the tip.
+    #[default]
+    Open,
+    /// A closed triangle, only its outline is drawn.
+    Outlined,
+}
+
+impl From<command_line::ArrowHead> for ArrowHead {
+    fn from(head: command_line::ArrowHead) -> Self {
+        match head {
+            command_line::ArrowHead::Open => Self::Open,
+            command_line::ArrowHead::Outlined => Self::Outlined,
+        }
+    }
+}
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Arrow {
     start: Vec2D,
     end: Option<Vec2D>,
+    /// Control point of the quadratic Bézier curve of a bent shaft.
+    #[serde(default)]
+    control: Option<Vec2D>,
+    /// Whether there is a head at the start as well.
+    #[serde(default)]
+    double: bool,
+    #[serde(default)]
+    head: ArrowHead,
     style: Style,
 }
 
@@ -24,6 +64,26 @@ pub struct ArrowTool {
     input_enabled: bool,
 }
 
+impl ArrowTool {
+    /// Moves the end, or with ALT bends the shaft so that it passes through the pointer.
+    /// CTRL adds a head at the start, SHIFT snaps the end to 15° steps.
+    fn update_arrow(arrow: &mut Arrow, event: &MouseEventMsg) {
+        let pos = arrow.start + event.pos;
+        match arrow.end {
+            Some(end) if event.modifier.intersects(ModifierType::ALT_MASK) => {
+                // the middle of a quadratic curve lies halfway between the control point and
+                // the middle of the chord
+                arrow.control = Some(pos * 2.0 - (arrow.start + end) * 0.5);
+            }
+            _ if event.modifier.intersects(ModifierType::SHIFT_MASK) => {
+                arrow.end = Some(arrow.start + event.pos.snapped_vector_15deg());
+            }
+            _ => arrow.end = Some(pos),
+        }
+        arrow.double = event.modifier.intersects(ModifierType::CONTROL_MASK);
+    }
+}
+
 impl Tool for ArrowTool {
     fn input_enabled(&self) -> bool {
         self.input_enabled
@@ -44,6 +104,9 @@ impl Tool for ArrowTool {
                 self.arrow = Some(Arrow {
                     start: event.pos,
                     end: None,
+                    control: None,
+                    double: false,
+                    head: APP_CONFIG.read().arrow_head(),
                     style: self.style,
                 });
 
@@ -56,11 +119,7 @@ impl Tool for ArrowTool {
 
                         ToolUpdateResult::Redraw
                     } else {
-                        if event.modifier.intersects(ModifierType::SHIFT_MASK) {
-                            a.end = Some(a.start + event.pos.snapped_vector_15deg());
-                        } else {
-                            a.end = Some(a.start + event.pos);
-                        }
+                        Self::update_arrow(a, &event);
                         let result = a.clone_box();
                         self.arrow = None;
 
@@ -75,11 +134,7 @@ impl Tool for ArrowTool {
                     if event.pos == Vec2D::zero() {
                         return ToolUpdateResult::Unmodified;
                     }
-                    if event.modifier.intersects(ModifierType::SHIFT_MASK) {
-                        a.end = Some(a.start + event.pos.snapped_vector_15deg());
-                    } else {
-                        a.end = Some(a.start + event.pos);
-                    }
+                    Self::update_arrow(a, &event);
 
                     ToolUpdateResult::Redraw
                 } else {
@@ -108,63 +163,77 @@ impl Tool for ArrowTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(arrow) = &mut self.arrow {
+            arrow.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 }
 
-impl Drawable for Arrow {
-    fn draw(
-        &self,
-        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
-        _bounds: (Vec2D, Vec2D),
-    ) -> Result<()> {
-        let end = match self.end {
-            Some(e) => e,
-            None => return Ok(()), // exit if no end
-        };
+/// Sizes of the arrow head, measured from the tip.
+struct HeadGeometry {
+    /// Distance from the tip to the head sides along the shaft.
+    depth: f32,
+    /// Distance of the head sides from the shaft.
+    half_width: f32,
+    /// Distance from the tip to the midpoint along the shaft.
+    midpoint_depth: f32,
+    tail_half_width: f32,
+}
 
-        // Fat arrow:
-        //          C
-        //  E       #
-        //    ######G###
-        //  A ######D##### B
-        //    ##########
-        //  F       #
-        //
-        //
-        // Thin arrow:
-        //          C
-        //           \
-        //  A -------- B
-        //           /
-        //
-        // A: start
-        // B: end
-        // C: head side
-        // D: midpoint
-        // E: tail side
-        // F: tail side
-        // G: the cross-section of C - D on the tail side.
-        // Head: the point of the head at the end of the arrow (2, 3, 4).
-        // Tail: the line from the start to the midpoint (1 - 4).
-        // Side: the sloped side of the arrow head (3 - 2).
-        // Midpoint: where the tail ends and the head begins (4).
-        // Arrow length: the distance from the start to the end (1 - 2).
-        // Head angle: the angle of the head point at end (2).
-        // Tail width: the distance from tail side to tail side (5 - 6).
-
-        let arrow_offset = end - self.start;
-        let arrow_length = arrow_offset.norm();
-        let arrow_direction = arrow_offset * (1.0 / arrow_length);
-
-        // We rotate the canvas so that we can draw the arrow on the x-axis.
-        // start will be at (0,0)
-        // end will be at (length, 0)
-        canvas.save();
-        canvas.translate(self.start.x, self.start.y);
-        canvas.rotate(arrow_direction.angle().radians);
+fn unit(v: Vec2D) -> Vec2D {
+    let norm = v.norm();
+    if norm <= f32::EPSILON {
+        Vec2D::zero()
+    } else {
+        v * (1.0 / norm)
+    }
+}
+
+fn normal(direction: Vec2D) -> Vec2D {
+    Vec2D::new(-direction.y, direction.x)
+}
+
+impl Arrow {
+    // Fat arrow:
+    //          C
+    //  E       #
+    //    ######G###
+    //  A ######D##### B
+    //    ##########
+    //  F       #
+    //
+    //
+    // Thin arrow:
+    //          C
+    //           \
+    //  A -------- B
+    //           /
+    //
+    // A: start
+    // B: end
+    // C: head side
+    // D: midpoint
+    // E: tail side
+    // F: tail side
+    // G: the cross-section of C - D on the tail side.
+    // Head: the point of the head at the end of the arrow (2, 3, 4).
+    // Tail: the line from the start to the midpoint (1 - 4).
+    // Side: the sloped side of the arrow head (3 - 2).
+    // Midpoint: where the tail ends and the head begins (4).
+    // Arrow length: the distance from the start to the end (1 - 2).
+    // Head angle: the angle of the head point at end (2).
+    // Tail width: the distance from tail side to tail side (5 - 6).
+    //
+    // A double headed arrow has a mirrored head at A, a curved arrow bends the tail between
+    // the heads, which point along the curve.
 
+    /// Computes the position of the head side C, the midpoint D and half of the tail width
+    /// relative to the tip B (see the sketch above).
+    fn head_geometry(&self) -> HeadGeometry {
         // The width of the tail (double distance from start to head side)
         let tail_width = self
             .style
@@ -183,43 +252,329 @@ impl Drawable for Arrow {
 
         let head_angle = Angle::from_degrees(60.0); // The angle of the point of the arrow head.
 
-        let tail_half_width = tail_width / 2.0;
-        let head_half_angle = head_angle * 0.5;
-        let head_left =
-            Vec2D::new(arrow_length, 0.0) - Vec2D::from_angle(head_half_angle) * head_side_length;
-        let midpoint_x = head_left.x + midpoint_offset;
+        let head_side = Vec2D::from_angle(head_angle * 0.5) * head_side_length;
+        HeadGeometry {
+            depth: head_side.x,
+            half_width: head_side.y,
+            midpoint_depth: head_side.x - midpoint_offset,
+            tail_half_width: tail_width / 2.0,
+        }
+    }
+
+    fn control_point(&self, end: Vec2D) -> Vec2D {
+        self.control.unwrap_or((self.start + end) * 0.5)
+    }
+
+    /// Directions the heads point to, at the start (backwards) and at the end.
+    fn head_directions(&self, end: Vec2D) -> (Vec2D, Vec2D) {
+        let control = self.control_point(end);
+        let chord = unit(end - self.start);
+        let start_direction = unit(self.start - control);
+        let end_direction = unit(end - control);
+        (
+            if start_direction.is_zero() {
+                chord * -1.0
+            } else {
+                start_direction
+            },
+            if end_direction.is_zero() {
+                chord
+            } else {
+                end_direction
+            },
+        )
+    }
+
+    /// Points along the shaft, a straight arrow has just its start and end.
+    fn shaft_points(&self, end: Vec2D) -> Vec<Vec2D> {
+        match self.control {
+            None => vec![self.start, end],
+            Some(control) => (0..=CURVE_SEGMENTS)
+                .map(|i| {
+                    let t = i as f32 / CURVE_SEGMENTS as f32;
+                    self.start * ((1.0 - t) * (1.0 - t))
+                        + control * (2.0 * (1.0 - t) * t)
+                        + end * (t * t)
+                })
+                .collect(),
+        }
+    }
+
+    /// The shaft without the parts covered by the heads, `trim_start` and `trim_end` are the
+    /// distances cut off at both ends. Empty if nothing is left.
+    fn trimmed_shaft(&self, end: Vec2D, trim_start: f32, trim_end: f32) -> Vec<Vec2D> {
+        let points = self.shaft_points(end);
+        let length: f32 = points.windows(2).map(|w| w[0].distance_to(&w[1])).sum();
+        if length <= trim_start + trim_end {
+            return Vec::new();
+        }
+
+        let (start_direction, end_direction) = self.head_directions(end);
+        let inner = points[1..points.len() - 1]
+            .iter()
+            .copied()
+            .filter(|p| p.distance_to(&self.start) > trim_start && p.distance_to(&end) > trim_end);
+        std::iter::once(self.start - start_direction * trim_start)
+            .chain(inner)
+            .chain(std::iter::once(end - end_direction * trim_end))
+            .collect()
+    }
+
+    /// C, B and the mirrored C of a head with its tip at `tip`.
+    fn head_points(geometry: &HeadGeometry, tip: Vec2D, direction: Vec2D) -> [Vec2D; 3] {
+        let base = tip - direction * geometry.depth;
+        let side = normal(direction) * geometry.half_width;
+        [base + side, tip, base - side]
+    }
+
+    /// Outline of a filled arrow.
+    fn fat_outline(&self, end: Vec2D) -> Vec<Vec2D> {
+        let geometry = self.head_geometry();
+        let (start_direction, end_direction) = self.head_directions(end);
+
+        let start_trim = if self.double {
+            geometry.midpoint_depth
+        } else {
+            0.0
+        };
+        let mut shaft = self.trimmed_shaft(end, start_trim, geometry.midpoint_depth);
+        if shaft.is_empty() && !self.double {
+            // If the midpoint is placed _before_ the start, there is only a head and no tail.
+            shaft.push(end - end_direction * geometry.midpoint_depth);
+        }
+
+        // the tail sides (E - G) left and right of the shaft
+        let last = shaft.len().saturating_sub(1);
+        let sides: Vec<_> = shaft
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let direction = if i == last {
+                    end_direction
+                } else if i == 0 {
+                    start_direction * -1.0
+                } else {
+                    unit(shaft[i + 1] - shaft[i - 1])
+                };
+                (
+                    *p + normal(direction) * geometry.tail_half_width,
+                    *p - normal(direction) * geometry.tail_half_width,
+                )
+            })
+            .collect();
+
+        let mut outline: Vec<_> = sides.iter().map(|(left, _)| *left).collect();
+        outline.extend(Self::head_points(&geometry, end, end_direction));
+        outline.extend(sides.iter().rev().map(|(_, right)| *right));
+        if self.double {
+            outline.extend(Self::head_points(&geometry, self.start, start_direction));
+        }
+        outline
+    }
+
+    /// Lines of an arrow without fill, each with whether it is closed.
+    /// Heads and shaft of an arrow without fill, only the shaft gets the dashes of the style.
+    fn thin_lines(&self, end: Vec2D) -> (Vec<(Vec<Vec2D>, bool)>, Vec<Vec2D>) {
+        let geometry = self.head_geometry();
+        let (start_direction, end_direction) = self.head_directions(end);
+
+        // an outlined head is hollow, the shaft must not reach into it
+        let (closed, trim) = match self.head {
+            ArrowHead::Open => (false, 0.0),
+            ArrowHead::Outlined => (true, geometry.depth),
+        };
+        let start_trim = if self.double { trim } else { 0.0 };
+
+        let mut heads = vec![(
+            Self::head_points(&geometry, end, end_direction).to_vec(),
+            closed,
+        )];
+        if self.double {
+            heads.push((
+                Self::head_points(&geometry, self.start, start_direction).to_vec(),
+                closed,
+            ));
+        }
+        (heads, self.trimmed_shaft(end, start_trim, trim))
+    }
+
+    fn head_style(&self) -> Style {
+        Style {
+            dash: LineDash::Solid,
+            ..self.style
+        }
+    }
+}
+
+impl Drawable for Arrow {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let end = match self.end {
+            Some(e) => e,
+            None => return Ok(()), // exit if no end
+        };
+        if (end - self.start).is_zero() {
+            return Ok(());
+        }
+
+        canvas.save();
 
         if self.style.fill {
             // Draw a 'fat' arrow.
             let mut path = Path::new();
-            path.move_to(midpoint_x, tail_half_width); // G
-            path.line_to(head_left.x, -head_left.y); // C
-            path.line_to(arrow_length, 0.0); // B
-            path.line_to(head_left.x, head_left.y); // C (mirrored)
-            path.line_to(midpoint_x, -tail_half_width); // G (mirrored)
-            if midpoint_x > 0.0 {
-                // If the midpoint is placed _before_ the start, there is only a head and no tail.
-                // We can skip the beginning of the tail.
-                path.line_to(0.0, -tail_half_width); // F
-                path.line_to(0.0, tail_half_width); // E
+            let outline = self.fat_outline(end);
+            path.move_to(outline[0].x, outline[0].y);
+            for p in &outline[1..] {
+                path.line_to(p.x, p.y);
             }
             path.close();
 
-            canvas.fill_path(&path, &self.style.into());
+            for layer in self.style.layers() {
+                layer.fill_solid(canvas, &path, self.style.color);
+            }
         } else {
-            // Draw a 'thin' arrow head.
-            let mut path = Path::new();
-            path.move_to(head_left.x, -head_left.y); // C
-            path.line_to(arrow_length, 0.0); // B
-            path.line_to(head_left.x, head_left.y); // C (mirrored)
+            // Draw a 'thin' arrow.
+            let (heads, shaft) = self.thin_lines(end);
+            let mut heads_path = Path::new();
+            for (points, closed) in heads {
+                heads_path.move_to(points[0].x, points[0].y);
+                for p in &points[1..] {
+                    heads_path.line_to(p.x, p.y);
+                }
+                if closed {
+                    heads_path.close();
+                }
+            }
 
-            path.move_to(0.0, 0.0); // A
-            path.line_to(arrow_length, 0.0); // B
+            let mut shaft_path = Path::new();
+            if !shaft.is_empty() {
+                shaft_path.move_to(shaft[0].x, shaft[0].y);
+                for p in &shaft[1..] {
+                    shaft_path.line_to(p.x, p.y);
+                }
+            }
 
-            canvas.stroke_path(&path, &self.style.into());
+            // heads stay solid on dashed arrows
+            let width = self
+                .style
+                .size
+                .to_line_width(self.style.annotation_size_factor);
+            for layer in self.style.layers() {
+                layer.stroke_solid(canvas, &heads_path, width);
+                layer.stroke_path(canvas, &shaft_path);
+            }
         }
 
         canvas.restore();
         Ok(())
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.end
+            .and_then(|end| math::points_bounding_box(self.shaft_points(end)))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        match self.end {
+            Some(end) => {
+                let half_width = self
+                    .style
+                    .size
+                    .to_arrow_tail_width(self.style.annotation_size_factor)
+                    / 2.0;
+                self.shaft_points(end)
+                    .windows(2)
+                    .any(|w| math::distance_to_segment(pos, w[0], w[1]) <= half_width + tolerance)
+            }
+            None => false,
+        }
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Arrow(*self))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let length: f32 = self.end.map_or(0.0, |end| {
+            self.shaft_points(end)
+                .windows(2)
+                .map(|w| w[0].distance_to(&w[1]))
+                .sum()
+        });
+        Some(DrawableDescription::new(
+            Tools::Arrow,
+            Some(self.style.color),
+            format!("{length:.0} px"),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let end = self.end?;
+        if (end - self.start).is_zero() {
+            return None;
+        }
+
+        let element = if self.style.fill {
+            format!(
+                r#"<polygon points="{}" {}/>"#,
+                export::svg_points(&self.fat_outline(end)),
+                export::svg_paint(&self.style, true)
+            )
+        } else {
+            let path_data = |points: &[Vec2D], closed: bool| {
+                let line = points
+                    .iter()
+                    .map(|p| format!("{} {}", p.x, p.y))
+                    .collect::<Vec<_>>()
+                    .join(" L ");
+                format!("M {line}{}", if closed { " Z" } else { "" })
+            };
+
+            let (heads, shaft) = self.thin_lines(end);
+            let data = heads
+                .iter()
+                .map(|(points, closed)| path_data(points, *closed))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let mut svg = format!(
+                r#"<path d="{data}" {}/>"#,
+                export::svg_paint(&self.head_style(), false)
+            );
+            if !shaft.is_empty() {
+                svg += &format!(
+                    r#"<path d="{}" {}/>"#,
+                    path_data(&shaft, false),
+                    export::svg_paint(&self.style, false)
+                );
+            }
+            svg
+        };
+        Some(export::svg_with_effects(&self.style, id, element))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.start += offset;
+        self.end = self.end.map(|end| end + offset);
+        self.control = self.control.map(|control| control + offset);
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        self.start = math::rect_map_point(self.start, from, to);
+        self.end = self.end.map(|end| math::rect_map_point(end, from, to));
+        self.control = self
+            .control
+            .map(|control| math::rect_map_point(control, from, to));
+    }
 }
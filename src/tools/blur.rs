@@ -1,61 +1,234 @@
 use std::cell::RefCell;
 
 use anyhow::Result;
-use femtovg::{imgref::Img, Color, ImageFilter, ImageFlags, ImageId, Paint, Path};
+use femtovg::{
+    imgref::{Img, ImgVec},
+    rgb::RGBA8,
+    Color, ImageFilter, ImageFlags, ImageId, Paint, Path,
+};
 
-use relm4::gtk::gdk::Key;
+use gdk_pixbuf::Pixbuf;
+use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
+    command_line,
     configuration::APP_CONFIG,
+    femtovg_area::Fonts,
     math::{self, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
     style::Style,
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
+
+#[derive(
+    Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum BlurModes {
+    #[default]
+    Gaussian = 0,
+    Pixelate = 1,
+    Blackbox = 2,
+}
+
+impl From<command_line::BlurModes> for BlurModes {
+    fn from(mode: command_line::BlurModes) -> Self {
+        match mode {
+            command_line::BlurModes::Gaussian => Self::Gaussian,
+            command_line::BlurModes::Pixelate => Self::Pixelate,
+            command_line::BlurModes::Blackbox => Self::Blackbox,
+        }
+    }
+}
+
+impl BlurModes {
+    /// Without modifier the primary mode is used, CTRL selects pixelate and SHIFT black box.
+    /// If that is the primary mode already, the modifier selects gaussian instead.
+    pub fn for_modifier(modifier: ModifierType) -> Self {
+        let primary = APP_CONFIG.read().primary_blur();
+        let secondary = if modifier.intersects(ModifierType::CONTROL_MASK) {
+            Some(Self::Pixelate)
+        } else if modifier.intersects(ModifierType::SHIFT_MASK) {
+            Some(Self::Blackbox)
+        } else {
+            None
+        };
+
+        match secondary {
+            Some(mode) if mode == primary => Self::Gaussian,
+            Some(mode) => mode,
+            None => primary,
+        }
+    }
+}
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Blur {
     top_left: Vec2D,
     size: Option<Vec2D>,
     style: Style,
     editing: bool,
+    #[serde(default)]
+    mode: BlurModes,
+    // only the background is redacted, never other annotations
+    #[serde(skip)]
+    background: Option<Pixbuf>,
+    #[serde(skip)]
     cached_image: RefCell<Option<ImageId>>,
 }
 
 impl Blur {
-    fn blur(
-        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        pos: Vec2D,
-        size: Vec2D,
-        sigma: f32,
-    ) -> Result<ImageId> {
-        let img = canvas.screenshot()?;
+    /// Area of the background covered by the blur, in whole pixels.
+    fn background_region(&self, background: &Pixbuf) -> Option<(i32, i32, i32, i32)> {
+        math::pixel_region(
+            self.bounding_box()?,
+            background.width(),
+            background.height(),
+        )
+    }
 
-        let transformed_pos = canvas.transform().transform_point(pos.x, pos.y);
-        let transformed_size = size * canvas.transform().average_scale();
+    pub(super) fn background_pixels(
+        background: &Pixbuf,
+        region: (i32, i32, i32, i32),
+    ) -> ImgVec<RGBA8> {
+        let (x, y, width, height) = region;
+        let bytes = background.read_pixel_bytes();
+        let stride = background.rowstride() as usize;
+        let channels = background.n_channels() as usize;
 
-        let (buf, width, height) = img
-            .sub_image(
-                transformed_pos.0 as usize,
-                transformed_pos.1 as usize,
-                (transformed_size.x as usize).max(1),
-                (transformed_size.y as usize).max(1),
-            )
-            .to_contiguous_buf();
-        let sub = Img::new(buf.into_owned(), width, height);
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for row in y as usize..(y + height) as usize {
+            for column in x as usize..(x + width) as usize {
+                let offset = row * stride + column * channels;
+                let p = &bytes[offset..offset + channels];
+                let alpha = if channels == 4 { p[3] } else { 255 };
+                pixels.push(RGBA8::new(p[0], p[1], p[2], alpha));
+            }
+        }
+        Img::new(pixels, width as usize, height as usize)
+    }
 
-        let src_image_id = canvas.create_image(sub.as_ref(), ImageFlags::empty())?;
+    /// Replaces every block of `block_size` pixels by its average color.
+    fn pixelate(image: &mut ImgVec<RGBA8>, block_size: usize) {
+        let (width, height) = (image.width(), image.height());
+        let block_size = block_size.max(1);
+
+        for block_y in (0..height).step_by(block_size) {
+            for block_x in (0..width).step_by(block_size) {
+                let rows = block_y..(block_y + block_size).min(height);
+                let columns = block_x..(block_x + block_size).min(width);
+
+                let mut sum = [0u32; 4];
+                for row in rows.clone() {
+                    for p in &image.buf()[row * width + columns.start..row * width + columns.end] {
+                        sum[0] += p.r as u32;
+                        sum[1] += p.g as u32;
+                        sum[2] += p.b as u32;
+                        sum[3] += p.a as u32;
+                    }
+                }
+                let count = (rows.len() * columns.len()) as u32;
+                let average = RGBA8::new(
+                    (sum[0] / count) as u8,
+                    (sum[1] / count) as u8,
+                    (sum[2] / count) as u8,
+                    (sum[3] / count) as u8,
+                );
+
+                for row in rows {
+                    image.buf_mut()[row * width + columns.start..row * width + columns.end]
+                        .fill(average);
+                }
+            }
+        }
+    }
+
+    /// Approximates a gaussian blur by three box blurs in both directions, for exports that
+    /// cannot use the GPU filter.
+    fn gaussian_blur(image: &mut ImgVec<RGBA8>, sigma: f32) {
+        let box_width = (4.0 * sigma * sigma + 1.0).sqrt();
+        let radius = ((box_width - 1.0) / 2.0).round().max(1.0) as usize;
+        for _ in 0..3 {
+            Self::box_blur(image, radius, true);
+            Self::box_blur(image, radius, false);
+        }
+    }
+
+    fn box_blur(image: &mut ImgVec<RGBA8>, radius: usize, horizontal: bool) {
+        let (width, height) = (image.width(), image.height());
+        let (lines, length) = if horizontal {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let index = |line: usize, i: usize| {
+            if horizontal {
+                line * width + i
+            } else {
+                i * width + line
+            }
+        };
+
+        let source = image.buf().to_vec();
+        for line in 0..lines {
+            for i in 0..length {
+                let range = i.saturating_sub(radius)..(i + radius + 1).min(length);
+                let count = range.len() as u32;
+                let mut sum = [0u32; 4];
+                for p in range.map(|j| source[index(line, j)]) {
+                    sum[0] += p.r as u32;
+                    sum[1] += p.g as u32;
+                    sum[2] += p.b as u32;
+                    sum[3] += p.a as u32;
+                }
+                image.buf_mut()[index(line, i)] = RGBA8::new(
+                    (sum[0] / count) as u8,
+                    (sum[1] / count) as u8,
+                    (sum[2] / count) as u8,
+                    (sum[3] / count) as u8,
+                );
+            }
+        }
+    }
+
+    fn redacted_image(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        background: &Pixbuf,
+        region: (i32, i32, i32, i32),
+    ) -> Result<ImageId> {
+        let mut pixels = Self::background_pixels(background, region);
+        if self.mode == BlurModes::Pixelate {
+            Self::pixelate(
+                &mut pixels,
+                self.style
+                    .size
+                    .to_pixelate_size(self.style.annotation_size_factor) as usize,
+            );
+            // keep the blocks sharp when zoomed in
+            return Ok(canvas.create_image(pixels.as_ref(), ImageFlags::NEAREST)?);
+        }
+
+        let src_image_id = canvas.create_image(pixels.as_ref(), ImageFlags::empty())?;
         let dst_image_id = canvas.create_image_empty(
-            sub.width(),
-            sub.height(),
+            pixels.width(),
+            pixels.height(),
             femtovg::PixelFormat::Rgba8,
             ImageFlags::empty(),
         )?;
 
         canvas.filter_image(
             dst_image_id,
-            ImageFilter::GaussianBlur { sigma },
+            ImageFilter::GaussianBlur {
+                sigma: self
+                    .style
+                    .size
+                    .to_blur_factor(self.style.annotation_size_factor),
+            },
             src_image_id,
         );
         //canvas.delete_image(src_image_id);
@@ -68,7 +241,7 @@ impl Drawable for Blur {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: femtovg::FontId,
+        _fonts: Fonts,
         bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         let size = match self.size {
@@ -79,13 +252,11 @@ impl Drawable for Blur {
             math::rect_ensure_positive_size(self.top_left, size),
             bounds,
         );
-        if self.editing {
-            // set style
-            let mut color = Color::black();
-            color.set_alphaf(0.6);
-            let paint = Paint::color(color);
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return Ok(());
+        }
 
-            // make rect
+        if self.editing {
             let mut path = Path::new();
             path.rounded_rect(
                 pos.x,
@@ -94,61 +265,171 @@ impl Drawable for Blur {
                 size.y,
                 APP_CONFIG.read().corner_roundness(),
             );
+            let mut color = Color::black();
+            color.set_alphaf(0.6);
+            canvas.fill_path(&path, &Paint::color(color));
+            return Ok(());
+        }
 
-            // draw
-            canvas.fill_path(&path, &paint);
-        } else {
-            if size.x <= 0.0 || size.y <= 0.0 {
-                return Ok(());
+        // the redaction covers whole pixels, rounded corners or anti aliased edges would let
+        // parts of the original pixels through
+        let mut path = Path::new();
+        path.rect(
+            pos.x.floor(),
+            pos.y.floor(),
+            (pos.x + size.x).ceil() - pos.x.floor(),
+            (pos.y + size.y).ceil() - pos.y.floor(),
+        );
+        canvas.fill_path(&path, &Paint::color(Color::black()));
+
+        // without background a black box is all we can do
+        let (background, region) = match (&self.background, self.mode) {
+            (Some(background), BlurModes::Gaussian | BlurModes::Pixelate) => {
+                match self.background_region(background) {
+                    Some(region) => (background, region),
+                    None => return Ok(()),
+                }
             }
+            _ => return Ok(()),
+        };
+
+        canvas.save();
+
+        // create new cached image
+        if self.cached_image.borrow().is_none() {
+            let image = self.redacted_image(canvas, background, region)?;
+            self.cached_image.borrow_mut().replace(image);
+        }
+
+        canvas.fill_path(
+            &path,
+            &Paint::image(
+                self.cached_image.borrow().unwrap(), // this unwrap is safe because we placed it above
+                region.0 as f32,
+                region.1 as f32,
+                region.2 as f32,
+                region.3 as f32,
+                0f32,
+                1f32,
+            ),
+        );
+        canvas.restore();
+        Ok(())
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Blur(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+        // the blur strength depends on the size
+        self.cached_image.replace(None);
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let size = self
+            .size
+            .map(DrawableDescription::format_size)
+            .unwrap_or_default();
+        let details = match self.mode {
+            BlurModes::Gaussian => size,
+            BlurModes::Pixelate => format!("{size} pixelated"),
+            BlurModes::Blackbox => format!("{size} black box"),
+        };
+        Some(DrawableDescription::new(Tools::Blur, None, details))
+    }
+
+    fn redact(&self, background: &mut ImgVec<RGBA8>) {
+        let Some((x, y, width, height)) = self.bounding_box().and_then(|bounding_box| {
+            math::pixel_region(
+                bounding_box,
+                background.width() as i32,
+                background.height() as i32,
+            )
+        }) else {
+            return;
+        };
+        let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
 
-            canvas.save();
-            canvas.flush();
-
-            // create new cached image
-            if self.cached_image.borrow().is_none() {
-                self.cached_image.borrow_mut().replace(Self::blur(
-                    canvas,
-                    pos,
-                    size,
-                    self.style
-                        .size
-                        .to_blur_factor(self.style.annotation_size_factor),
-                )?);
+        let mut region = background.sub_image_mut(x, y, width, height);
+        if self.mode == BlurModes::Blackbox {
+            for row in region.rows_mut() {
+                row.fill(RGBA8::new(0, 0, 0, 255));
             }
+            return;
+        }
 
-            let mut path = Path::new();
-            path.rounded_rect(
-                pos.x,
-                pos.y,
-                size.x,
-                size.y,
-                APP_CONFIG.read().corner_roundness(),
+        let (buf, _, _) = region.as_ref().to_contiguous_buf();
+        let mut pixels = Img::new(buf.into_owned(), width, height);
+        if self.mode == BlurModes::Pixelate {
+            Self::pixelate(
+                &mut pixels,
+                self.style
+                    .size
+                    .to_pixelate_size(self.style.annotation_size_factor) as usize,
             );
-
-            canvas.fill_path(
-                &path,
-                &Paint::image(
-                    self.cached_image.borrow().unwrap(), // this unwrap is safe because we placed it above
-                    pos.x,
-                    pos.y,
-                    size.x,
-                    size.y,
-                    0f32,
-                    1f32,
-                ),
+        } else {
+            Self::gaussian_blur(
+                &mut pixels,
+                self.style
+                    .size
+                    .to_blur_factor(self.style.annotation_size_factor),
             );
-            canvas.restore();
         }
-        Ok(())
+        for (row, redacted) in region.rows_mut().zip(pixels.rows()) {
+            row.copy_from_slice(redacted);
+        }
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.top_left += offset;
+        // the blurred content changes with the position
+        self.cached_image.replace(None);
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            let bottom_right = math::rect_map_point(self.top_left + size, from, to);
+            self.top_left = top_left;
+            self.size = Some(bottom_right - top_left);
+            self.cached_image.replace(None);
+        }
     }
 }
 
-#[derive(Default)]
 pub struct BlurTool {
     blur: Option<Blur>,
     style: Style,
     input_enabled: bool,
+    background: Pixbuf,
+}
+
+impl BlurTool {
+    pub fn new(background: Pixbuf) -> Self {
+        Self {
+            blur: None,
+            style: Style::default(),
+            input_enabled: false,
+            background,
+        }
+    }
+
+    /// Links a blur loaded from a project file to the background it redacts.
+    pub fn restore(&self, mut blur: Blur) -> Blur {
+        blur.background = Some(self.background.clone());
+        blur
+    }
 }
 
 impl Tool for BlurTool {
@@ -173,6 +454,8 @@ impl Tool for BlurTool {
                     size: None,
                     style: self.style,
                     editing: true,
+                    mode: BlurModes::for_modifier(event.modifier),
+                    background: Some(self.background.clone()),
                     cached_image: RefCell::new(None),
                 });
 
@@ -224,7 +507,13 @@ impl Tool for BlurTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(blur) = &mut self.blur {
+            blur.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
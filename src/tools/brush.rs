@@ -1,15 +1,20 @@
 use std::time::Instant;
 
-use femtovg::{FontId, Path};
+use femtovg::Path;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
-    math::Vec2D,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
     sketch_board::{MouseButton, MouseEventMsg, MouseEventType},
     style::Style,
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
 
 #[derive(Default)]
 pub struct BrushTool {
@@ -18,12 +23,13 @@ pub struct BrushTool {
     input_enabled: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BrushDrawable {
     // The start point of the brush stroke this is relative to canvas
     // after this the points are relative to the start point
     start_point: Option<Vec2D>,
     points: Vec<Vec2D>,
+    #[serde(skip)]
     smoother: Smoother,
     style: Style,
 }
@@ -32,13 +38,22 @@ impl BrushDrawable {
     fn add_point(&mut self, point: Vec2D) {
         self.points.push(self.smoother.update(point));
     }
+
+    fn absolute_points(&self) -> Vec<Vec2D> {
+        match self.start_point {
+            Some(start_point) if !self.points.is_empty() => std::iter::once(start_point)
+                .chain(self.points.iter().skip(1).map(|p| start_point + *p))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
 }
 
 impl Drawable for BrushDrawable {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> anyhow::Result<()> {
         if self.points.is_empty() {
@@ -57,10 +72,92 @@ impl Drawable for BrushDrawable {
             path.line_to(start_point.x + p.x, start_point.y + p.y);
         }
 
-        canvas.stroke_path(&path, &self.style.into());
+        for layer in self.style.layers() {
+            layer.stroke_path(canvas, &path);
+        }
         canvas.restore();
         Ok(())
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        math::points_bounding_box(self.absolute_points())
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let max_distance = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+            / 2.0
+            + tolerance;
+        let points = self.absolute_points();
+        match points.as_slice() {
+            [single] => single.distance_to(&pos) <= max_distance,
+            _ => points
+                .windows(2)
+                .any(|w| math::distance_to_segment(pos, w[0], w[1]) <= max_distance),
+        }
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Brush(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Brush,
+            Some(self.style.color),
+            format!("{} points", self.points.len()),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let points = self.absolute_points();
+        if points.is_empty() {
+            return None;
+        }
+        Some(export::svg_with_effects(
+            &self.style,
+            id,
+            format!(
+                r#"<polyline points="{}" {}/>"#,
+                export::svg_points(&points),
+                export::svg_paint(&self.style, false)
+            ),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        // only the first point is absolute
+        self.start_point = self.start_point.map(|p| p + offset);
+        if let Some(first) = self.points.first_mut() {
+            *first += offset;
+        }
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        let Some(start_point) = self.start_point else {
+            return;
+        };
+
+        let new_start = math::rect_map_point(start_point, from, to);
+        for (i, p) in self.points.iter_mut().enumerate() {
+            *p = if i == 0 {
+                math::rect_map_point(*p, from, to)
+            } else {
+                math::rect_map_point(start_point + *p, from, to) - new_start
+            };
+        }
+        self.start_point = Some(new_start);
+    }
 }
 
 impl Tool for BrushTool {
@@ -116,6 +213,7 @@ impl Tool for BrushTool {
                 });
                 ToolUpdateResult::Unmodified
             }
+            MouseEventType::DoubleClick => ToolUpdateResult::Unmodified,
         }
     }
 
@@ -128,11 +226,17 @@ impl Tool for BrushTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(drawable) = &mut self.drawable {
+            drawable.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct Smoother {
     history: Vec<Vec2D>, // last N raw inputs
     smoothed_point: Option<Vec2D>,
@@ -0,0 +1,431 @@
+use std::cell::Cell;
+
+use anyhow::Result;
+use femtovg::Path;
+use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, TextEventMsg},
+    style::Style,
+};
+
+use super::{
+    text::Text, Drawable, DrawableClone, DrawableData, DrawableDescription, InputContext, Tool,
+    ToolUpdateResult, Tools,
+};
+
+// direction of the top, right, bottom and left edge when going around the box clockwise
+const EDGE_DIRECTIONS: [Vec2D; 4] = [
+    Vec2D { x: 1.0, y: 0.0 },
+    Vec2D { x: 0.0, y: 1.0 },
+    Vec2D { x: -1.0, y: 0.0 },
+    Vec2D { x: 0.0, y: -1.0 },
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Callout {
+    anchor: Vec2D,
+    text: Text,
+    style: Style,
+    // the box depends on the font metrics, so it is only known after drawing
+    #[serde(skip)]
+    area: Cell<Option<(Vec2D, Vec2D)>>,
+}
+
+impl Callout {
+    fn new(anchor: Vec2D, text_pos: Vec2D, style: Style, im_context: Option<InputContext>) -> Self {
+        Self {
+            anchor,
+            text: Text::new(text_pos, Self::text_style(style), im_context),
+            style,
+            area: Cell::new(None),
+        }
+    }
+
+    /// The text is black or white, like the number of a marker.
+    fn text_style(style: Style) -> Style {
+        Style {
+            color: style.effective_fill_color().contrasting(),
+            ..style
+        }
+    }
+
+    fn text_size(&self) -> f32 {
+        self.style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32
+    }
+
+    fn padding(&self) -> f32 {
+        self.text_size() * 0.4
+    }
+
+    /// The box around the text, estimated from the font size if it was never drawn.
+    fn box_area(&self) -> (Vec2D, Vec2D) {
+        self.area.get().unwrap_or_else(|| {
+            let text = self.text.text();
+            let lines = text.split('\n').count() as f32;
+            let columns = text
+                .split('\n')
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or_default() as f32;
+            let size = self.text_size();
+            let padding = self.padding();
+            (
+                self.text.pos() - Vec2D::new(padding, padding + size),
+                Vec2D::new(columns * size * 0.6, lines * size * 1.2)
+                    + Vec2D::new(padding, padding) * 2.0,
+            )
+        })
+    }
+
+    fn corner_radius(size: Vec2D) -> f32 {
+        APP_CONFIG
+            .read()
+            .corner_roundness()
+            .min(size.x.min(size.y) / 2.0)
+    }
+
+    /// Edge of the box the tail starts from and the tail corners in clockwise order,
+    /// there is no tail if the anchor is inside the box.
+    fn tail(&self, pos: Vec2D, size: Vec2D, radius: f32) -> Option<(usize, [Vec2D; 3])> {
+        if math::rect_contains((pos, size), self.anchor, 0.0) {
+            return None;
+        }
+
+        let center = pos + size * 0.5;
+        let direction = self.anchor - center;
+        let edge = if direction.x.abs() * size.y > direction.y.abs() * size.x {
+            if direction.x > 0.0 {
+                1
+            } else {
+                3
+            }
+        } else if direction.y > 0.0 {
+            2
+        } else {
+            0
+        };
+
+        let (middle, length) = match edge {
+            0 => (center - Vec2D::new(0.0, size.y / 2.0), size.x),
+            1 => (center + Vec2D::new(size.x / 2.0, 0.0), size.y),
+            2 => (center + Vec2D::new(0.0, size.y / 2.0), size.x),
+            _ => (center - Vec2D::new(size.x / 2.0, 0.0), size.y),
+        };
+
+        // the base stays on the straight part of the edge, as close to the anchor as possible
+        let along = EDGE_DIRECTIONS[edge];
+        let half_base = self.padding().min(length / 4.0);
+        let limit = (length / 2.0 - radius - half_base).max(0.0);
+        let offset = self.anchor - middle;
+        let base = middle + along * (offset.x * along.x + offset.y * along.y).clamp(-limit, limit);
+
+        Some((
+            edge,
+            [
+                base - along * half_base,
+                self.anchor,
+                base + along * half_base,
+            ],
+        ))
+    }
+
+    /// Corners of the box in clockwise order starting top right, each with the direction of the
+    /// edge leading to it.
+    fn corners(pos: Vec2D, size: Vec2D) -> [(Vec2D, Vec2D); 4] {
+        [
+            (Vec2D::new(pos.x + size.x, pos.y), EDGE_DIRECTIONS[0]),
+            (pos + size, EDGE_DIRECTIONS[1]),
+            (Vec2D::new(pos.x, pos.y + size.y), EDGE_DIRECTIONS[2]),
+            (pos, EDGE_DIRECTIONS[3]),
+        ]
+    }
+
+    /// Box and tail as one outline, so that both are filled in one go.
+    fn outline(&self, pos: Vec2D, size: Vec2D) -> Path {
+        let radius = Self::corner_radius(size);
+        let tail = self.tail(pos, size, radius);
+
+        let mut path = Path::new();
+        path.move_to(pos.x + radius, pos.y);
+        for (edge, (corner, _)) in Self::corners(pos, size).into_iter().enumerate() {
+            if let Some((_, points)) = tail.filter(|(tail_edge, _)| *tail_edge == edge) {
+                for p in points {
+                    path.line_to(p.x, p.y);
+                }
+            }
+            let next = corner + EDGE_DIRECTIONS[(edge + 1) % 4] * radius;
+            path.arc_to(corner.x, corner.y, next.x, next.y, radius);
+        }
+        path.close();
+        path
+    }
+
+    fn svg_outline(&self, pos: Vec2D, size: Vec2D) -> String {
+        let radius = Self::corner_radius(size);
+        let tail = self.tail(pos, size, radius);
+
+        let mut data = format!("M{},{}", pos.x + radius, pos.y);
+        for (edge, (corner, direction)) in Self::corners(pos, size).into_iter().enumerate() {
+            if let Some((_, points)) = tail.filter(|(tail_edge, _)| *tail_edge == edge) {
+                for p in points {
+                    data += &format!(" L{},{}", p.x, p.y);
+                }
+            }
+            let start = corner - direction * radius;
+            let end = corner + EDGE_DIRECTIONS[(edge + 1) % 4] * radius;
+            data += &format!(
+                " L{},{} A{radius},{radius} 0 0 1 {},{}",
+                start.x, start.y, end.x, end.y
+            );
+        }
+        data + " Z"
+    }
+}
+
+impl Drawable for Callout {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        fonts: Fonts,
+        bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let (text_pos, text_size) = self.text.measure(canvas, fonts)?;
+        let padding = Vec2D::new(self.padding(), self.padding());
+        let (pos, size) = (text_pos - padding, text_size + padding * 2.0);
+        self.area.set(Some((pos, size)));
+
+        canvas.save();
+        self.style.fill_path(canvas, &self.outline(pos, size));
+        canvas.restore();
+
+        self.text.draw(canvas, fonts, bounds)
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        let (pos, size) = self.box_area();
+        math::points_bounding_box([pos, pos + size, self.anchor])
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let area = self.box_area();
+        math::rect_contains(area, pos, tolerance)
+            || self
+                .tail(area.0, area.1, Self::corner_radius(area.1))
+                .is_some_and(|(_, points)| math::polygon_contains(&points, pos))
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Callout(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+        self.text.set_style(Self::text_style(style));
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let details = self.text.describe()?.details;
+        Some(DrawableDescription::new(
+            Tools::Callout,
+            Some(self.style.color),
+            details,
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let (pos, size) = self.box_area();
+        Some(format!(
+            r#"<g><path d="{}" {}/>{}</g>"#,
+            self.svg_outline(pos, size),
+            export::svg_shape_paint(&self.style, true),
+            self.text.to_svg(id).unwrap_or_default()
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.anchor += offset;
+        self.text.translate(offset);
+        self.area
+            .set(self.area.get().map(|(pos, size)| (pos + offset, size)));
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // like text, the box keeps its size and only follows with its top left corner
+        let anchor = math::rect_map_point(self.anchor, from, to);
+        let (pos, _) = self.box_area();
+        self.translate(math::rect_map_point(pos, from, to) - pos);
+        self.anchor = anchor;
+    }
+}
+
+#[derive(Default)]
+pub struct CalloutTool {
+    callout: Option<Callout>,
+    dragging: bool,
+    style: Style,
+    input_enabled: bool,
+    im_context: Option<InputContext>,
+}
+
+impl CalloutTool {
+    /// Stops editing, a callout without text is dropped.
+    fn finish(&mut self) -> ToolUpdateResult {
+        self.input_enabled = false;
+        self.dragging = false;
+        match self.callout.take() {
+            Some(mut callout) => {
+                callout.text.finish_editing();
+                if callout.text.text().is_empty() {
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Commit(callout.clone_box())
+                }
+            }
+            None => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    /// The text starts where the drag ends, the anchor stays at the start of the drag.
+    fn move_text(&mut self, offset: Vec2D) {
+        if let Some(callout) = &mut self.callout {
+            let target = callout.anchor + offset;
+            callout.text.translate(target - callout.text.pos());
+            callout.area.set(None);
+        }
+    }
+}
+
+impl Tool for CalloutTool {
+    fn get_tool_type(&self) -> Tools {
+        Tools::Callout
+    }
+
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn set_im_context(&mut self, context: Option<InputContext>) {
+        self.im_context = context.clone();
+        if let Some(callout) = &mut self.callout {
+            callout.text.set_im_context(context);
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.callout {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        if let Some(callout) = &mut self.callout {
+            callout.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_text_event(&mut self, event: TextEventMsg) -> ToolUpdateResult {
+        match &mut self.callout {
+            Some(callout) => callout.text.handle_text_event(event),
+            None => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        let Some(callout) = &mut self.callout else {
+            return ToolUpdateResult::Unmodified;
+        };
+
+        if event.key == Key::Return && event.modifier != ModifierType::SHIFT_MASK {
+            self.finish()
+        } else if event.key == Key::Escape {
+            self.callout = None;
+            self.input_enabled = false;
+            ToolUpdateResult::Redraw
+        } else {
+            callout.text.handle_edit_key(event)
+        }
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        match event.type_ {
+            // a click accepts the current callout, the drag of the same press starts the next one
+            MouseEventType::Click if event.button == MouseButton::Primary => self.finish(),
+            MouseEventType::BeginDrag if event.button == MouseButton::Primary => {
+                let text_size =
+                    self.style
+                        .size
+                        .to_text_size(self.style.annotation_size_factor) as f32;
+                self.callout = Some(Callout::new(
+                    event.pos,
+                    event.pos + Vec2D::new(text_size, -text_size) * 1.5,
+                    self.style,
+                    self.im_context.clone(),
+                ));
+                self.dragging = true;
+                self.input_enabled = true;
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag | MouseEventType::EndDrag => {
+                if !self.dragging {
+                    return ToolUpdateResult::Unmodified;
+                }
+                if event.type_ == MouseEventType::EndDrag {
+                    self.dragging = false;
+                }
+                if event.pos.is_zero() {
+                    return ToolUpdateResult::Unmodified;
+                }
+                self.move_text(event.pos);
+                ToolUpdateResult::Redraw
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.finish()
+    }
+
+    fn active(&self) -> bool {
+        self.callout.is_some()
+    }
+
+    fn handle_undo(&mut self) -> ToolUpdateResult {
+        if let Some(callout) = &self.callout {
+            callout.text.undo();
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_redo(&mut self) -> ToolUpdateResult {
+        if let Some(callout) = &self.callout {
+            callout.text.redo();
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+}
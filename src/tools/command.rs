@@ -0,0 +1,177 @@
+use std::fmt::Display;
+
+use crate::style::Style;
+
+use super::{Crop, CropTool, Drawable, DrawableDescription};
+
+/// A reversible change to the committed drawables or the crop, each step of the history is one.
+#[derive(Debug)]
+pub enum EditCommand {
+    Add {
+        index: usize,
+        drawable: Box<dyn Drawable>,
+    },
+    Remove {
+        index: usize,
+    },
+    Modify {
+        index: usize,
+        drawable: Box<dyn Drawable>,
+    },
+    Reorder {
+        from: usize,
+        to: usize,
+    },
+    Style {
+        index: usize,
+        style: Style,
+    },
+    Crop {
+        crop: Option<Crop>,
+    },
+    /// Applied as a whole and in order, e.g. to reset everything in one step.
+    Batch(Vec<EditCommand>),
+}
+
+impl EditCommand {
+    /// Applies the command and returns the command that reverts it.
+    pub fn apply(
+        self,
+        drawables: &mut Vec<Box<dyn Drawable>>,
+        crop_tool: &mut CropTool,
+    ) -> EditCommand {
+        match self {
+            Self::Add {
+                index,
+                mut drawable,
+            } => {
+                drawable.handle_added();
+                drawables.insert(index, drawable);
+                Self::Remove { index }
+            }
+            Self::Remove { index } => {
+                let mut drawable = drawables.remove(index);
+                drawable.handle_removed();
+                Self::Add { index, drawable }
+            }
+            Self::Modify { index, drawable } => Self::Modify {
+                index,
+                drawable: std::mem::replace(&mut drawables[index], drawable),
+            },
+            Self::Reorder { from, to } => {
+                let drawable = drawables.remove(from);
+                drawables.insert(to, drawable);
+                Self::Reorder { from: to, to: from }
+            }
+            Self::Style { index, style } => {
+                let drawable = &mut drawables[index];
+                let previous = drawable.style().unwrap_or(style);
+                drawable.set_style(style);
+                Self::Style {
+                    index,
+                    style: previous,
+                }
+            }
+            Self::Crop { crop } => Self::Crop {
+                crop: crop_tool.replace_crop(crop),
+            },
+            Self::Batch(commands) => {
+                // revert in the opposite order
+                let mut reverts: Vec<_> = commands
+                    .into_iter()
+                    .map(|command| command.apply(drawables, crop_tool))
+                    .collect();
+                reverts.reverse();
+                Self::Batch(reverts)
+            }
+        }
+    }
+
+    /// Whether the command only changes what `undo` reverts, the size or the fill opacity of
+    /// the same annotation, e.g. while dragging a slider. Both are then one step of the history.
+    pub fn continues_adjustment(
+        &self,
+        undo: &EditCommand,
+        drawables: &[Box<dyn Drawable>],
+    ) -> bool {
+        let (
+            Self::Style { index, style },
+            Self::Style {
+                index: previous,
+                style: original,
+            },
+        ) = (self, undo)
+        else {
+            return false;
+        };
+        let Some(current) = drawables.get(*index).and_then(|d| d.style()) else {
+            return false;
+        };
+        let only_sliders = |from: Style, to: Style| {
+            Style {
+                size: to.size,
+                fill_opacity: to.fill_opacity,
+                ..from
+            } == to
+        };
+        index == previous && only_sliders(*original, current) && only_sliders(current, *style)
+    }
+
+    /// Describes the command for the history, must be called before it is applied.
+    pub fn describe(&self, drawables: &[Box<dyn Drawable>]) -> HistoryEntry {
+        let describe_at = |index: &usize| drawables.get(*index).and_then(|d| d.describe());
+        let (change, drawable) = match self {
+            Self::Add { drawable, .. } => (HistoryChange::Added, drawable.describe()),
+            Self::Remove { index } => (HistoryChange::Removed, describe_at(index)),
+            Self::Modify { drawable, .. } => (HistoryChange::Modified, drawable.describe()),
+            Self::Reorder { from, .. } => (HistoryChange::Reordered, describe_at(from)),
+            Self::Style { index, .. } => (HistoryChange::Restyled, describe_at(index)),
+            Self::Crop { .. } => (HistoryChange::Cropped, None),
+            Self::Batch(_) => (HistoryChange::Reset, None),
+        };
+        HistoryEntry { change, drawable }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistoryChange {
+    Added,
+    Removed,
+    Modified,
+    Reordered,
+    Restyled,
+    Cropped,
+    Reset,
+}
+
+/// One step of the undo history.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub change: HistoryChange,
+    pub drawable: Option<DrawableDescription>,
+}
+
+impl Display for HistoryEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let change = match self.change {
+            HistoryChange::Added => "Add",
+            HistoryChange::Removed => "Delete",
+            HistoryChange::Modified => "Edit",
+            HistoryChange::Reordered => "Reorder",
+            HistoryChange::Restyled => "Restyle",
+            HistoryChange::Cropped => return write!(f, "Crop"),
+            HistoryChange::Reset => return write!(f, "Reset"),
+        };
+        match &self.drawable {
+            Some(drawable) => write!(f, "{change} {drawable}"),
+            None => write!(f, "{change} annotation"),
+        }
+    }
+}
+
+/// All steps that can be undone or redone, `position` is the number of steps that are applied.
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    pub position: usize,
+}
@@ -1,6 +1,7 @@
 use std::f32::consts::PI;
 
 use crate::{
+    femtovg_area::Fonts,
     math::{self, Vec2D},
     sketch_board::{KeyEventMsg, MouseEventMsg, MouseEventType},
 };
@@ -8,7 +9,7 @@ use anyhow::Result;
 use femtovg::{Color, Paint, Path};
 use relm4::gtk::gdk::Key;
 
-use super::{Drawable, Tool, ToolUpdateResult, Tools};
+use super::{Drawable, EditCommand, Tool, ToolUpdateResult, Tools};
 
 #[derive(Debug, Clone)]
 pub struct Crop {
@@ -20,8 +21,12 @@ pub struct Crop {
 #[derive(Default)]
 pub struct CropTool {
     crop: Option<Crop>,
+    // the crop before the current drag, it is restored until the change is applied as command
+    crop_before_drag: Option<Crop>,
     action: Option<CropToolAction>,
     input_enabled: bool,
+    // the handles are only shown while the tool is active
+    activated: bool,
 }
 
 impl Crop {
@@ -36,7 +41,7 @@ impl Crop {
         }
     }
 
-    fn draw_single_handle(
+    pub(super) fn draw_single_handle(
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
         center: Vec2D,
         scale: f32,
@@ -63,42 +68,11 @@ impl Crop {
         math::rect_ensure_positive_size(self.pos, self.size)
     }
 
-    fn get_handle_pos(crop_pos: Vec2D, crop_size: Vec2D, handle: CropHandle) -> Vec2D {
-        match handle {
-            CropHandle::TopLeftCorner => crop_pos,
-            CropHandle::TopEdge => crop_pos + Vec2D::new(crop_size.x / 2.0, 0.0),
-            CropHandle::TopRightCorner => crop_pos + Vec2D::new(crop_size.x, 0.0),
-            CropHandle::RightEdge => crop_pos + Vec2D::new(crop_size.x, crop_size.y / 2.0),
-            CropHandle::BottomRightCorner => crop_pos + Vec2D::new(crop_size.x, crop_size.y),
-            CropHandle::BottomEdge => crop_pos + Vec2D::new(crop_size.x / 2.0, crop_size.y),
-            CropHandle::BottomLeftCorner => crop_pos + Vec2D::new(0.0, crop_size.y),
-            CropHandle::LeftEdge => crop_pos + Vec2D::new(0.0, crop_size.y / 2.0),
-        }
-    }
     fn get_closest_handle(&self, mouse_pos: Vec2D) -> (CropHandle, f32) {
-        let mut min_distance_squared = f32::MAX;
-        let mut closest_handle = CropHandle::TopLeftCorner;
-        for h in CropHandle::all() {
-            let handle_pos = Self::get_handle_pos(self.pos, self.size, h);
-            let distance_squared = (handle_pos - mouse_pos).norm2();
-            if distance_squared < min_distance_squared {
-                min_distance_squared = distance_squared;
-                closest_handle = h;
-            }
-        }
-        (closest_handle, min_distance_squared)
+        CropHandle::closest(self.pos, self.size, mouse_pos)
     }
     fn test_handle_hit(&self, mouse_pos: Vec2D, margin2: f32) -> Option<CropHandle> {
-        const HANDLE_SIZE: f32 = Crop::HANDLE_RADIUS + Crop::HANDLE_BORDER;
-        const HANDLE_SIZE2: f32 = HANDLE_SIZE * HANDLE_SIZE;
-        let allowed_distance2 = HANDLE_SIZE2 + margin2;
-
-        let (handle, distance2) = self.get_closest_handle(mouse_pos);
-        if distance2 < allowed_distance2 {
-            Some(handle)
-        } else {
-            None
-        }
+        CropHandle::hit(self.pos, self.size, mouse_pos, margin2)
     }
 }
 
@@ -106,7 +80,7 @@ impl Drawable for Crop {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: femtovg::FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         let size = self.size;
@@ -146,8 +120,8 @@ impl Drawable for Crop {
     }
 }
 
-#[derive(Clone, Copy)]
-enum CropHandle {
+#[derive(Clone, Copy, Debug)]
+pub(super) enum CropHandle {
     TopLeftCorner,
     TopEdge,
     TopRightCorner,
@@ -181,10 +155,31 @@ impl CropTool {
             None => None,
         }
     }
+
+    pub fn set_crop(&mut self, rect: (Vec2D, Vec2D)) {
+        let (pos, size) = math::rect_ensure_positive_size(rect.0, rect.1);
+        self.crop = Some(Crop {
+            pos,
+            size,
+            active: false,
+        });
+    }
+
+    /// Swaps the crop, e.g. by undo or redo. Returns the previous crop.
+    pub fn replace_crop(&mut self, crop: Option<Crop>) -> Option<Crop> {
+        self.action = None;
+        std::mem::replace(
+            &mut self.crop,
+            crop.map(|crop| Crop {
+                active: self.activated,
+                ..crop
+            }),
+        )
+    }
 }
 
 impl CropHandle {
-    fn all() -> [CropHandle; 8] {
+    pub(super) fn all() -> [CropHandle; 8] {
         [
             CropHandle::TopLeftCorner,
             CropHandle::TopEdge,
@@ -196,45 +191,64 @@ impl CropHandle {
             CropHandle::LeftEdge,
         ]
     }
-}
-
-impl CropTool {
-    const HANDLE_MARGIN_IN_2: f32 = 15.0 * 15.0;
-    const HANDLE_MARGIN_OUT: f32 = 40.0;
-
-    fn test_inside_crop(&self, mouse_pos: Vec2D, margin: f32) -> bool {
-        let crop = match &self.crop {
-            Some(c) => c,
-            None => return false,
-        };
 
-        let (mut min_x, mut max_x) = (crop.pos.x, crop.pos.x + crop.size.x);
-        if min_x > max_x {
-            (min_x, max_x) = (max_x, min_x);
+    pub(super) fn position(self, rect_pos: Vec2D, rect_size: Vec2D) -> Vec2D {
+        match self {
+            CropHandle::TopLeftCorner => rect_pos,
+            CropHandle::TopEdge => rect_pos + Vec2D::new(rect_size.x / 2.0, 0.0),
+            CropHandle::TopRightCorner => rect_pos + Vec2D::new(rect_size.x, 0.0),
+            CropHandle::RightEdge => rect_pos + Vec2D::new(rect_size.x, rect_size.y / 2.0),
+            CropHandle::BottomRightCorner => rect_pos + Vec2D::new(rect_size.x, rect_size.y),
+            CropHandle::BottomEdge => rect_pos + Vec2D::new(rect_size.x / 2.0, rect_size.y),
+            CropHandle::BottomLeftCorner => rect_pos + Vec2D::new(0.0, rect_size.y),
+            CropHandle::LeftEdge => rect_pos + Vec2D::new(0.0, rect_size.y / 2.0),
         }
-        min_x -= margin;
-        max_x += margin;
+    }
 
-        let (mut min_y, mut max_y) = (crop.pos.y, crop.pos.y + crop.size.y);
-        if min_y > max_y {
-            (min_y, max_y) = (max_y, min_y);
+    pub(super) fn closest(rect_pos: Vec2D, rect_size: Vec2D, mouse_pos: Vec2D) -> (Self, f32) {
+        let mut min_distance_squared = f32::MAX;
+        let mut closest_handle = CropHandle::TopLeftCorner;
+        for h in CropHandle::all() {
+            let handle_pos = h.position(rect_pos, rect_size);
+            let distance_squared = (handle_pos - mouse_pos).norm2();
+            if distance_squared < min_distance_squared {
+                min_distance_squared = distance_squared;
+                closest_handle = h;
+            }
         }
-        min_y -= margin;
-        max_y += margin;
+        (closest_handle, min_distance_squared)
+    }
 
-        min_x < mouse_pos.x && mouse_pos.x < max_x && min_y < mouse_pos.y && mouse_pos.y < max_y
+    pub(super) fn hit(
+        rect_pos: Vec2D,
+        rect_size: Vec2D,
+        mouse_pos: Vec2D,
+        margin2: f32,
+    ) -> Option<Self> {
+        const HANDLE_SIZE: f32 = Crop::HANDLE_RADIUS + Crop::HANDLE_BORDER;
+        const HANDLE_SIZE2: f32 = HANDLE_SIZE * HANDLE_SIZE;
+        let allowed_distance2 = HANDLE_SIZE2 + margin2;
+
+        let (handle, distance2) = Self::closest(rect_pos, rect_size, mouse_pos);
+        if distance2 < allowed_distance2 {
+            Some(handle)
+        } else {
+            None
+        }
     }
 
-    fn apply_drag_handle_transformation(
-        crop: &mut Crop,
-        state: &DragHandleState,
+    /// Moves the corners of a rectangle as if this handle was dragged by `direction`.
+    /// Returns the new top left and bottom right corners.
+    pub(super) fn apply_drag(
+        self,
+        top_left: Vec2D,
+        bottom_right: Vec2D,
         direction: Vec2D,
-    ) {
-        let mut tl = state.top_left_start;
-        let mut br = state.bottom_right_start;
+    ) -> (Vec2D, Vec2D) {
+        let mut tl = top_left;
+        let mut br = bottom_right;
 
-        // apply transformation
-        match state.handle {
+        match self {
             CropHandle::TopLeftCorner => {
                 tl += direction;
             }
@@ -263,12 +277,54 @@ impl CropTool {
             }
         }
 
+        (tl, br)
+    }
+}
+
+impl CropTool {
+    const HANDLE_MARGIN_IN_2: f32 = 15.0 * 15.0;
+    const HANDLE_MARGIN_OUT: f32 = 40.0;
+
+    fn test_inside_crop(&self, mouse_pos: Vec2D, margin: f32) -> bool {
+        let crop = match &self.crop {
+            Some(c) => c,
+            None => return false,
+        };
+
+        let (mut min_x, mut max_x) = (crop.pos.x, crop.pos.x + crop.size.x);
+        if min_x > max_x {
+            (min_x, max_x) = (max_x, min_x);
+        }
+        min_x -= margin;
+        max_x += margin;
+
+        let (mut min_y, mut max_y) = (crop.pos.y, crop.pos.y + crop.size.y);
+        if min_y > max_y {
+            (min_y, max_y) = (max_y, min_y);
+        }
+        min_y -= margin;
+        max_y += margin;
+
+        min_x < mouse_pos.x && mouse_pos.x < max_x && min_y < mouse_pos.y && mouse_pos.y < max_y
+    }
+
+    fn apply_drag_handle_transformation(
+        crop: &mut Crop,
+        state: &DragHandleState,
+        direction: Vec2D,
+    ) {
+        let (tl, br) =
+            state
+                .handle
+                .apply_drag(state.top_left_start, state.bottom_right_start, direction);
+
         // convert back and save
         crop.pos = tl;
         crop.size = br - tl;
     }
 
     fn begin_drag(&mut self, pos: Vec2D) -> ToolUpdateResult {
+        self.crop_before_drag = self.crop.clone();
         match &self.crop {
             None => {
                 // No crop exists, create a new one
@@ -336,29 +392,27 @@ impl CropTool {
             return ToolUpdateResult::Unmodified;
         };
 
-        let Some(action) = &self.action else {
+        let Some(action) = self.action.take() else {
             return ToolUpdateResult::Unmodified;
         };
 
-        match action {
-            // crop never returns "commit" because nothing gets
-            // committed to the drawables stack
-            CropToolAction::NewCrop => {
-                crop.size = direction;
-                self.action = None;
-                ToolUpdateResult::Redraw
-            }
+        match &action {
+            CropToolAction::NewCrop => crop.size = direction,
             CropToolAction::DragHandle(state) => {
-                Self::apply_drag_handle_transformation(crop, state, direction);
-                self.action = None;
-                ToolUpdateResult::Redraw
-            }
-            CropToolAction::Move(state) => {
-                crop.pos = state.start + direction;
-                self.action = None;
-                ToolUpdateResult::Redraw
+                Self::apply_drag_handle_transformation(crop, state, direction)
             }
+            CropToolAction::Move(state) => crop.pos = state.start + direction,
         }
+
+        // a click on a handle or into the crop doesn't change anything
+        if direction.is_zero() && !matches!(action, CropToolAction::NewCrop) {
+            self.crop_before_drag = None;
+            return ToolUpdateResult::Redraw;
+        }
+
+        // nothing gets committed to the drawables stack, but the change goes into the history
+        let crop = std::mem::replace(&mut self.crop, self.crop_before_drag.take());
+        ToolUpdateResult::Edit(EditCommand::Crop { crop })
     }
 }
 
@@ -377,10 +431,12 @@ impl Tool for CropTool {
 
     fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
         if event.key == Key::Escape {
-            if let Some(crop) = &self.crop {
+            if let Some(crop) = &mut self.crop {
                 if crop.active {
                     // Crop is active, deactivate it
-                    return self.handle_deactivated();
+                    crop.active = false;
+                    self.action = None;
+                    return ToolUpdateResult::Redraw;
                 }
             }
         }
@@ -398,6 +454,7 @@ impl Tool for CropTool {
     }
 
     fn handle_activated(&mut self) -> ToolUpdateResult {
+        self.activated = true;
         if let Some(c) = &mut self.crop {
             c.active = true;
             return ToolUpdateResult::Redraw;
@@ -406,6 +463,7 @@ impl Tool for CropTool {
     }
 
     fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.activated = false;
         if let Some(c) = &mut self.crop {
             c.active = false;
         }
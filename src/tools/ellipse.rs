@@ -1,16 +1,21 @@
 use anyhow::Result;
-use femtovg::{FontId, Path};
+use femtovg::Path;
 use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::Vec2D,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
     style::Style,
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Ellipse {
     origin: Vec2D,
     middle: Vec2D,
@@ -24,7 +29,7 @@ impl Drawable for Ellipse {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         let radii = match self.radii {
@@ -50,15 +55,75 @@ impl Drawable for Ellipse {
             );
         }
 
-        if self.style.fill {
-            canvas.fill_path(&path, &self.style.into());
-        } else {
-            canvas.stroke_path(&path, &self.style.into());
+        for layer in self.style.layers() {
+            if self.style.fill {
+                layer.fill_path(canvas, &path);
+            } else {
+                layer.stroke_path(canvas, &path);
+            }
         }
         canvas.restore();
 
         Ok(())
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.radii
+            .map(|radii| math::rect_ensure_positive_size(self.middle - radii, radii * 2.0))
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Ellipse(*self))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Ellipse,
+            Some(self.style.color),
+            self.radii
+                .map(|radii| DrawableDescription::format_size(radii * 2.0))
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let radii = self.radii?;
+        Some(export::svg_with_effects(
+            &self.style,
+            id,
+            format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {}/>"#,
+                self.middle.x,
+                self.middle.y,
+                radii.x.abs(),
+                radii.y.abs(),
+                export::svg_shape_paint(&self.style, self.style.fill)
+            ),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.origin += offset;
+        self.middle += offset;
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(radii) = self.radii {
+            let middle = math::rect_map_point(self.middle, from, to);
+            let corner = math::rect_map_point(self.middle + radii, from, to);
+            self.origin = math::rect_map_point(self.origin, from, to);
+            self.middle = middle;
+            self.radii = Some(corner - middle);
+        }
+    }
 }
 
 impl Ellipse {
@@ -176,7 +241,13 @@ impl Tool for EllipseTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(ellipse) = &mut self.ellipse {
+            ellipse.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
@@ -4,18 +4,20 @@ use anyhow::Result;
 use femtovg::{Paint, Path};
 
 use relm4::gtk::gdk::{Key, ModifierType};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     command_line,
     configuration::APP_CONFIG,
+    export,
+    femtovg_area::Fonts,
     math::{self, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
     style::Style,
     tools::DrawableClone,
 };
 
-use super::{Drawable, Tool, ToolUpdateResult, Tools};
+use super::{Drawable, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools};
 
 const HIGHLIGHT_OPACITY: f64 = 0.4;
 
@@ -35,24 +37,35 @@ impl From<command_line::Highlighters> for Highlighters {
     }
 }
 
-#[derive(Clone, Debug)]
-struct BlockHighlight {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlockHighlight {
     top_left: Vec2D,
     size: Option<Vec2D>,
 }
 
-#[derive(Clone, Debug)]
-struct FreehandHighlight {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FreehandHighlight {
     points: Vec<Vec2D>,
     shift_pressed: bool,
 }
 
-#[derive(Clone, Debug)]
-struct Highlighter<T> {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Highlighter<T> {
     data: T,
     style: Style,
 }
 
+impl FreehandHighlight {
+    fn absolute_points(&self) -> Vec<Vec2D> {
+        match self.points.first() {
+            Some(first) => std::iter::once(*first)
+                .chain(self.points.iter().skip(1).map(|p| *first + *p))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
 trait Highlight {
     fn highlight(&self, canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>) -> Result<()>;
 }
@@ -123,8 +136,9 @@ impl Highlight for Highlighter<BlockHighlight> {
     }
 }
 
-#[derive(Clone, Debug)]
-enum HighlightKind {
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum HighlightKind {
     Block(Highlighter<BlockHighlight>),
     Freehand(Highlighter<FreehandHighlight>),
 }
@@ -140,7 +154,7 @@ impl Drawable for HighlightKind {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: femtovg::FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         match self {
@@ -148,6 +162,151 @@ impl Drawable for HighlightKind {
             HighlightKind::Freehand(highlighter) => highlighter.highlight(canvas),
         }
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter
+                .data
+                .size
+                .map(|size| math::rect_ensure_positive_size(highlighter.data.top_left, size)),
+            HighlightKind::Freehand(highlighter) => {
+                math::points_bounding_box(highlighter.data.absolute_points())
+            }
+        }
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        match self {
+            HighlightKind::Block(_) => self
+                .bounding_box()
+                .is_some_and(|bbox| math::rect_contains(bbox, pos, tolerance)),
+            HighlightKind::Freehand(highlighter) => {
+                let max_distance = highlighter
+                    .style
+                    .size
+                    .to_highlight_width(highlighter.style.annotation_size_factor)
+                    / 2.0
+                    + tolerance;
+                let points = highlighter.data.absolute_points();
+                match points.as_slice() {
+                    [single] => single.distance_to(&pos) <= max_distance,
+                    _ => points
+                        .windows(2)
+                        .any(|w| math::distance_to_segment(pos, w[0], w[1]) <= max_distance),
+                }
+            }
+        }
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Highlight(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        match self {
+            HighlightKind::Block(highlighter) => Some(highlighter.style),
+            HighlightKind::Freehand(highlighter) => Some(highlighter.style),
+        }
+    }
+
+    fn set_style(&mut self, style: Style) {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter.style = style,
+            HighlightKind::Freehand(highlighter) => highlighter.style = style,
+        }
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let (color, details) = match self {
+            HighlightKind::Block(highlighter) => (
+                highlighter.style.color,
+                highlighter
+                    .data
+                    .size
+                    .map(DrawableDescription::format_size)
+                    .unwrap_or_default(),
+            ),
+            HighlightKind::Freehand(highlighter) => {
+                (highlighter.style.color, "freehand".to_string())
+            }
+        };
+        Some(DrawableDescription::new(
+            Tools::Highlight,
+            Some(color),
+            details,
+        ))
+    }
+
+    fn to_svg(&self, _id: usize) -> Option<String> {
+        match self {
+            HighlightKind::Block(highlighter) => {
+                let (pos, size) = self.bounding_box()?;
+                Some(format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{}" fill-opacity="{HIGHLIGHT_OPACITY}"/>"#,
+                    pos.x,
+                    pos.y,
+                    size.x,
+                    size.y,
+                    APP_CONFIG.read().corner_roundness(),
+                    export::svg_color(highlighter.style.color),
+                ))
+            }
+            HighlightKind::Freehand(highlighter) => {
+                let points = highlighter.data.absolute_points();
+                if points.is_empty() {
+                    return None;
+                }
+                Some(format!(
+                    r#"<polyline points="{}" fill="none" stroke="{}" stroke-opacity="{HIGHLIGHT_OPACITY}" stroke-width="{}" stroke-linejoin="round" stroke-linecap="square"/>"#,
+                    export::svg_points(&points),
+                    export::svg_color(highlighter.style.color),
+                    highlighter
+                        .style
+                        .size
+                        .to_highlight_width(highlighter.style.annotation_size_factor),
+                ))
+            }
+        }
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        match self {
+            HighlightKind::Block(highlighter) => highlighter.data.top_left += offset,
+            HighlightKind::Freehand(highlighter) => {
+                // only the first point is absolute
+                if let Some(first) = highlighter.data.points.first_mut() {
+                    *first += offset;
+                }
+            }
+        }
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        match self {
+            HighlightKind::Block(highlighter) => {
+                if let Some(size) = highlighter.data.size {
+                    let top_left = math::rect_map_point(highlighter.data.top_left, from, to);
+                    let bottom_right =
+                        math::rect_map_point(highlighter.data.top_left + size, from, to);
+                    highlighter.data.top_left = top_left;
+                    highlighter.data.size = Some(bottom_right - top_left);
+                }
+            }
+            HighlightKind::Freehand(highlighter) => {
+                let Some(first) = highlighter.data.points.first().copied() else {
+                    return;
+                };
+                let new_first = math::rect_map_point(first, from, to);
+                for (i, p) in highlighter.data.points.iter_mut().enumerate() {
+                    *p = if i == 0 {
+                        new_first
+                    } else {
+                        math::rect_map_point(first + *p, from, to) - new_first
+                    };
+                }
+            }
+        }
+    }
 }
 
 impl Tool for HighlightTool {
@@ -319,7 +478,13 @@ impl Tool for HighlightTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(highlighter) = &mut self.highlighter {
+            highlighter.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
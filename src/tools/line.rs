@@ -1,14 +1,19 @@
 use anyhow::Result;
-use femtovg::{FontId, Path};
+use femtovg::Path;
 use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
-    math::Vec2D,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
     style::Style,
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
 
 #[derive(Default)]
 pub struct LineTool {
@@ -17,7 +22,7 @@ pub struct LineTool {
     input_enabled: bool,
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Line {
     start: Vec2D,
     direction: Option<Vec2D>,
@@ -28,7 +33,7 @@ impl Drawable for Line {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         let direction = match self.direction {
@@ -42,12 +47,83 @@ impl Drawable for Line {
         path.move_to(self.start.x, self.start.y);
         path.line_to(self.start.x + direction.x, self.start.y + direction.y);
 
-        canvas.stroke_path(&path, &self.style.into());
+        for layer in self.style.layers() {
+            layer.stroke_path(canvas, &path);
+        }
 
         canvas.restore();
 
         Ok(())
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.direction
+            .and_then(|d| math::points_bounding_box([self.start, self.start + d]))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        match self.direction {
+            Some(d) => {
+                let half_width = self
+                    .style
+                    .size
+                    .to_line_width(self.style.annotation_size_factor)
+                    / 2.0;
+                math::distance_to_segment(pos, self.start, self.start + d) <= half_width + tolerance
+            }
+            None => false,
+        }
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Line(*self))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let length = self.direction.map_or(0.0, |direction| direction.norm());
+        Some(DrawableDescription::new(
+            Tools::Line,
+            Some(self.style.color),
+            format!("{length:.0} px"),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let end = self.start + self.direction?;
+        Some(export::svg_with_effects(
+            &self.style,
+            id,
+            format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {}/>"#,
+                self.start.x,
+                self.start.y,
+                end.x,
+                end.y,
+                export::svg_paint(&self.style, false)
+            ),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.start += offset;
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(d) = self.direction {
+            let start = math::rect_map_point(self.start, from, to);
+            let end = math::rect_map_point(self.start + d, from, to);
+            self.start = start;
+            self.direction = Some(end - start);
+        }
+    }
 }
 
 impl Tool for LineTool {
@@ -119,7 +195,13 @@ impl Tool for LineTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(line) = &mut self.line {
+            line.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
@@ -0,0 +1,529 @@
+use std::cell::RefCell;
+
+use anyhow::Result;
+use femtovg::{ImageFlags, ImageId, Paint, Path};
+use gdk_pixbuf::Pixbuf;
+use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType},
+    style::Style,
+};
+
+use super::{
+    blur::Blur, Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult,
+    Tools,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LensShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+impl LensShape {
+    fn path(self, (pos, size): (Vec2D, Vec2D)) -> Path {
+        let mut path = Path::new();
+        match self {
+            LensShape::Rectangle => path.rounded_rect(
+                pos.x,
+                pos.y,
+                size.x,
+                size.y,
+                APP_CONFIG.read().corner_roundness(),
+            ),
+            LensShape::Ellipse => path.ellipse(
+                pos.x + size.x / 2.0,
+                pos.y + size.y / 2.0,
+                size.x / 2.0,
+                size.y / 2.0,
+            ),
+        }
+        path
+    }
+
+    fn svg(self, (pos, size): (Vec2D, Vec2D), attributes: &str) -> String {
+        match self {
+            LensShape::Rectangle => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {attributes}/>"#,
+                pos.x,
+                pos.y,
+                size.x,
+                size.y,
+                APP_CONFIG.read().corner_roundness()
+            ),
+            LensShape::Ellipse => format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {attributes}/>"#,
+                pos.x + size.x / 2.0,
+                pos.y + size.y / 2.0,
+                size.x / 2.0,
+                size.y / 2.0
+            ),
+        }
+    }
+
+    /// Point of the outline that lies furthest in `direction`.
+    fn support_point(self, (pos, size): (Vec2D, Vec2D), direction: Vec2D) -> Vec2D {
+        let half = size * 0.5;
+        let center = pos + half;
+        match self {
+            LensShape::Rectangle => {
+                center + Vec2D::new(half.x * direction.x.signum(), half.y * direction.y.signum())
+            }
+            LensShape::Ellipse => {
+                let scaled = Vec2D::new(half.x * direction.x, half.y * direction.y);
+                let norm = scaled.norm();
+                if norm <= f32::EPSILON {
+                    return center;
+                }
+                center + Vec2D::new(half.x * scaled.x / norm, half.y * scaled.y / norm)
+            }
+        }
+    }
+}
+
+/// An enlarged copy of a region of the screenshot, shown somewhere else in the image.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Magnifier {
+    top_left: Vec2D,
+    size: Option<Vec2D>,
+    shape: LensShape,
+    /// Center of the enlarged copy, `None` while the source region is selected.
+    lens: Option<Vec2D>,
+    connectors: bool,
+    style: Style,
+    // the enlarged copy shows the screenshot, never other annotations
+    #[serde(skip)]
+    background: Option<Pixbuf>,
+    #[serde(skip)]
+    cached_image: RefCell<Option<ImageId>>,
+}
+
+impl Magnifier {
+    fn zoom(&self) -> f32 {
+        self.style.size.to_zoom_factor()
+    }
+
+    fn source(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+            .filter(|(_, size)| size.x > 0.0 && size.y > 0.0)
+    }
+
+    fn lens_area(&self) -> Option<(Vec2D, Vec2D)> {
+        let (_, size) = self.source()?;
+        let size = size * self.zoom();
+        Some((self.lens? - size * 0.5, size))
+    }
+
+    /// Default position of the lens, right next to the source region.
+    fn lens_beside_source(&self) -> Option<Vec2D> {
+        let (pos, size) = self.source()?;
+        let gap = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+            * 4.0;
+        Some(Vec2D::new(
+            pos.x + size.x + gap + size.x * self.zoom() / 2.0,
+            pos.y + size.y / 2.0,
+        ))
+    }
+
+    /// Pairs of points that connect the outline of the source region with the lens.
+    fn connector_lines(&self) -> Vec<(Vec2D, Vec2D)> {
+        let (Some(source), Some(lens)) = (self.source(), self.lens_area()) else {
+            return Vec::new();
+        };
+        if !self.connectors {
+            return Vec::new();
+        }
+
+        let offset = (lens.0 + lens.1 * 0.5) - (source.0 + source.1 * 0.5);
+        if offset.norm() <= f32::EPSILON {
+            return Vec::new();
+        }
+        let normal = Vec2D::new(-offset.y, offset.x) * (1.0 / offset.norm());
+
+        [normal, normal * -1.0]
+            .into_iter()
+            .map(|n| {
+                (
+                    self.shape.support_point(source, n),
+                    self.shape.support_point(lens, n),
+                )
+            })
+            .collect()
+    }
+
+    /// Area of the background shown in the lens, in whole pixels.
+    fn background_region(&self, background: &Pixbuf) -> Option<(i32, i32, i32, i32)> {
+        let (pos, size) = self.source()?;
+        let x = (pos.x.floor() as i32).clamp(0, background.width());
+        let y = (pos.y.floor() as i32).clamp(0, background.height());
+        let right = ((pos.x + size.x).ceil() as i32).clamp(0, background.width());
+        let bottom = ((pos.y + size.y).ceil() as i32).clamp(0, background.height());
+        if right <= x || bottom <= y {
+            return None;
+        }
+        Some((x, y, right - x, bottom - y))
+    }
+
+    fn draw_lens(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        lens: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let (Some(background), Some((source_pos, _))) = (&self.background, self.source()) else {
+            return Ok(());
+        };
+        let Some(region) = self.background_region(background) else {
+            return Ok(());
+        };
+
+        // the pixels are taken from the background itself, so the lens is as sharp as the
+        // screenshot at any output resolution
+        if self.cached_image.borrow().is_none() {
+            let pixels = Blur::background_pixels(background, region);
+            let image = canvas.create_image(pixels.as_ref(), ImageFlags::NEAREST)?;
+            self.cached_image.borrow_mut().replace(image);
+        }
+
+        let zoom = self.zoom();
+        let image_pos = lens.0 + (Vec2D::new(region.0 as f32, region.1 as f32) - source_pos) * zoom;
+        canvas.fill_path(
+            &self.shape.path(lens),
+            &Paint::image(
+                self.cached_image.borrow().unwrap(), // this unwrap is safe because we placed it above
+                image_pos.x,
+                image_pos.y,
+                region.2 as f32 * zoom,
+                region.3 as f32 * zoom,
+                0f32,
+                1f32,
+            ),
+        );
+        Ok(())
+    }
+}
+
+impl Drawable for Magnifier {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some(source) = self.source() else {
+            return Ok(());
+        };
+
+        canvas.save();
+
+        for (start, end) in self.connector_lines() {
+            let mut path = Path::new();
+            path.move_to(start.x, start.y);
+            path.line_to(end.x, end.y);
+            self.style.stroke_path(canvas, &path);
+        }
+        self.style.stroke_path(canvas, &self.shape.path(source));
+
+        if let Some(lens) = self.lens_area() {
+            self.draw_lens(canvas, lens)?;
+            self.style.stroke_path(canvas, &self.shape.path(lens));
+        }
+
+        canvas.restore();
+        Ok(())
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        let (source_pos, source_size) = self.source()?;
+        let mut corners = vec![source_pos, source_pos + source_size];
+        if let Some((lens_pos, lens_size)) = self.lens_area() {
+            corners.extend([lens_pos, lens_pos + lens_size]);
+        }
+        math::points_bounding_box(corners)
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        self.source()
+            .into_iter()
+            .chain(self.lens_area())
+            .any(|area| math::rect_contains(area, pos, tolerance))
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Magnifier(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Magnifier,
+            Some(self.style.color),
+            self.size
+                .map(|size| {
+                    format!(
+                        "{} ×{}",
+                        DrawableDescription::format_size(size),
+                        self.zoom()
+                    )
+                })
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let (source_pos, _) = self.source()?;
+        let lens = self.lens_area()?;
+        let zoom = self.zoom();
+        let offset = lens.0 - source_pos * zoom;
+        let paint = export::svg_paint(&self.style, false);
+
+        let mut svg = format!(
+            r##"<clipPath id="magnifier-clip-{id}">{}</clipPath>
+<g clip-path="url(#magnifier-clip-{id})"><use xlink:href="#background" transform="translate({} {}) scale({zoom})" style="image-rendering:pixelated"/></g>
+"##,
+            self.shape.svg(lens, ""),
+            offset.x,
+            offset.y,
+        );
+        for (start, end) in self.connector_lines() {
+            svg.push_str(&format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" {paint}/>"#,
+                start.x, start.y, end.x, end.y
+            ));
+            svg.push('\n');
+        }
+        svg.push_str(&self.shape.svg(self.source()?, &paint));
+        svg.push('\n');
+        svg.push_str(&self.shape.svg(lens, &paint));
+        Some(svg)
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.top_left += offset;
+        if let Some(lens) = &mut self.lens {
+            *lens += offset;
+        }
+        // the enlarged content changes with the position
+        self.cached_image.replace(None);
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            let bottom_right = math::rect_map_point(self.top_left + size, from, to);
+            self.top_left = top_left;
+            self.size = Some(bottom_right - top_left);
+            self.lens = self.lens.map(|lens| math::rect_map_point(lens, from, to));
+            self.cached_image.replace(None);
+        }
+    }
+}
+
+pub struct MagnifierTool {
+    magnifier: Option<Magnifier>,
+    /// Lens position at the start of a drag that moves the lens.
+    drag_start: Option<Vec2D>,
+    style: Style,
+    input_enabled: bool,
+    background: Pixbuf,
+}
+
+impl MagnifierTool {
+    pub fn new(background: Pixbuf) -> Self {
+        Self {
+            magnifier: None,
+            drag_start: None,
+            style: Style::default(),
+            input_enabled: false,
+            background,
+        }
+    }
+
+    /// Links a magnifier loaded from a project file to the background it enlarges.
+    pub fn restore(&self, mut magnifier: Magnifier) -> Magnifier {
+        magnifier.background = Some(self.background.clone());
+        magnifier
+    }
+
+    fn placing_lens(&self) -> bool {
+        self.magnifier.as_ref().is_some_and(|m| m.lens.is_some())
+    }
+
+    fn update_source(magnifier: &mut Magnifier, event: &MouseEventMsg) {
+        magnifier.shape = if event.modifier.intersects(ModifierType::CONTROL_MASK) {
+            LensShape::Ellipse
+        } else {
+            LensShape::Rectangle
+        };
+        magnifier.size = Some(if event.modifier.intersects(ModifierType::SHIFT_MASK) {
+            let max_size = event.pos.x.abs().max(event.pos.y.abs());
+            Vec2D::new(
+                max_size * event.pos.x.signum(),
+                max_size * event.pos.y.signum(),
+            )
+        } else {
+            event.pos
+        });
+    }
+
+    fn finish(&mut self) -> ToolUpdateResult {
+        self.drag_start = None;
+        match self.magnifier.take() {
+            Some(magnifier) if magnifier.lens.is_some() => {
+                ToolUpdateResult::Commit(magnifier.clone_box())
+            }
+            Some(_) => ToolUpdateResult::Redraw,
+            None => ToolUpdateResult::Unmodified,
+        }
+    }
+}
+
+impl Tool for MagnifierTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        // first a drag selects the source region, then a click or drag places the lens
+        if self.placing_lens() {
+            let Some(magnifier) = &mut self.magnifier else {
+                return ToolUpdateResult::Unmodified;
+            };
+            return match event.type_ {
+                MouseEventType::Click => {
+                    magnifier.lens = Some(event.pos);
+                    ToolUpdateResult::Redraw
+                }
+                MouseEventType::BeginDrag => {
+                    self.drag_start = magnifier.lens;
+                    ToolUpdateResult::Unmodified
+                }
+                MouseEventType::UpdateDrag => {
+                    let Some(start) = self.drag_start else {
+                        return ToolUpdateResult::Unmodified;
+                    };
+                    magnifier.lens = Some(start + event.pos);
+                    ToolUpdateResult::Redraw
+                }
+                MouseEventType::EndDrag => {
+                    if let Some(start) = self.drag_start {
+                        magnifier.lens = Some(start + event.pos);
+                    }
+                    magnifier.connectors = !event.modifier.intersects(ModifierType::ALT_MASK);
+                    self.finish()
+                }
+                MouseEventType::DoubleClick => ToolUpdateResult::Unmodified,
+            };
+        }
+
+        match event.type_ {
+            MouseEventType::BeginDrag => {
+                self.magnifier = Some(Magnifier {
+                    top_left: event.pos,
+                    size: None,
+                    shape: LensShape::default(),
+                    lens: None,
+                    connectors: true,
+                    style: self.style,
+                    background: Some(self.background.clone()),
+                    cached_image: RefCell::new(None),
+                });
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::UpdateDrag => {
+                let Some(magnifier) = &mut self.magnifier else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    return ToolUpdateResult::Unmodified;
+                }
+                Self::update_source(magnifier, &event);
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::EndDrag => {
+                let Some(magnifier) = &mut self.magnifier else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    self.magnifier = None;
+                    return ToolUpdateResult::Redraw;
+                }
+                Self::update_source(magnifier, &event);
+                magnifier.lens = magnifier.lens_beside_source();
+                ToolUpdateResult::Redraw
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if self.magnifier.is_none() {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        if event.key == Key::Escape {
+            self.magnifier = None;
+            self.drag_start = None;
+            ToolUpdateResult::Redraw
+        } else if event.key == Key::Return || event.key == Key::KP_Enter {
+            self.finish()
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.finish()
+    }
+
+    fn active(&self) -> bool {
+        self.magnifier.is_some()
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        if let Some(magnifier) = &mut self.magnifier {
+            magnifier.style = style;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.magnifier {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> Tools {
+        Tools::Magnifier
+    }
+}
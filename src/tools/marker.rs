@@ -1,14 +1,22 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::f64::consts::PI;
 use std::rc::Rc;
 
 use femtovg::{Color, Paint, Path};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::sketch_board::{MouseButton, MouseEventType};
 use crate::style::Style;
-use crate::{math::Vec2D, sketch_board::MouseEventMsg};
+use crate::{
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::MouseEventMsg,
+};
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
 
 pub struct MarkerTool {
     style: Style,
@@ -16,35 +24,30 @@ pub struct MarkerTool {
     input_enabled: bool,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Marker {
     pos: Vec2D,
     number: u16,
     style: Style,
+    #[serde(skip)]
     tool_next_number: Rc<RefCell<u16>>,
+    // depends on the font metrics, so it is only known after drawing
+    #[serde(skip)]
+    radius: Cell<f32>,
 }
 
 impl Drawable for Marker {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: femtovg::FontId,
+        fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> anyhow::Result<()> {
         let text = format!("{}", self.number);
 
-        let marker_color: Color = self.style.color.into();
-        // https://en.wikipedia.org/wiki/Luma_(video)
-        let luminance = 0.2126 * marker_color.r + 0.7152 * marker_color.g + 0.0722 * marker_color.b;
-        let text_color = if luminance > 0.5 {
-            Color::black()
-        } else {
-            Color::white()
-        };
-
-        let mut paint = Paint::color(text_color);
+        let mut paint = Paint::color(self.style.effective_fill_color().contrasting().into());
 
-        paint.set_font(&[font]);
+        paint.set_font(&[fonts.regular]);
         paint.set_font_size(
             (self
                 .style
@@ -80,27 +83,129 @@ impl Drawable for Marker {
             femtovg::Solidity::Solid,
         );
 
-        let circle_paint = Paint::color(marker_color).with_line_width(
-            self.style
-                .size
-                .to_line_width(self.style.annotation_size_factor)
-                * 2.0,
-        );
+        let ring_width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+            * 2.0;
+
+        self.radius.set(circle_radius + ring_width / 2.0);
 
         canvas.save();
-        canvas.fill_path(&inner_circle_path, &circle_paint);
-        canvas.stroke_path(&outer_circle_path, &circle_paint);
+        // the disc covers the effects of the number, only the marker itself gets them
+        for layer in self.style.layers() {
+            layer.fill_solid(
+                canvas,
+                &inner_circle_path,
+                self.style.effective_fill_color(),
+            );
+            layer.stroke_solid(canvas, &outer_circle_path, ring_width);
+        }
         canvas.fill_text(self.pos.x, self.pos.y, &text, &paint)?;
         canvas.restore();
         Ok(())
     }
 
-    fn handle_undo(&mut self) {
-        *self.tool_next_number.borrow_mut() = self.number;
+    fn handle_removed(&mut self) {
+        // only the latest number is given out again, older ones would end up twice
+        let mut next_number = self.tool_next_number.borrow_mut();
+        if *next_number == self.number + 1 {
+            *next_number = self.number;
+        }
+    }
+
+    fn handle_added(&mut self) {
+        let mut next_number = self.tool_next_number.borrow_mut();
+        *next_number = (*next_number).max(self.number + 1);
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        let radius = self.radius.get();
+        if radius <= 0.0 {
+            return None;
+        }
+        Some((
+            self.pos - Vec2D::new(radius, radius),
+            Vec2D::new(radius, radius) * 2.0,
+        ))
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        let radius = self.radius.get();
+        radius > 0.0 && self.pos.distance_to(&pos) <= radius + tolerance
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Marker(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
     }
 
-    fn handle_redo(&mut self) {
-        *self.tool_next_number.borrow_mut() = self.number + 1;
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Marker,
+            Some(self.style.color),
+            self.number.to_string(),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let line_width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor);
+        let font_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor);
+        // the radius depends on the font metrics, estimate it if the marker was never drawn
+        let circle_radius = match self.radius.get() {
+            r if r > 0.0 => r - line_width,
+            _ => font_size as f32 * 0.75,
+        };
+
+        let marker_color: Color = self.style.color.into();
+        let fill_color = self.style.effective_fill_color();
+        let text_color = export::svg_color(fill_color.contrasting());
+
+        let color = export::svg_color(self.style.color);
+        let opacity = marker_color.a;
+        let element = format!(
+            r#"<g><circle cx="{x}" cy="{y}" r="{}" fill="{}" fill-opacity="{}"/><circle cx="{x}" cy="{y}" r="{circle_radius}" fill="none" stroke="{color}" stroke-opacity="{opacity}" stroke-width="{}"/><text x="{x}" y="{y}" font-size="{font_size}" text-anchor="middle" dominant-baseline="central" fill="{text_color}">{}</text></g>"#,
+            circle_radius * 0.8,
+            export::svg_color(fill_color),
+            fill_color.a as f32 / 255.0,
+            line_width * 2.0,
+            self.number,
+            x = self.pos.x,
+            y = self.pos.y,
+        );
+        Some(export::svg_with_effects(&self.style, id, element))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.pos += offset;
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // markers keep their size, only the center follows
+        self.pos = math::rect_map_point(self.pos, from, to);
+    }
+}
+
+impl MarkerTool {
+    /// Connects a restored marker to this tool, numbering continues after it.
+    pub fn restore(&self, mut marker: Marker) -> Marker {
+        marker.tool_next_number = self.next_number.clone();
+        let mut next_number = self.next_number.borrow_mut();
+        *next_number = (*next_number).max(marker.number + 1);
+        marker
     }
 }
 
@@ -135,6 +240,7 @@ impl Tool for MarkerTool {
                         number: *self.next_number.borrow(),
                         style: self.style,
                         tool_next_number: self.next_number.clone(),
+                        radius: Cell::new(0.0),
                     };
 
                     // increment for next
@@ -7,33 +7,40 @@ use std::{
 };
 
 use anyhow::Result;
-use femtovg::{renderer::OpenGl, Canvas, FontId};
+use femtovg::{imgref::ImgVec, renderer::OpenGl, rgb::RGBA8, Canvas};
 use gdk_pixbuf::{
     glib::{Variant, VariantTy},
     prelude::{StaticVariantType, ToVariant},
+    Pixbuf,
 };
 
 use glib::variant::FromVariant;
 use relm4::gtk::{self, IMMulticontext};
-use serde_derive::Deserialize;
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     command_line,
-    math::Vec2D,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
     sketch_board::{InputEvent, KeyEventMsg, MouseEventMsg, TextEventMsg},
-    style::Style,
+    style::{Color, Style},
 };
 
 mod arrow;
 mod blur;
 mod brush;
+mod callout;
+mod command;
 mod crop;
 mod ellipse;
 mod highlight;
 mod line;
+mod magnifier;
 mod marker;
 mod pointer;
+mod polygon;
 mod rectangle;
+mod spotlight;
 mod text;
 
 pub enum ToolEvent {
@@ -111,10 +118,25 @@ pub trait Tool {
         ToolUpdateResult::Unmodified
     }
 
+    /// Called after the committed drawables changed outside of the tool, e.g. by undo or redo.
+    fn handle_drawables_changed(&mut self) -> ToolUpdateResult {
+        ToolUpdateResult::Unmodified
+    }
+
     fn set_im_context(&mut self, _context: Option<InputContext>) {}
 
     fn get_drawable(&self) -> Option<&dyn Drawable>;
 
+    /// Helpers that are only shown on screen, but never end up in the rendered image.
+    fn get_overlay(&self) -> Option<&dyn Drawable> {
+        None
+    }
+
+    /// Index of a committed drawable that is not rendered, because the tool edits a copy of it.
+    fn get_hidden_drawable(&self) -> Option<usize> {
+        None
+    }
+
     fn get_tool_type(&self) -> Tools;
 }
 
@@ -140,26 +162,154 @@ where
 }
 
 pub trait Drawable: DrawableClone + Debug {
-    fn draw(&self, canvas: &mut Canvas<OpenGl>, font: FontId, bounds: (Vec2D, Vec2D))
+    fn draw(&self, canvas: &mut Canvas<OpenGl>, fonts: Fonts, bounds: (Vec2D, Vec2D))
         -> Result<()>;
-    fn handle_undo(&mut self) {}
-    fn handle_redo(&mut self) {}
+
+    /// Called when the drawable is taken off the stack, e.g. by undo, delete or reset.
+    fn handle_removed(&mut self) {}
+
+    /// Called when the drawable is put on the stack, e.g. by commit or redo.
+    fn handle_added(&mut self) {}
+
+    /// Position and (positive) size of the area covered by the drawable in image coordinates.
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        None
+    }
+
+    /// Whether `pos` hits the drawable, `tolerance` extends the area that counts as a hit.
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        self.bounding_box()
+            .is_some_and(|bounding_box| math::rect_contains(bounding_box, pos, tolerance))
+    }
+
+    fn translate(&mut self, _offset: Vec2D) {}
+
+    fn style(&self) -> Option<Style> {
+        None
+    }
+
+    fn set_style(&mut self, _style: Style) {}
+
+    /// Transforms the drawable so that the former bounding box `from` ends up at `to`.
+    fn resize(&mut self, _from: (Vec2D, Vec2D), _to: (Vec2D, Vec2D)) {}
+
+    /// Serializable copy of the drawable, used for project files.
+    fn to_data(&self) -> Option<DrawableData> {
+        None
+    }
+
+    /// Destroys the covered part of the background pixels, for exports that embed the
+    /// background instead of drawing the drawable on top of it.
+    fn redact(&self, _background: &mut ImgVec<RGBA8>) {}
+
+    /// Vector description of the drawable as svg element(s), `id` is unique per drawable.
+    fn to_svg(&self, _id: usize) -> Option<String> {
+        None
+    }
+
+    /// Short human readable summary, shown in the history.
+    fn describe(&self) -> Option<DrawableDescription> {
+        None
+    }
+
+    /// Area that is kept bright, all spotlights of the image share one dimmed overlay.
+    fn spotlight(&self) -> Option<Spotlight> {
+        None
+    }
 }
 
+/// Pixels of `region` of the background, with the redactions of all drawables applied.
+pub fn redacted_background(
+    background: &Pixbuf,
+    drawables: &[Box<dyn Drawable>],
+    region: (i32, i32, i32, i32),
+) -> ImgVec<RGBA8> {
+    let mut pixels =
+        blur::Blur::background_pixels(background, (0, 0, background.width(), background.height()));
+    for drawable in drawables {
+        drawable.redact(&mut pixels);
+    }
+
+    let (x, y, width, height) = region;
+    let (buf, width, height) = pixels
+        .sub_image(x as usize, y as usize, width as usize, height as usize)
+        .to_contiguous_buf();
+    ImgVec::new(buf.into_owned(), width, height)
+}
+
+#[derive(Debug, Clone)]
+pub struct DrawableDescription {
+    pub tool: Tools,
+    pub color: Option<Color>,
+    pub details: String,
+}
+
+impl DrawableDescription {
+    pub fn new(tool: Tools, color: Option<Color>, details: impl Into<String>) -> Self {
+        Self {
+            tool,
+            color,
+            details: details.into(),
+        }
+    }
+
+    /// Width and height of an area, e.g. `120×80`.
+    pub fn format_size(size: Vec2D) -> String {
+        format!("{:.0}×{:.0}", size.x.abs(), size.y.abs())
+    }
+}
+
+impl Display for DrawableDescription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.details.is_empty() {
+            write!(f, "{}", self.tool.display_name())
+        } else {
+            write!(f, "{} {}", self.tool.display_name(), self.details)
+        }
+    }
+}
+
+/// All drawables that can be stored in a project file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum DrawableData {
+    Arrow(arrow::Arrow),
+    Blur(blur::Blur),
+    Brush(brush::BrushDrawable),
+    Callout(callout::Callout),
+    Ellipse(ellipse::Ellipse),
+    Highlight(highlight::HighlightKind),
+    Line(line::Line),
+    Magnifier(magnifier::Magnifier),
+    Marker(marker::Marker),
+    Polygon(polygon::Polygon),
+    Rectangle(rectangle::Rectangle),
+    Spotlight(spotlight::Spotlight),
+    Text(text::Text),
+}
+
+pub type DrawableStore = Rc<RefCell<Vec<Box<dyn Drawable>>>>;
+
 #[derive(Debug)]
 pub enum ToolUpdateResult {
     Commit(Box<dyn Drawable>),
+    Edit(EditCommand),
     Redraw,
     Unmodified,
 }
 
-pub use arrow::ArrowTool;
-pub use blur::BlurTool;
-pub use crop::CropTool;
+pub use arrow::{ArrowHead, ArrowTool};
+pub use blur::{BlurModes, BlurTool};
+pub use callout::CalloutTool;
+pub use command::{EditCommand, History, HistoryEntry};
+pub use crop::{Crop, CropTool};
 pub use ellipse::EllipseTool;
 pub use highlight::{HighlightTool, Highlighters};
 pub use line::LineTool;
+pub use magnifier::MagnifierTool;
+pub use polygon::PolygonTool;
 pub use rectangle::RectangleTool;
+pub use spotlight::{Spotlight, SpotlightTool};
 pub use text::TextTool;
 
 use self::{brush::BrushTool, marker::MarkerTool, pointer::PointerTool};
@@ -178,6 +328,10 @@ pub enum Tools {
     Blur = 8,
     Highlight = 9,
     Brush = 10,
+    Polygon = 11,
+    Callout = 12,
+    Spotlight = 13,
+    Magnifier = 14,
 }
 
 impl Tools {
@@ -194,6 +348,10 @@ impl Tools {
             Tools::Marker => "Numbered Marker",
             Tools::Blur => "Blur",
             Tools::Highlight => "Highlight",
+            Tools::Polygon => "Polygon",
+            Tools::Callout => "Callout",
+            Tools::Spotlight => "Spotlight",
+            Tools::Magnifier => "Magnifier",
         }
     }
 }
@@ -213,6 +371,10 @@ impl Display for Tools {
             Self::Blur => write!(f, "blur"),
             Self::Highlight => write!(f, "highlight"),
             Self::Brush => write!(f, "brush"),
+            Self::Polygon => write!(f, "polygon"),
+            Self::Callout => write!(f, "callout"),
+            Self::Spotlight => write!(f, "spotlight"),
+            Self::Magnifier => write!(f, "magnifier"),
         }
     }
 }
@@ -220,15 +382,18 @@ impl Display for Tools {
 pub struct ToolsManager {
     tools: HashMap<Tools, Rc<RefCell<dyn Tool>>>,
     crop_tool: Rc<RefCell<CropTool>>,
+    marker_tool: Rc<RefCell<MarkerTool>>,
+    blur_tool: Rc<RefCell<BlurTool>>,
+    magnifier_tool: Rc<RefCell<MagnifierTool>>,
 }
 
 impl ToolsManager {
-    pub fn new() -> Self {
+    pub fn new(drawables: DrawableStore, background: Pixbuf) -> Self {
         let mut tools: HashMap<Tools, Rc<RefCell<dyn Tool>>> = HashMap::new();
         //tools.insert(Tools::Crop, Rc::new(RefCell::new(CropTool::default())));
         tools.insert(
             Tools::Pointer,
-            Rc::new(RefCell::new(PointerTool::default())),
+            Rc::new(RefCell::new(PointerTool::new(drawables.clone()))),
         );
         tools.insert(Tools::Line, Rc::new(RefCell::new(LineTool::default())));
         tools.insert(Tools::Arrow, Rc::new(RefCell::new(ArrowTool::default())));
@@ -240,17 +405,39 @@ impl ToolsManager {
             Tools::Ellipse,
             Rc::new(RefCell::new(EllipseTool::default())),
         );
-        tools.insert(Tools::Text, Rc::new(RefCell::new(TextTool::default())));
-        tools.insert(Tools::Blur, Rc::new(RefCell::new(BlurTool::default())));
+        tools.insert(Tools::Text, Rc::new(RefCell::new(TextTool::new(drawables))));
+        tools.insert(
+            Tools::Callout,
+            Rc::new(RefCell::new(CalloutTool::default())),
+        );
+        let blur_tool = Rc::new(RefCell::new(BlurTool::new(background.clone())));
+        tools.insert(Tools::Blur, blur_tool.clone());
+        let magnifier_tool = Rc::new(RefCell::new(MagnifierTool::new(background)));
+        tools.insert(Tools::Magnifier, magnifier_tool.clone());
         tools.insert(
             Tools::Highlight,
             Rc::new(RefCell::new(HighlightTool::default())),
         );
-        tools.insert(Tools::Marker, Rc::new(RefCell::new(MarkerTool::default())));
+        tools.insert(
+            Tools::Spotlight,
+            Rc::new(RefCell::new(SpotlightTool::default())),
+        );
+        let marker_tool = Rc::new(RefCell::new(MarkerTool::default()));
+        tools.insert(Tools::Marker, marker_tool.clone());
         tools.insert(Tools::Brush, Rc::new(RefCell::new(BrushTool::default())));
+        tools.insert(
+            Tools::Polygon,
+            Rc::new(RefCell::new(PolygonTool::default())),
+        );
 
         let crop_tool = Rc::new(RefCell::new(CropTool::default()));
-        Self { tools, crop_tool }
+        Self {
+            tools,
+            crop_tool,
+            marker_tool,
+            blur_tool,
+            magnifier_tool,
+        }
     }
 
     pub fn get(&self, tool: &Tools) -> Rc<RefCell<dyn Tool>> {
@@ -269,6 +456,25 @@ impl ToolsManager {
     pub fn get_crop_tool(&self) -> Rc<RefCell<CropTool>> {
         self.crop_tool.clone()
     }
+
+    /// Turns a drawable loaded from a project file back into a committed drawable.
+    pub fn restore_drawable(&self, data: DrawableData) -> Box<dyn Drawable> {
+        match data {
+            DrawableData::Arrow(d) => Box::new(d),
+            DrawableData::Blur(d) => Box::new(self.blur_tool.borrow().restore(d)),
+            DrawableData::Brush(d) => Box::new(d),
+            DrawableData::Callout(d) => Box::new(d),
+            DrawableData::Ellipse(d) => Box::new(d),
+            DrawableData::Highlight(d) => Box::new(d),
+            DrawableData::Line(d) => Box::new(d),
+            DrawableData::Magnifier(d) => Box::new(self.magnifier_tool.borrow().restore(d)),
+            DrawableData::Marker(d) => Box::new(self.marker_tool.borrow().restore(d)),
+            DrawableData::Polygon(d) => Box::new(d),
+            DrawableData::Rectangle(d) => Box::new(d),
+            DrawableData::Spotlight(d) => Box::new(d),
+            DrawableData::Text(d) => Box::new(d),
+        }
+    }
 }
 
 impl StaticVariantType for Tools {
@@ -297,6 +503,10 @@ impl FromVariant for Tools {
             8 => Some(Tools::Blur),
             9 => Some(Tools::Highlight),
             10 => Some(Tools::Brush),
+            11 => Some(Tools::Polygon),
+            12 => Some(Tools::Callout),
+            13 => Some(Tools::Spotlight),
+            14 => Some(Tools::Magnifier),
             _ => None,
         })
     }
@@ -316,6 +526,10 @@ impl From<command_line::Tools> for Tools {
             command_line::Tools::Blur => Self::Blur,
             command_line::Tools::Highlight => Self::Highlight,
             command_line::Tools::Brush => Self::Brush,
+            command_line::Tools::Polygon => Self::Polygon,
+            command_line::Tools::Callout => Self::Callout,
+            command_line::Tools::Spotlight => Self::Spotlight,
+            command_line::Tools::Magnifier => Self::Magnifier,
         }
     }
 }
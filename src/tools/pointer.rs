@@ -1,8 +1,239 @@
-use super::{Tool, Tools};
+use anyhow::Result;
+use femtovg::{Color, Paint, Path};
+use relm4::gtk::gdk::Key;
+
+use crate::{
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType},
+    style::Style,
+};
+
+use super::{
+    crop::{Crop, CropHandle},
+    Drawable, DrawableStore, EditCommand, Tool, ToolUpdateResult, Tools,
+};
 
-#[derive(Default)]
 pub struct PointerTool {
     input_enabled: bool,
+    drawables: DrawableStore,
+    selection: Option<Selection>,
+    action: Option<PointerAction>,
+    // the style of the toolbars, changes to it are applied to the selection
+    style: Style,
+}
+
+/// The selected drawable, rendered as an outline with resize handles.
+#[derive(Clone, Debug)]
+pub struct Selection {
+    index: usize,
+    drawables: DrawableStore,
+}
+
+enum PointerAction {
+    Move {
+        original: Box<dyn Drawable>,
+    },
+    Resize {
+        handle: CropHandle,
+        bounding_box: (Vec2D, Vec2D),
+        original: Box<dyn Drawable>,
+    },
+}
+
+impl Selection {
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.drawables
+            .borrow()
+            .get(self.index)
+            .and_then(|d| d.bounding_box())
+    }
+}
+
+impl Drawable for Selection {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some((pos, size)) = self.bounding_box() else {
+            return Ok(());
+        };
+        let scale = canvas.transform().average_scale();
+
+        let mut outline = Path::new();
+        outline.rect(pos.x, pos.y, size.x, size.y);
+
+        canvas.save();
+        canvas.stroke_path(
+            &outline,
+            &Paint::color(Color::rgbf(0.1, 0.1, 0.1)).with_line_width(3.0 / scale),
+        );
+        canvas.stroke_path(
+            &outline,
+            &Paint::color(Color::rgbf(0.9, 0.9, 0.9)).with_line_width(1.0 / scale),
+        );
+        for handle in CropHandle::all() {
+            Crop::draw_single_handle(canvas, handle.position(pos, size), scale);
+        }
+        canvas.restore();
+
+        Ok(())
+    }
+}
+
+impl PointerTool {
+    const HIT_TOLERANCE: f32 = 10.0;
+    const HANDLE_MARGIN_2: f32 = 15.0 * 15.0;
+
+    pub fn new(drawables: DrawableStore) -> Self {
+        Self {
+            input_enabled: false,
+            drawables,
+            selection: None,
+            action: None,
+            style: Style::default(),
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selection = Some(Selection {
+            index,
+            drawables: self.drawables.clone(),
+        });
+    }
+
+    fn find_drawable_at(&self, pos: Vec2D) -> Option<usize> {
+        // topmost drawable first
+        self.drawables
+            .borrow()
+            .iter()
+            .rposition(|d| d.hit_test(pos, Self::HIT_TOLERANCE))
+    }
+
+    fn transformed(action: &PointerAction, direction: Vec2D) -> Box<dyn Drawable> {
+        match action {
+            PointerAction::Move { original } => {
+                let mut drawable = original.clone_box();
+                drawable.translate(direction);
+                drawable
+            }
+            PointerAction::Resize {
+                handle,
+                bounding_box,
+                original,
+            } => {
+                let (top_left, bottom_right) =
+                    handle.apply_drag(bounding_box.0, bounding_box.0 + bounding_box.1, direction);
+                let mut drawable = original.clone_box();
+                drawable.resize(*bounding_box, (top_left, bottom_right - top_left));
+                drawable
+            }
+        }
+    }
+
+    fn begin_drag(&mut self, pos: Vec2D) -> ToolUpdateResult {
+        if let Some(selection) = &self.selection {
+            if let Some(bounding_box) = selection.bounding_box() {
+                let original = self.drawables.borrow()[selection.index].clone_box();
+                if let Some(handle) =
+                    CropHandle::hit(bounding_box.0, bounding_box.1, pos, Self::HANDLE_MARGIN_2)
+                {
+                    self.action = Some(PointerAction::Resize {
+                        handle,
+                        bounding_box,
+                        original,
+                    });
+                    return ToolUpdateResult::Redraw;
+                } else if math::rect_contains(bounding_box, pos, 0.0) {
+                    self.action = Some(PointerAction::Move { original });
+                    return ToolUpdateResult::Redraw;
+                }
+            }
+        }
+
+        match self.find_drawable_at(pos) {
+            Some(index) => {
+                let original = self.drawables.borrow()[index].clone_box();
+                self.select(index);
+                self.action = Some(PointerAction::Move { original });
+                ToolUpdateResult::Redraw
+            }
+            None => {
+                if self.selection.take().is_some() {
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+        }
+    }
+
+    fn update_drag(&mut self, direction: Vec2D) -> ToolUpdateResult {
+        let (Some(selection), Some(action)) = (&self.selection, &self.action) else {
+            return ToolUpdateResult::Unmodified;
+        };
+
+        // preview the change directly in the stack, it gets recorded when the drag ends
+        self.drawables.borrow_mut()[selection.index] = Self::transformed(action, direction);
+        ToolUpdateResult::Redraw
+    }
+
+    fn end_drag(&mut self, direction: Vec2D) -> ToolUpdateResult {
+        let (Some(selection), Some(action)) = (&self.selection, self.action.take()) else {
+            return ToolUpdateResult::Unmodified;
+        };
+
+        let transformed = Self::transformed(&action, direction);
+        let original = match action {
+            PointerAction::Move { original } | PointerAction::Resize { original, .. } => original,
+        };
+
+        // restore the original, so that the edit can be undone
+        self.drawables.borrow_mut()[selection.index] = original;
+
+        if direction.is_zero() {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Edit(EditCommand::Modify {
+                index: selection.index,
+                drawable: transformed,
+            })
+        }
+    }
+
+    /// Moves the selection `steps` up (positive) or down (negative) the stack of drawables.
+    fn reorder(&mut self, steps: isize) -> ToolUpdateResult {
+        let Some(selection) = &mut self.selection else {
+            return ToolUpdateResult::Unmodified;
+        };
+        let last = self.drawables.borrow().len().saturating_sub(1);
+        let to = selection.index.saturating_add_signed(steps).min(last);
+        if to == selection.index {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        let from = std::mem::replace(&mut selection.index, to);
+        ToolUpdateResult::Edit(EditCommand::Reorder { from, to })
+    }
+
+    fn cancel(&mut self) -> ToolUpdateResult {
+        if let (Some(selection), Some(action)) = (&self.selection, self.action.take()) {
+            let original = match action {
+                PointerAction::Move { original } | PointerAction::Resize { original, .. } => {
+                    original
+                }
+            };
+            self.drawables.borrow_mut()[selection.index] = original;
+        }
+
+        if self.selection.take().is_some() {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
 }
 
 impl Tool for PointerTool {
@@ -14,6 +245,13 @@ impl Tool for PointerTool {
         None
     }
 
+    fn get_overlay(&self) -> Option<&dyn Drawable> {
+        match &self.selection {
+            Some(s) => Some(s),
+            None => None,
+        }
+    }
+
     fn input_enabled(&self) -> bool {
         self.input_enabled
     }
@@ -21,4 +259,97 @@ impl Tool for PointerTool {
     fn set_input_enabled(&mut self, value: bool) {
         self.input_enabled = value;
     }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        if event.button != MouseButton::Primary {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match event.type_ {
+            MouseEventType::BeginDrag => self.begin_drag(event.pos),
+            MouseEventType::UpdateDrag => self.update_drag(event.pos),
+            MouseEventType::EndDrag => self.end_drag(event.pos),
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape {
+            return self.cancel();
+        }
+
+        if self.action.is_some() {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        match event.key {
+            Key::Delete | Key::BackSpace => match self.selection.take() {
+                Some(selection) => ToolUpdateResult::Edit(EditCommand::Remove {
+                    index: selection.index,
+                }),
+                None => ToolUpdateResult::Unmodified,
+            },
+            Key::Page_Up => self.reorder(1),
+            Key::Page_Down => self.reorder(-1),
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        let previous = std::mem::replace(&mut self.style, style);
+        let Some(selection) = self.selection.as_ref().filter(|_| style != previous) else {
+            return ToolUpdateResult::Unmodified;
+        };
+        let Some(mut drawable_style) = self.drawables.borrow()[selection.index].style() else {
+            return ToolUpdateResult::Unmodified;
+        };
+
+        // only what was changed in the toolbar, the rest of the style stays as it is
+        if style.color != previous.color {
+            drawable_style.color = style.color;
+        }
+        if style.size != previous.size {
+            drawable_style.size = style.size;
+        }
+        if style.fill != previous.fill {
+            drawable_style.fill = style.fill;
+        }
+        if style.dash != previous.dash {
+            drawable_style.dash = style.dash;
+        }
+        if style.fill_color != previous.fill_color {
+            drawable_style.fill_color = style.fill_color;
+        }
+        if style.fill_opacity != previous.fill_opacity {
+            drawable_style.fill_opacity = style.fill_opacity;
+        }
+        if style.shadow != previous.shadow {
+            drawable_style.shadow = style.shadow;
+        }
+        if style.outline != previous.outline {
+            drawable_style.outline = style.outline;
+        }
+        if style.annotation_size_factor != previous.annotation_size_factor {
+            drawable_style.annotation_size_factor = style.annotation_size_factor;
+        }
+
+        ToolUpdateResult::Edit(EditCommand::Style {
+            index: selection.index,
+            style: drawable_style,
+        })
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.cancel()
+    }
+
+    fn handle_drawables_changed(&mut self) -> ToolUpdateResult {
+        // indices might not be valid anymore
+        self.action = None;
+        if self.selection.take().is_some() {
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
 }
@@ -0,0 +1,329 @@
+use anyhow::Result;
+use femtovg::Path;
+use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType},
+    style::Style,
+};
+
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
+
+// vertices closer than this are merged, e.g. the extra click of a double-click
+const MIN_VERTEX_DISTANCE: f32 = 3.0;
+
+#[derive(Default)]
+pub struct PolygonTool {
+    polygon: Option<Polygon>,
+    drag_start: Option<Vec2D>,
+    style: Style,
+    input_enabled: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Polygon {
+    points: Vec<Vec2D>,
+    closed: bool,
+    style: Style,
+}
+
+impl Polygon {
+    fn filled(&self) -> bool {
+        self.closed && self.style.fill
+    }
+
+    fn segments(&self) -> impl Iterator<Item = (Vec2D, Vec2D)> + '_ {
+        let closing = match (self.closed, self.points.first(), self.points.last()) {
+            (true, Some(first), Some(last)) => Some((*last, *first)),
+            _ => None,
+        };
+        self.points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+    }
+}
+
+impl Drawable for Polygon {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        let Some(first) = self.points.first() else {
+            return Ok(());
+        };
+
+        canvas.save();
+
+        let mut path = Path::new();
+        path.move_to(first.x, first.y);
+        for p in self.points.iter().skip(1) {
+            path.line_to(p.x, p.y);
+        }
+        if self.closed {
+            path.close();
+        }
+
+        if self.points.len() == 1 {
+            // single vertex while drawing, show where the shape starts
+            let mut helpers = Path::new();
+            helpers.circle(first.x, first.y, 2.0);
+            canvas.stroke_path(
+                &helpers,
+                &femtovg::Paint::color(femtovg::Color::rgba(128, 128, 128, 255)),
+            );
+        } else {
+            for layer in self.style.layers() {
+                if self.filled() {
+                    layer.fill_path(canvas, &path);
+                } else {
+                    layer.stroke_path(canvas, &path);
+                }
+            }
+        }
+
+        canvas.restore();
+
+        Ok(())
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        math::points_bounding_box(self.points.iter().copied())
+    }
+
+    fn hit_test(&self, pos: Vec2D, tolerance: f32) -> bool {
+        if self.filled() && math::polygon_contains(&self.points, pos) {
+            return true;
+        }
+
+        let half_width = self
+            .style
+            .size
+            .to_line_width(self.style.annotation_size_factor)
+            / 2.0;
+        self.segments().any(|(start, end)| {
+            math::distance_to_segment(pos, start, end) <= half_width + tolerance
+        })
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Polygon(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Polygon,
+            Some(self.style.color),
+            format!("{} points", self.points.len()),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        if self.points.len() < 2 {
+            return None;
+        }
+        let element = if self.closed { "polygon" } else { "polyline" };
+        Some(export::svg_with_effects(
+            &self.style,
+            id,
+            format!(
+                r#"<{element} points="{}" {}/>"#,
+                export::svg_points(&self.points),
+                export::svg_shape_paint(&self.style, self.filled())
+            ),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        for p in &mut self.points {
+            *p += offset;
+        }
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        for p in &mut self.points {
+            *p = math::rect_map_point(*p, from, to);
+        }
+    }
+}
+
+impl PolygonTool {
+    /// Position of a vertex following `previous`, with shift the segment snaps to 15° steps.
+    fn vertex_position(previous: Option<Vec2D>, pos: Vec2D, modifier: ModifierType) -> Vec2D {
+        match previous {
+            Some(previous)
+                if modifier.intersects(ModifierType::SHIFT_MASK) && !(pos - previous).is_zero() =>
+            {
+                previous + (pos - previous).snapped_vector_15deg()
+            }
+            _ => pos,
+        }
+    }
+
+    fn finish(&mut self, closed: bool) -> ToolUpdateResult {
+        let Some(mut polygon) = self.polygon.take() else {
+            return ToolUpdateResult::Unmodified;
+        };
+        self.drag_start = None;
+
+        polygon
+            .points
+            .dedup_by(|a, b| a.distance_to(b) < MIN_VERTEX_DISTANCE);
+        if closed
+            && polygon.points.len() > 2
+            && polygon.points[0].distance_to(&polygon.points[polygon.points.len() - 1])
+                < MIN_VERTEX_DISTANCE
+        {
+            polygon.points.pop();
+        }
+
+        if polygon.points.len() < 2 {
+            return ToolUpdateResult::Redraw;
+        }
+        polygon.closed = closed;
+        ToolUpdateResult::Commit(polygon.clone_box())
+    }
+}
+
+impl Tool for PolygonTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        match event.type_ {
+            MouseEventType::Click => {
+                if event.button != MouseButton::Primary {
+                    return ToolUpdateResult::Unmodified;
+                }
+                match &mut self.polygon {
+                    Some(polygon) => {
+                        let pos = Self::vertex_position(
+                            polygon.points.last().copied(),
+                            event.pos,
+                            event.modifier,
+                        );
+                        polygon.points.push(pos);
+                    }
+                    None => {
+                        self.polygon = Some(Polygon {
+                            points: vec![event.pos],
+                            closed: false,
+                            style: self.style,
+                        });
+                    }
+                }
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::DoubleClick if event.button == MouseButton::Primary => {
+                self.finish(!event.modifier.intersects(ModifierType::CONTROL_MASK))
+            }
+            MouseEventType::DoubleClick => ToolUpdateResult::Unmodified,
+            MouseEventType::BeginDrag => {
+                // the click of the same press already added the vertex
+                if self.polygon.is_some() {
+                    self.drag_start = Some(event.pos);
+                }
+                ToolUpdateResult::Unmodified
+            }
+            MouseEventType::UpdateDrag | MouseEventType::EndDrag => {
+                let (Some(polygon), Some(start)) = (&mut self.polygon, self.drag_start) else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.type_ == MouseEventType::EndDrag {
+                    self.drag_start = None;
+                }
+                if event.pos.is_zero() {
+                    return ToolUpdateResult::Unmodified;
+                }
+
+                // dragging away from the first vertex draws the first segment, like the line tool
+                if polygon.points.len() == 1 {
+                    polygon.points.push(start);
+                }
+                let count = polygon.points.len();
+                polygon.points[count - 1] = Self::vertex_position(
+                    Some(polygon.points[count - 2]),
+                    start + event.pos,
+                    event.modifier,
+                );
+                ToolUpdateResult::Redraw
+            }
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if self.polygon.is_none() {
+            return ToolUpdateResult::Unmodified;
+        }
+
+        if event.key == Key::Escape {
+            self.polygon = None;
+            self.drag_start = None;
+            ToolUpdateResult::Redraw
+        } else if event.key == Key::Return || event.key == Key::KP_Enter {
+            self.finish(!event.modifier.intersects(ModifierType::CONTROL_MASK))
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_undo(&mut self) -> ToolUpdateResult {
+        if let Some(polygon) = &mut self.polygon {
+            polygon.points.pop();
+            if polygon.points.is_empty() {
+                self.polygon = None;
+            }
+            self.drag_start = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_deactivated(&mut self) -> ToolUpdateResult {
+        self.finish(true)
+    }
+
+    fn active(&self) -> bool {
+        self.polygon.is_some()
+    }
+
+    fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
+        self.style = style;
+        if let Some(polygon) = &mut self.polygon {
+            polygon.style = style;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.polygon {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> super::Tools {
+        Tools::Polygon
+    }
+}
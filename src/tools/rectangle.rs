@@ -1,17 +1,22 @@
 use anyhow::Result;
-use femtovg::{FontId, Path};
+use femtovg::Path;
 use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
 
 use crate::{
     configuration::APP_CONFIG,
-    math::Vec2D,
+    export,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
     sketch_board::{MouseEventMsg, MouseEventType},
     style::Style,
 };
 
-use super::{Drawable, DrawableClone, Tool, ToolUpdateResult, Tools};
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct Rectangle {
     origin: Vec2D,
     top_left: Vec2D,
@@ -25,7 +30,7 @@ impl Drawable for Rectangle {
     fn draw(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        _font: FontId,
+        _fonts: Fonts,
         _bounds: (Vec2D, Vec2D),
     ) -> Result<()> {
         let size = match self.size {
@@ -52,15 +57,76 @@ impl Drawable for Rectangle {
             );
         }
 
-        if self.style.fill {
-            canvas.fill_path(&path, &self.style.into());
-        } else {
-            canvas.stroke_path(&path, &self.style.into());
+        for layer in self.style.layers() {
+            if self.style.fill {
+                layer.fill_path(canvas, &path);
+            } else {
+                layer.stroke_path(canvas, &path);
+            }
         }
         canvas.restore();
 
         Ok(())
     }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Rectangle(*self))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Rectangle,
+            Some(self.style.color),
+            self.size
+                .map(DrawableDescription::format_size)
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let (pos, size) = self.bounding_box()?;
+        Some(export::svg_with_effects(
+            &self.style,
+            id,
+            format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {}/>"#,
+                pos.x,
+                pos.y,
+                size.x,
+                size.y,
+                APP_CONFIG.read().corner_roundness(),
+                export::svg_shape_paint(&self.style, self.style.fill)
+            ),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.origin += offset;
+        self.top_left += offset;
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            let bottom_right = math::rect_map_point(self.top_left + size, from, to);
+            self.origin = math::rect_map_point(self.origin, from, to);
+            self.top_left = top_left;
+            self.size = Some(bottom_right - top_left);
+        }
+    }
 }
 
 impl Rectangle {
@@ -171,7 +237,13 @@ impl Tool for RectangleTool {
 
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
         self.style = style;
-        ToolUpdateResult::Unmodified
+        // the size can change while drawing, e.g. with the mouse wheel
+        if let Some(rectangle) = &mut self.rectangle {
+            rectangle.set_style(style);
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
     }
 
     fn get_drawable(&self) -> Option<&dyn Drawable> {
@@ -0,0 +1,227 @@
+use anyhow::Result;
+use femtovg::Path;
+use relm4::gtk::gdk::{Key, ModifierType};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::{
+    configuration::APP_CONFIG,
+    femtovg_area::Fonts,
+    math::{self, Vec2D},
+    sketch_board::{KeyEventMsg, MouseEventMsg, MouseEventType},
+    style::Style,
+};
+
+use super::{
+    Drawable, DrawableClone, DrawableData, DrawableDescription, Tool, ToolUpdateResult, Tools,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpotlightShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+/// An area that stays bright, everything outside of all spotlights of an image is dimmed by one
+/// shared overlay that is drawn right above the background.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Spotlight {
+    top_left: Vec2D,
+    size: Option<Vec2D>,
+    shape: SpotlightShape,
+}
+
+impl Spotlight {
+    fn area(&self) -> Option<(Vec2D, Vec2D)> {
+        self.size
+            .map(|size| math::rect_ensure_positive_size(self.top_left, size))
+            .filter(|(_, size)| size.x > 0.0 && size.y > 0.0)
+    }
+
+    pub fn path(&self) -> Option<Path> {
+        let (pos, size) = self.area()?;
+        let mut path = Path::new();
+        match self.shape {
+            SpotlightShape::Rectangle => path.rounded_rect(
+                pos.x,
+                pos.y,
+                size.x,
+                size.y,
+                APP_CONFIG.read().corner_roundness(),
+            ),
+            SpotlightShape::Ellipse => path.ellipse(
+                pos.x + size.x / 2.0,
+                pos.y + size.y / 2.0,
+                size.x / 2.0,
+                size.y / 2.0,
+            ),
+        }
+        Some(path)
+    }
+
+    /// The area as svg element, with the given paint attributes.
+    pub fn svg_shape(&self, paint: &str) -> Option<String> {
+        let (pos, size) = self.area()?;
+        Some(match self.shape {
+            SpotlightShape::Rectangle => format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" {paint}/>"#,
+                pos.x,
+                pos.y,
+                size.x,
+                size.y,
+                APP_CONFIG.read().corner_roundness()
+            ),
+            SpotlightShape::Ellipse => format!(
+                r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" {paint}/>"#,
+                pos.x + size.x / 2.0,
+                pos.y + size.y / 2.0,
+                size.x / 2.0,
+                size.y / 2.0
+            ),
+        })
+    }
+
+    fn calculate_shape(&mut self, event: &MouseEventMsg) {
+        self.shape = if event.modifier.intersects(ModifierType::CONTROL_MASK) {
+            SpotlightShape::Ellipse
+        } else {
+            SpotlightShape::Rectangle
+        };
+        self.size = Some(if event.modifier.intersects(ModifierType::SHIFT_MASK) {
+            let max_size = event.pos.x.abs().max(event.pos.y.abs());
+            Vec2D::new(
+                max_size * event.pos.x.signum(),
+                max_size * event.pos.y.signum(),
+            )
+        } else {
+            event.pos
+        });
+    }
+}
+
+impl Drawable for Spotlight {
+    fn draw(
+        &self,
+        _canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        _fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        // the renderer draws the overlay of all spotlights at once
+        Ok(())
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.area()
+    }
+
+    fn spotlight(&self) -> Option<Spotlight> {
+        Some(*self)
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Spotlight(*self))
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        Some(DrawableDescription::new(
+            Tools::Spotlight,
+            None,
+            self.size
+                .map(DrawableDescription::format_size)
+                .unwrap_or_default(),
+        ))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.top_left += offset;
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        if let Some(size) = self.size {
+            let top_left = math::rect_map_point(self.top_left, from, to);
+            let bottom_right = math::rect_map_point(self.top_left + size, from, to);
+            self.top_left = top_left;
+            self.size = Some(bottom_right - top_left);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct SpotlightTool {
+    spotlight: Option<Spotlight>,
+    input_enabled: bool,
+}
+
+impl Tool for SpotlightTool {
+    fn input_enabled(&self) -> bool {
+        self.input_enabled
+    }
+
+    fn set_input_enabled(&mut self, value: bool) {
+        self.input_enabled = value;
+    }
+
+    fn handle_mouse_event(&mut self, event: MouseEventMsg) -> ToolUpdateResult {
+        match event.type_ {
+            MouseEventType::BeginDrag => {
+                self.spotlight = Some(Spotlight {
+                    top_left: event.pos,
+                    size: None,
+                    shape: SpotlightShape::default(),
+                });
+                ToolUpdateResult::Redraw
+            }
+            MouseEventType::EndDrag => {
+                let Some(spotlight) = &mut self.spotlight else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    self.spotlight = None;
+                    ToolUpdateResult::Redraw
+                } else {
+                    spotlight.calculate_shape(&event);
+                    let result = spotlight.clone_box();
+                    self.spotlight = None;
+                    ToolUpdateResult::Commit(result)
+                }
+            }
+            MouseEventType::UpdateDrag => {
+                let Some(spotlight) = &mut self.spotlight else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                if event.pos == Vec2D::zero() {
+                    return ToolUpdateResult::Unmodified;
+                }
+                spotlight.calculate_shape(&event);
+                ToolUpdateResult::Redraw
+            }
+            _ => ToolUpdateResult::Unmodified,
+        }
+    }
+
+    fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Escape && self.spotlight.is_some() {
+            self.spotlight = None;
+            ToolUpdateResult::Redraw
+        } else {
+            ToolUpdateResult::Unmodified
+        }
+    }
+
+    fn handle_style_event(&mut self, _style: Style) -> ToolUpdateResult {
+        // the overlay is always black, only its opacity is configurable
+        ToolUpdateResult::Unmodified
+    }
+
+    fn get_drawable(&self) -> Option<&dyn Drawable> {
+        match &self.spotlight {
+            Some(d) => Some(d),
+            None => None,
+        }
+    }
+
+    fn get_tool_type(&self) -> Tools {
+        Tools::Spotlight
+    }
+}
@@ -1,31 +1,192 @@
 use anyhow::Result;
-use femtovg::{FontId, Paint, Path};
+use femtovg::{Paint, Path};
 use relm4::gtk::prelude::IMContextExt;
 use relm4::gtk::{
-    gdk::{Key, ModifierType, Rectangle},
-    TextBuffer,
+    gdk::{Display, Key, ModifierType, Rectangle},
+    glib::GString,
+    TextBuffer, TextIter, TextTag,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cell::{Cell, OnceCell, RefCell},
+    ops::Range,
 };
-use std::{borrow::Cow, ops::Range};
 
 use relm4::gtk::prelude::*;
 
 use crate::{
+    configuration::APP_CONFIG,
+    export,
+    femtovg_area::Fonts,
     ime::preedit::{Preedit, UnderlineKind},
-    math::Vec2D,
+    math::{self, Vec2D},
     sketch_board::{KeyEventMsg, MouseButton, MouseEventMsg, MouseEventType, TextEventMsg},
-    style::Style,
+    style::{Color, Style},
 };
 
-use super::{Drawable, DrawableClone, InputContext, Tool, ToolUpdateResult, Tools};
+use super::{
+    crop::Crop, Drawable, DrawableData, DrawableDescription, DrawableStore, EditCommand,
+    InputContext, Tool, ToolUpdateResult, Tools,
+};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(into = "TextData", from = "TextData")]
 pub struct Text {
     pos: Vec2D,
     editing: bool,
-    text_buffer: TextBuffer,
+    // created on first use, unlike loading and rendering the text editing needs GTK
+    text_buffer: OnceCell<TextBuffer>,
+    // the text and its formatted runs until there is a buffer
+    stored_text: String,
+    stored_runs: Vec<TextRun>,
     style: Style,
+    align: TextAlign,
+    // box in the fill color behind the text
+    background: bool,
+    // lines wrap within a box of this width, without one at the border of the image
+    wrap_width: Option<f32>,
+    // formatting toggled without a selection, applies to what is typed next
+    pending_format: Option<TextFormat>,
     preedit: Option<Preedit>,
     im_context: Option<InputContext>,
+    // depends on the font metrics, so it is only known after drawing
+    bounds: Cell<Option<(Vec2D, Vec2D)>>,
+    // clicked position the cursor moves to, it also needs the font metrics; with the flag set
+    // the selection is extended to it instead
+    click: Cell<Option<(Vec2D, bool)>>,
+    // byte ranges of the lines without their line break as they were drawn last, automatic
+    // wrapping included
+    drawn_lines: RefCell<Vec<Range<usize>>>,
+}
+
+/// Formatting of a run of characters, runs without a color of their own take the one of the
+/// style.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TextFormat {
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub italic: bool,
+    #[serde(default)]
+    pub underline: bool,
+    #[serde(default)]
+    pub color: Option<Color>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Formatted characters of a text, `start` and `end` count characters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TextRun {
+    start: usize,
+    end: usize,
+    format: TextFormat,
+}
+
+/// The persistent part of a [`Text`], the buffer is recreated on load.
+#[derive(PartialEq, Serialize, Deserialize)]
+struct TextData {
+    pos: Vec2D,
+    text: String,
+    style: Style,
+    #[serde(default)]
+    runs: Vec<TextRun>,
+    #[serde(default)]
+    align: TextAlign,
+    #[serde(default)]
+    background: bool,
+    #[serde(default)]
+    wrap_width: Option<f32>,
+}
+
+impl From<Text> for TextData {
+    fn from(value: Text) -> Self {
+        Self {
+            pos: value.pos,
+            text: value.text().to_string(),
+            style: value.style,
+            // unformatted text is the common case, it needs no runs
+            runs: value
+                .runs()
+                .into_iter()
+                .filter(|run| run.format != TextFormat::default())
+                .collect(),
+            align: value.align,
+            background: value.background,
+            wrap_width: value.wrap_width,
+        }
+    }
+}
+
+impl From<TextData> for Text {
+    fn from(value: TextData) -> Self {
+        let mut text = Text::new(value.pos, value.style, None);
+        text.stored_text = value.text;
+        text.stored_runs = value.runs;
+        text.align = value.align;
+        text.background = value.background;
+        text.wrap_width = value.wrap_width;
+        text.editing = false;
+        text
+    }
+}
+
+const BOLD_TAG: &str = "bold";
+const ITALIC_TAG: &str = "italic";
+const UNDERLINE_TAG: &str = "underline";
+// followed by the color as rrggbbaa
+const COLOR_TAG_PREFIX: &str = "color-";
+
+/// The displayed text with its formatting, the runs cover all of the text in byte ranges.
+struct FormattedText<'a> {
+    text: &'a str,
+    runs: Vec<(Range<usize>, TextFormat)>,
+    // the regular font in the color of the style
+    paint: Paint,
+    fonts: Fonts,
+}
+
+impl FormattedText<'_> {
+    fn run_paint(&self, format: TextFormat) -> Paint {
+        let mut paint = self.paint.clone();
+        paint.set_font(&[self.fonts.get(format.bold, format.italic)]);
+        if let Some(color) = format.color {
+            paint.set_color(color.into());
+        }
+        paint
+    }
+
+    /// The runs cut to the part within `range`.
+    fn runs_in(
+        &self,
+        range: Range<usize>,
+    ) -> impl Iterator<Item = (Range<usize>, TextFormat)> + '_ {
+        self.runs.iter().filter_map(move |(run, format)| {
+            let start = run.start.max(range.start);
+            let end = run.end.min(range.end);
+            (start < end).then_some((start..end, *format))
+        })
+    }
+
+    fn width(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        range: Range<usize>,
+    ) -> f32 {
+        self.runs_in(range)
+            .map(|(range, format)| {
+                Text::text_width(canvas, &self.run_paint(format), &self.text[range])
+            })
+            .sum()
+    }
 }
 
 struct DisplayContent<'a> {
@@ -37,12 +198,22 @@ struct DisplayContent<'a> {
 struct LineLayout {
     range: Range<usize>,
     baseline: f32,
+    // start of the line after the alignment is applied
+    x: f32,
 }
 
 struct TextDrawingContext<'a> {
-    paint: &'a Paint,
-    text: &'a str,
+    formatted: &'a FormattedText<'a>,
     lines: &'a [LineLayout],
+    // of the widest line
+    width: f32,
+}
+
+struct TextLayout {
+    lines: Vec<LineLayout>,
+    cursor: CursorMetrics,
+    // of the widest line
+    width: f32,
 }
 
 #[derive(Clone, Copy)]
@@ -53,18 +224,546 @@ struct CursorMetrics {
 }
 
 impl Text {
-    fn new(pos: Vec2D, style: Style, im_context: Option<InputContext>) -> Self {
-        let text_buffer = TextBuffer::new(None);
-        text_buffer.set_enable_undo(true);
-
+    pub(super) fn new(pos: Vec2D, style: Style, im_context: Option<InputContext>) -> Self {
         Self {
             pos,
-            text_buffer,
+            text_buffer: OnceCell::new(),
+            stored_text: String::new(),
+            stored_runs: Vec::new(),
             editing: true,
             style,
+            align: TextAlign::default(),
+            background: false,
+            wrap_width: None,
+            pending_format: None,
             preedit: None,
             im_context,
+            bounds: Cell::new(None),
+            click: Cell::new(None),
+            drawn_lines: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(super) fn pos(&self) -> Vec2D {
+        self.pos
+    }
+
+    pub(super) fn wrap_width(&self) -> Option<f32> {
+        self.wrap_width
+    }
+
+    /// Wraps the lines within a box of `width` from `left` on, without a width they wrap at the
+    /// border of the image.
+    pub(super) fn set_wrap_box(&mut self, left: f32, width: Option<f32>) {
+        self.pos.x = left;
+        self.wrap_width = width;
+    }
+
+    /// Whether `pos` is on the handle at the left (`false`) or the right (`true`) edge of the
+    /// wrap box, the handles are only known after drawing.
+    pub(super) fn wrap_handle_at(&self, pos: Vec2D, margin: f32) -> Option<bool> {
+        let width = self.wrap_width?;
+        let (top_left, size) = self.bounds.get()?;
+        let y = top_left.y + size.y / 2.0;
+        [(self.pos.x, false), (self.pos.x + width, true)]
+            .into_iter()
+            .find(|(x, _)| Vec2D::new(*x, y).distance_to(&pos) <= margin)
+            .map(|(_, right)| right)
+    }
+
+    pub(super) fn text(&self) -> GString {
+        match self.text_buffer.get() {
+            Some(buffer) => buffer.text(&buffer.start_iter(), &buffer.end_iter(), false),
+            None => GString::from(self.stored_text.as_str()),
+        }
+    }
+
+    /// The buffer the text is edited in, created with the stored text on first use.
+    fn text_buffer(&self) -> &TextBuffer {
+        if let Some(buffer) = self.text_buffer.get() {
+            return buffer;
+        }
+
+        let buffer = self.text_buffer.get_or_init(|| {
+            let buffer = TextBuffer::new(None);
+            buffer.set_enable_undo(true);
+            buffer
+        });
+        buffer.begin_irreversible_action();
+        buffer.set_text(&self.stored_text);
+        for run in &self.stored_runs {
+            let start = buffer.iter_at_offset(run.start as i32);
+            let end = buffer.iter_at_offset(run.end as i32);
+            for tag in self.format_tags(run.format) {
+                buffer.apply_tag(&tag, &start, &end);
+            }
+        }
+        buffer.end_irreversible_action();
+        buffer
+    }
+
+    fn cursor_position(&self) -> usize {
+        self.text_buffer
+            .get()
+            .map_or(0, |buffer| buffer.cursor_position() as usize)
+    }
+
+    pub(super) fn set_im_context(&mut self, im_context: Option<InputContext>) {
+        self.im_context = im_context;
+    }
+
+    /// Starts editing a committed text again, with the cursor at `click`.
+    pub(super) fn reopen(&mut self, im_context: Option<InputContext>, click: Vec2D) {
+        self.editing = true;
+        self.im_context = im_context;
+        self.move_cursor_to(click, false);
+    }
+
+    /// Moves the cursor to the character closest to `pos` on the next draw, `extend` selects
+    /// the text in between.
+    pub(super) fn move_cursor_to(&self, pos: Vec2D, extend: bool) {
+        self.click.set(Some((pos, extend)));
+    }
+
+    /// Ends editing, the text is drawn without cursor and preedit from now on.
+    pub(super) fn finish_editing(&mut self) {
+        self.preedit = None;
+        self.editing = false;
+        self.im_context = None;
+    }
+
+    pub(super) fn undo(&self) {
+        self.text_buffer().undo();
+    }
+
+    pub(super) fn redo(&self) {
+        self.text_buffer().redo();
+    }
+
+    fn tag(&self, name: &str) -> TextTag {
+        let table = self.text_buffer().tag_table();
+        table.lookup(name).unwrap_or_else(|| {
+            // only marks the characters, the format is rendered from the name
+            let tag = TextTag::new(Some(name));
+            table.add(&tag);
+            tag
+        })
+    }
+
+    fn format_tags(&self, format: TextFormat) -> Vec<TextTag> {
+        let color = format.color.map(|c| {
+            format!(
+                "{COLOR_TAG_PREFIX}{:02x}{:02x}{:02x}{:02x}",
+                c.r, c.g, c.b, c.a
+            )
+        });
+        [
+            format.bold.then_some(BOLD_TAG),
+            format.italic.then_some(ITALIC_TAG),
+            format.underline.then_some(UNDERLINE_TAG),
+            color.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|name| self.tag(name))
+        .collect()
+    }
+
+    fn format_at(iter: &TextIter) -> TextFormat {
+        let mut format = TextFormat::default();
+        for name in iter.tags().iter().filter_map(|tag| tag.name()) {
+            match name.as_str() {
+                BOLD_TAG => format.bold = true,
+                ITALIC_TAG => format.italic = true,
+                UNDERLINE_TAG => format.underline = true,
+                name => {
+                    if let Some(rgba) = name
+                        .strip_prefix(COLOR_TAG_PREFIX)
+                        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    {
+                        let [r, g, b, a] = rgba.to_be_bytes();
+                        format.color = Some(Color::new(r, g, b, a));
+                    }
+                }
+            }
+        }
+        format
+    }
+
+    /// Consecutive characters of the same format, together they cover the whole text.
+    fn runs(&self) -> Vec<TextRun> {
+        let Some(buffer) = self.text_buffer.get() else {
+            return self.stored_runs_covering();
+        };
+
+        let mut runs: Vec<TextRun> = Vec::new();
+        let mut iter = buffer.start_iter();
+        while !iter.is_end() {
+            let start = iter.offset() as usize;
+            let format = Self::format_at(&iter);
+            iter.forward_to_tag_toggle(None::<&TextTag>);
+            let end = iter.offset() as usize;
+            match runs.last_mut() {
+                Some(last) if last.format == format => last.end = end,
+                _ => runs.push(TextRun { start, end, format }),
+            }
+        }
+        runs
+    }
+
+    /// The stored runs with unformatted runs in between, only formatted runs are stored.
+    fn stored_runs_covering(&self) -> Vec<TextRun> {
+        let len = self.stored_text.chars().count();
+        let mut runs = Vec::new();
+        let mut start = 0;
+        for run in &self.stored_runs {
+            // the file might have been edited by hand
+            let (run_start, run_end) = (run.start.clamp(start, len), run.end.min(len));
+            if run_start >= run_end {
+                continue;
+            }
+            if start < run_start {
+                runs.push(TextRun {
+                    start,
+                    end: run_start,
+                    format: TextFormat::default(),
+                });
+            }
+            runs.push(TextRun {
+                start: run_start,
+                end: run_end,
+                format: run.format,
+            });
+            start = run_end;
+        }
+        if start < len {
+            runs.push(TextRun {
+                start,
+                end: len,
+                format: TextFormat::default(),
+            });
+        }
+        runs
+    }
+
+    /// Format of the text typed next, the one toggled last or of the character before the
+    /// cursor.
+    fn typing_format(&self) -> TextFormat {
+        self.pending_format.unwrap_or_else(|| {
+            let mut iter = self
+                .text_buffer()
+                .iter_at_mark(&self.text_buffer().get_insert());
+            iter.backward_char();
+            Self::format_at(&iter)
+        })
+    }
+
+    fn insert(&mut self, text: &str) {
+        // scripts type without GTK, which the buffer needs, and only ever append
+        if self.text_buffer.get().is_none() && !relm4::gtk::is_initialized_main_thread() {
+            self.stored_text.push_str(text);
+            return;
+        }
+
+        let tags = self.format_tags(self.typing_format());
+        let tags: Vec<&TextTag> = tags.iter().collect();
+        // replacing the selection is a single step to undo
+        self.text_buffer().begin_user_action();
+        self.text_buffer().delete_selection(true, true);
+        let mut iter = self
+            .text_buffer()
+            .iter_at_mark(&self.text_buffer().get_insert());
+        self.text_buffer().insert_with_tags(&mut iter, text, &tags);
+        self.text_buffer().end_user_action();
+    }
+
+    /// Toggles bold, italic or underline of the selection, or of the text typed next.
+    fn toggle_format(&mut self, tag: &str, flag: fn(&mut TextFormat) -> &mut bool) {
+        match self.text_buffer().selection_bounds() {
+            Some((start, end)) => {
+                let tag = self.tag(tag);
+                if start.has_tag(&tag) {
+                    self.text_buffer().remove_tag(&tag, &start, &end);
+                } else {
+                    self.text_buffer().apply_tag(&tag, &start, &end);
+                }
+            }
+            None => {
+                let mut format = self.typing_format();
+                let value = flag(&mut format);
+                *value = !*value;
+                self.pending_format = Some(format);
+            }
+        }
+    }
+
+    /// Colors the selection, or the text typed next.
+    pub(super) fn set_run_color(&mut self, color: Color) {
+        match self.text_buffer().selection_bounds() {
+            Some((start, end)) => {
+                self.text_buffer().tag_table().foreach(|tag| {
+                    if tag
+                        .name()
+                        .is_some_and(|name| name.starts_with(COLOR_TAG_PREFIX))
+                    {
+                        self.text_buffer().remove_tag(tag, &start, &end);
+                    }
+                });
+                for tag in self.format_tags(TextFormat {
+                    color: Some(color),
+                    ..TextFormat::default()
+                }) {
+                    self.text_buffer().apply_tag(&tag, &start, &end);
+                }
+            }
+            None => {
+                self.pending_format = Some(TextFormat {
+                    color: Some(color),
+                    ..self.typing_format()
+                });
+            }
+        }
+    }
+
+    /// Formatting shortcuts, the alignment and the background box.
+    fn handle_format_key(&mut self, event: KeyEventMsg) -> bool {
+        let control = ModifierType::CONTROL_MASK;
+        let control_shift = ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK;
+        match (event.key.to_lower(), event.modifier) {
+            (Key::b, m) if m == control => self.toggle_format(BOLD_TAG, |f| &mut f.bold),
+            (Key::i, m) if m == control => self.toggle_format(ITALIC_TAG, |f| &mut f.italic),
+            (Key::u, m) if m == control => self.toggle_format(UNDERLINE_TAG, |f| &mut f.underline),
+            (Key::l, m) if m == control => self.align = TextAlign::Left,
+            (Key::e, m) if m == control => self.align = TextAlign::Center,
+            (Key::r, m) if m == control => self.align = TextAlign::Right,
+            (Key::b, m) if m == control_shift => self.background = !self.background,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Select all, copy and cut, copying without a selection is left to the keybinds. Pasting is
+    /// read by the sketch board and arrives like typed text.
+    fn handle_selection_key(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.modifier != ModifierType::CONTROL_MASK {
+            return ToolUpdateResult::Unmodified;
+        }
+        let Some(clipboard) = Display::default().map(|display| display.clipboard()) else {
+            return ToolUpdateResult::Unmodified;
+        };
+        let has_selection = self.text_buffer().has_selection();
+
+        match event.key.to_lower() {
+            Key::a => {
+                let (start, end) = self.text_buffer().bounds();
+                self.text_buffer().select_range(&start, &end);
+            }
+            Key::c if has_selection => self.text_buffer().copy_clipboard(&clipboard),
+            Key::x if has_selection => self.text_buffer().cut_clipboard(&clipboard, true),
+            _ => return ToolUpdateResult::Unmodified,
+        }
+        ToolUpdateResult::Redraw
+    }
+
+    /// Inserts committed text or updates the preedit of the input method.
+    pub(super) fn handle_text_event(&mut self, event: TextEventMsg) -> ToolUpdateResult {
+        match event {
+            TextEventMsg::Commit(text) => {
+                self.preedit = None;
+                self.insert(&text);
+                ToolUpdateResult::Redraw
+            }
+            TextEventMsg::Preedit {
+                text,
+                cursor_chars,
+                spans,
+            } => {
+                if text.is_empty() {
+                    if self.preedit.take().is_some() {
+                        ToolUpdateResult::Redraw
+                    } else {
+                        ToolUpdateResult::Unmodified
+                    }
+                } else {
+                    self.preedit = Some(Preedit {
+                        text,
+                        cursor_chars,
+                        spans,
+                    });
+                    ToolUpdateResult::Redraw
+                }
+            }
+            TextEventMsg::PreeditEnd => {
+                if self.preedit.take().is_some() {
+                    ToolUpdateResult::Redraw
+                } else {
+                    ToolUpdateResult::Unmodified
+                }
+            }
+        }
+    }
+
+    /// Line breaks, formatting, the clipboard, deleting, moving the cursor and selecting,
+    /// everything but accepting or discarding the text.
+    pub(super) fn handle_edit_key(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        if event.key == Key::Return && event.modifier == ModifierType::SHIFT_MASK {
+            self.insert("\n");
+            return ToolUpdateResult::Redraw;
+        } else if self.handle_format_key(event) {
+            return ToolUpdateResult::Redraw;
+        }
+
+        let result = match self.handle_selection_key(event) {
+            ToolUpdateResult::Unmodified => self.handle_cursor_key(event),
+            result => result,
+        };
+        // the format of the text at the new position applies from now on
+        if !matches!(result, ToolUpdateResult::Unmodified) {
+            self.pending_format = None;
+        }
+        result
+    }
+
+    fn handle_cursor_key(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
+        // shift moves the cursor without moving the other end of the selection
+        let move_cursor = if event.modifier.contains(ModifierType::SHIFT_MASK) {
+            Action::Select
+        } else {
+            Action::MoveCursor
+        };
+        let event = KeyEventMsg {
+            modifier: event.modifier - ModifierType::SHIFT_MASK,
+            ..event
+        };
+
+        if event.key == Key::BackSpace {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    Action::Delete,
+                    ActionScope::BackwardWord,
+                );
+            } else {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    Action::Delete,
+                    ActionScope::BackwardChar,
+                );
+            }
+        } else if event.key == Key::Delete {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    Action::Delete,
+                    ActionScope::ForwardWord,
+                );
+            } else {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    Action::Delete,
+                    ActionScope::ForwardChar,
+                );
+            }
+        } else if event.key == Key::Left {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::BackwardWord,
+                );
+            } else {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::BackwardChar,
+                );
+            }
+        } else if event.key == Key::Right {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::ForwardWord,
+                );
+            } else {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::ForwardChar,
+                );
+            }
+        } else if event.key == Key::Home {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::BufferStart,
+                );
+            } else {
+                let scope = self
+                    .shown_line_scope(false)
+                    .unwrap_or(ActionScope::BackwardLine);
+                return TextTool::handle_text_buffer_action(self.text_buffer(), move_cursor, scope);
+            }
+        } else if event.key == Key::End {
+            if event.modifier == ModifierType::CONTROL_MASK {
+                return TextTool::handle_text_buffer_action(
+                    self.text_buffer(),
+                    move_cursor,
+                    ActionScope::BufferEnd,
+                );
+            } else {
+                let scope = self
+                    .shown_line_scope(true)
+                    .unwrap_or(ActionScope::ForwardLine);
+                return TextTool::handle_text_buffer_action(self.text_buffer(), move_cursor, scope);
+            }
         }
+        ToolUpdateResult::Unmodified
+    }
+
+    /// `Home` and `End` within the lines as they are drawn: the start or end of the line with
+    /// the cursor, or of the line before or after it if the cursor is there already.
+    fn shown_line_scope(&self, forward: bool) -> Option<ActionScope> {
+        let text = self.text();
+        let lines = self.drawn_lines.borrow();
+        let cursor = Self::byte_index_from_char_index(&text, self.cursor_position());
+        let index = lines.iter().position(|line| cursor <= line.end)?;
+
+        let target = if forward {
+            if cursor < lines[index].end {
+                lines[index].end
+            } else {
+                lines.get(index + 1)?.end
+            }
+        } else if cursor > lines[index].start {
+            lines[index].start
+        } else {
+            lines.get(index.checked_sub(1)?)?.start
+        };
+        // the lines are from the last draw, the text might have changed since
+        let offset = text.get(..target)?.chars().count();
+        Some(ActionScope::Offset(offset as i32))
+    }
+
+    /// The lines as drawn last, the explicit lines if the text was not drawn yet.
+    fn shown_lines(&self, text: &str) -> Vec<Range<usize>> {
+        let lines = self.drawn_lines.borrow();
+        if lines
+            .last()
+            .is_some_and(|line| text.get(line.clone()).is_some())
+        {
+            return lines.clone();
+        }
+
+        let mut start = 0;
+        text.split('\n')
+            .map(|line| {
+                let range = start..start + line.len();
+                start = range.end + 1;
+                range
+            })
+            .collect()
     }
 
     fn byte_index_from_char_index(text: &str, char_index: usize) -> usize {
@@ -75,7 +774,7 @@ impl Text {
     }
 
     fn display_text<'a>(&self, base_text: &'a str) -> DisplayContent<'a> {
-        let cursor_char_index = self.text_buffer.cursor_position() as usize;
+        let cursor_char_index = self.cursor_position();
         let base_cursor_byte = Self::byte_index_from_char_index(base_text, cursor_char_index);
 
         if self.editing {
@@ -93,69 +792,459 @@ impl Text {
                 composed.push_str(&preedit.text);
                 composed.push_str(&base_text[base_cursor_byte..]);
 
-                let preedit_char_len = preedit.text.chars().count();
-                let cursor_chars = preedit
-                    .cursor_chars
-                    .map(|value| value.min(preedit_char_len))
-                    .unwrap_or(preedit_char_len);
-                let preedit_cursor_byte =
-                    Self::byte_index_from_char_index(&preedit.text, cursor_chars);
-                let composed_cursor_byte = base_cursor_byte + preedit_cursor_byte;
+                let preedit_char_len = preedit.text.chars().count();
+                let cursor_chars = preedit
+                    .cursor_chars
+                    .map(|value| value.min(preedit_char_len))
+                    .unwrap_or(preedit_char_len);
+                let preedit_cursor_byte =
+                    Self::byte_index_from_char_index(&preedit.text, cursor_chars);
+                let composed_cursor_byte = base_cursor_byte + preedit_cursor_byte;
+
+                DisplayContent {
+                    text: Cow::Owned(composed),
+                    cursor_byte_pos: composed_cursor_byte,
+                    preedit_range: Some(base_cursor_byte..base_cursor_byte + preedit.text.len()),
+                }
+            } else {
+                DisplayContent {
+                    text: Cow::Borrowed(base_text),
+                    cursor_byte_pos: base_cursor_byte,
+                    preedit_range: None,
+                }
+            }
+        } else {
+            DisplayContent {
+                text: Cow::Borrowed(base_text),
+                cursor_byte_pos: base_cursor_byte,
+                preedit_range: None,
+            }
+        }
+    }
+}
+
+impl Drawable for Text {
+    fn draw(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        fonts: Fonts,
+        _bounds: (Vec2D, Vec2D),
+    ) -> Result<()> {
+        if let Some((click, extend)) = self.click.take().filter(|_| self.editing) {
+            self.place_cursor(canvas, fonts, click, extend)?;
+        }
+
+        let gtext = self.text();
+        let base_text = gtext.as_str();
+        let display = self.display_text(base_text);
+        let formatted = self.formatted(&display, fonts);
+        let layout = self.layout(canvas, &formatted)?;
+        let cursor_metrics = layout.cursor;
+        if display.preedit_range.is_none() {
+            *self.drawn_lines.borrow_mut() = layout
+                .lines
+                .iter()
+                .map(|line| {
+                    line.range.start
+                        ..line.range.start
+                            + formatted.text[line.range.clone()]
+                                .trim_end_matches('\n')
+                                .len()
+                })
+                .collect();
+        }
+
+        let layout_context = TextDrawingContext {
+            formatted: &formatted,
+            lines: &layout.lines,
+            width: layout.width,
+        };
+
+        let text_area = (!layout.lines.is_empty()).then(|| {
+            (
+                Vec2D::new(self.pos.x, self.pos.y + cursor_metrics.top_offset),
+                Vec2D::new(
+                    layout.width,
+                    cursor_metrics.line_height * layout.lines.len() as f32,
+                ),
+            )
+        });
+
+        if self.background {
+            // shadow and outline surround the box instead of the characters
+            if let Some(area) = text_area {
+                let (pos, size) = self.background_area(area);
+                let mut path = Path::new();
+                path.rounded_rect(
+                    pos.x,
+                    pos.y,
+                    size.x,
+                    size.y,
+                    APP_CONFIG.read().corner_roundness(),
+                );
+                for layer in self.style.layers() {
+                    layer.fill_solid(canvas, &path, self.background_color());
+                }
+            }
+        } else {
+            // shadow and outline of all lines go below the text, they would cover the line above
+            for layer in self.style.layers().filter(|l| !l.is_annotation()) {
+                for line in &layout.lines {
+                    Self::fill_line(canvas, &formatted, line, |canvas, x, text, paint, _| {
+                        layer.fill_text(canvas, x, line.baseline, text, paint)
+                    })?;
+                }
+            }
+        }
+
+        if self.editing {
+            if let (Some(preedit), Some(preedit_range)) = (&self.preedit, &display.preedit_range) {
+                self.draw_preedit_background(
+                    canvas,
+                    &layout_context,
+                    preedit,
+                    preedit_range,
+                    cursor_metrics,
+                );
+            } else {
+                self.draw_selection(canvas, &layout_context, base_text, cursor_metrics);
+            }
+        }
+
+        for line in &layout.lines {
+            Self::fill_line(
+                canvas,
+                &formatted,
+                line,
+                |canvas, x, text, paint, format| {
+                    canvas.fill_text(x, line.baseline, text, paint)?;
+                    if format.underline {
+                        let width = Self::text_width(canvas, paint, text.trim_end_matches('\n'));
+                        Self::draw_run_underline(canvas, paint, x, line.baseline, width);
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+
+        self.bounds.set(text_area.map(|area| {
+            if self.background {
+                self.background_area(area)
+            } else {
+                area
+            }
+        }));
+
+        if let Some(width) = self.wrap_width.filter(|_| self.editing) {
+            self.draw_wrap_box(canvas, width, cursor_metrics, layout.lines.len());
+        }
+
+        if self.editing {
+            if let (Some(preedit), Some(preedit_range)) = (&self.preedit, &display.preedit_range) {
+                self.draw_preedit_overlays(
+                    canvas,
+                    &layout_context,
+                    preedit,
+                    preedit_range,
+                    cursor_metrics,
+                )?;
+            }
+        }
+
+        if self.editing {
+            self.draw_cursor_and_update_ime(
+                canvas,
+                &layout_context,
+                cursor_metrics,
+                display.cursor_byte_pos,
+            );
+        }
+
+        Ok(())
+    }
+
+    fn bounding_box(&self) -> Option<(Vec2D, Vec2D)> {
+        self.bounds.get()
+    }
+
+    fn to_data(&self) -> Option<DrawableData> {
+        Some(DrawableData::Text(self.clone()))
+    }
+
+    fn style(&self) -> Option<Style> {
+        Some(self.style)
+    }
+
+    fn set_style(&mut self, style: Style) {
+        self.style = style;
+    }
+
+    fn describe(&self) -> Option<DrawableDescription> {
+        let text = self.text();
+        let first_line = text.lines().next().unwrap_or_default();
+        let mut details: String = first_line.chars().take(20).collect();
+        if details.len() < text.len() {
+            details.push('…');
+        }
+        Some(DrawableDescription::new(
+            Tools::Text,
+            Some(self.style.color),
+            format!("“{details}”"),
+        ))
+    }
+
+    fn to_svg(&self, id: usize) -> Option<String> {
+        let text = self.text();
+        if text.is_empty() {
+            return None;
+        }
+
+        let font_size = self
+            .style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32;
+        let lines = self.shown_lines(&text);
+        let padding = if self.background {
+            self.background_padding()
+        } else {
+            0.0
+        };
+        let (line_height, width) = match self.bounds.get() {
+            Some((_, size)) if size.y > 0.0 => (
+                (size.y - padding * 2.0) / lines.len() as f32,
+                size.x - padding * 2.0,
+            ),
+            _ => (font_size, 0.0),
+        };
+        let font_family = APP_CONFIG
+            .read()
+            .font()
+            .family()
+            .unwrap_or("Roboto")
+            .to_string();
+
+        let anchor = match self.align {
+            TextAlign::Left => "start",
+            TextAlign::Center => "middle",
+            TextAlign::Right => "end",
+        };
+        let x = self.aligned_x(width, 0.0);
+
+        let runs = self.byte_runs(&text);
+        let tspans: String = lines
+            .iter()
+            .enumerate()
+            .map(|(i, range)| {
+                let parts: String = runs
+                    .iter()
+                    .filter_map(|(run, format)| {
+                        let start = run.start.max(range.start);
+                        let end = run.end.min(range.end);
+                        (start < end).then(|| {
+                            format!(
+                                "<tspan{}>{}</tspan>",
+                                svg_format(*format),
+                                export::svg_escape(&text[start..end])
+                            )
+                        })
+                    })
+                    .collect();
+                format!(
+                    r#"<tspan x="{x}" dy="{}" text-anchor="{anchor}">{parts}</tspan>"#,
+                    if i == 0 { 0.0 } else { line_height },
+                )
+            })
+            .collect();
+
+        let background = match self.bounds.get() {
+            Some((pos, size)) if self.background => {
+                let color = self.background_color();
+                format!(
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{}" fill="{}" fill-opacity="{}"/>"#,
+                    pos.x,
+                    pos.y,
+                    size.x,
+                    size.y,
+                    APP_CONFIG.read().corner_roundness(),
+                    export::svg_color(color),
+                    color.a as f32 / 255.0
+                )
+            }
+            _ => String::new(),
+        };
+        let element = format!(
+            r#"<g>{background}<text x="{}" y="{}" font-family="{}" font-size="{font_size}" {}>{tspans}</text></g>"#,
+            self.pos.x,
+            self.pos.y,
+            export::svg_escape(&font_family),
+            export::svg_paint(&self.style, true)
+        );
+        Some(export::svg_with_effects(&self.style, id, element))
+    }
+
+    fn translate(&mut self, offset: Vec2D) {
+        self.pos += offset;
+        self.bounds
+            .set(self.bounds.get().map(|(pos, size)| (pos + offset, size)));
+    }
+
+    fn resize(&mut self, from: (Vec2D, Vec2D), to: (Vec2D, Vec2D)) {
+        // text keeps its font size, only the anchor and the wrap box follow
+        if from.1.x.abs() > f32::EPSILON {
+            self.wrap_width = self
+                .wrap_width
+                .map(|width| (width * to.1.x / from.1.x).abs());
+        }
+        let pos = math::rect_map_point(self.pos, from, to);
+        self.translate(pos - self.pos);
+    }
+}
+
+impl Text {
+    fn paint(&self, fonts: Fonts) -> Paint {
+        let mut paint: Paint = self.style.into();
+        paint.set_font(&[fonts.regular]);
+        paint
+    }
 
-                DisplayContent {
-                    text: Cow::Owned(composed),
-                    cursor_byte_pos: composed_cursor_byte,
-                    preedit_range: Some(base_cursor_byte..base_cursor_byte + preedit.text.len()),
-                }
-            } else {
-                DisplayContent {
-                    text: Cow::Borrowed(base_text),
-                    cursor_byte_pos: base_cursor_byte,
-                    preedit_range: None,
+    /// The runs in byte ranges of `text`, the runs themselves count characters.
+    fn byte_runs(&self, text: &str) -> Vec<(Range<usize>, TextFormat)> {
+        let bytes: Vec<usize> = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain([text.len()])
+            .collect();
+        self.runs()
+            .into_iter()
+            .map(|run| (bytes[run.start]..bytes[run.end], run.format))
+            .collect()
+    }
+
+    fn formatted<'a>(&self, display: &'a DisplayContent<'_>, fonts: Fonts) -> FormattedText<'a> {
+        // the displayed text may contain the preedit, the runs after it move along
+        let mut runs = Vec::new();
+        for (range, format) in self.byte_runs(&self.text()) {
+            match &display.preedit_range {
+                Some(preedit) if range.end > preedit.start => {
+                    let shift = preedit.len();
+                    if range.start < preedit.start {
+                        runs.push((range.start..preedit.start, format));
+                        runs.push((preedit.end..range.end + shift, format));
+                    } else {
+                        runs.push((range.start + shift..range.end + shift, format));
+                    }
                 }
+                _ => runs.push((range, format)),
             }
-        } else {
-            DisplayContent {
-                text: Cow::Borrowed(base_text),
-                cursor_byte_pos: base_cursor_byte,
-                preedit_range: None,
-            }
+        }
+        if let Some(preedit) = &display.preedit_range {
+            runs.push((preedit.clone(), self.typing_format()));
+            runs.sort_by_key(|(range, _)| range.start);
+        }
+
+        FormattedText {
+            text: display.text.as_ref(),
+            runs,
+            paint: self.paint(fonts),
+            fonts,
         }
     }
-}
 
-impl Drawable for Text {
-    fn draw(
-        &self,
+    /// Start of a line of the given width within the widest line.
+    fn aligned_x(&self, width: f32, line_width: f32) -> f32 {
+        let factor = match self.align {
+            TextAlign::Left => 0.0,
+            TextAlign::Center => 0.5,
+            TextAlign::Right => 1.0,
+        };
+        self.pos.x + (width - line_width) * factor
+    }
+
+    /// Calls `fill` for each run of the line with its start, text and paint.
+    fn fill_line(
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
-        _bounds: (Vec2D, Vec2D),
+        formatted: &FormattedText<'_>,
+        line: &LineLayout,
+        mut fill: impl FnMut(
+            &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+            f32,
+            &str,
+            &Paint,
+            TextFormat,
+        ) -> Result<()>,
     ) -> Result<()> {
-        let gtext = self.text_buffer.text(
-            &self.text_buffer.start_iter(),
-            &self.text_buffer.end_iter(),
-            false,
-        );
-        let base_text = gtext.as_str();
-        let display = self.display_text(base_text);
-        let text = display.text.as_ref();
+        let mut x = line.x;
+        for (range, format) in formatted.runs_in(line.range.clone()) {
+            let paint = formatted.run_paint(format);
+            let text = &formatted.text[range];
+            fill(canvas, x, text, &paint, format)?;
+            x += Self::text_width(canvas, &paint, text);
+        }
+        Ok(())
+    }
 
-        let mut base_paint: Paint = self.style.into();
-        base_paint.set_font(&[font]);
+    fn draw_run_underline(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        paint: &Paint,
+        x: f32,
+        baseline: f32,
+        width: f32,
+    ) {
+        let thickness = (paint.font_size() / 15.0).max(1.0);
+        let mut path = Path::new();
+        path.move_to(x, baseline + thickness * 2.0);
+        path.line_to(x + width, baseline + thickness * 2.0);
+        let mut paint = paint.clone();
+        paint.set_line_width(thickness);
+        paint.set_line_cap(femtovg::LineCap::Butt);
+        canvas.stroke_path(&path, &paint);
+    }
+
+    fn background_padding(&self) -> f32 {
+        self.style
+            .size
+            .to_text_size(self.style.annotation_size_factor) as f32
+            * 0.25
+    }
 
+    /// The box behind the text, a bit larger than the text itself.
+    fn background_area(&self, (pos, size): (Vec2D, Vec2D)) -> (Vec2D, Vec2D) {
+        let padding = self.background_padding();
+        (
+            pos - Vec2D::new(padding, padding),
+            size + Vec2D::new(padding * 2.0, padding * 2.0),
+        )
+    }
+
+    /// The fill color, or whichever of black and white contrasts the text.
+    fn background_color(&self) -> Color {
+        Style {
+            color: self.style.color.contrasting(),
+            ..self.style
+        }
+        .effective_fill_color()
+    }
+
+    fn layout(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        formatted: &FormattedText<'_>,
+    ) -> Result<TextLayout> {
+        let paint = &formatted.paint;
+        let text = formatted.text;
         let transform = canvas.transform();
         let canva_scale = transform.average_scale();
         let canvas_offset_x = transform[4];
         let canvas_width = canvas.width() as f32;
 
-        let width = canvas_width / canva_scale - self.pos.x - canvas_offset_x;
+        let wrap_width = self
+            .wrap_width
+            .unwrap_or(canvas_width / canva_scale - self.pos.x - canvas_offset_x);
 
-        let lines = canvas.break_text_vec(width, text, &base_paint)?;
+        let lines = Self::break_lines(canvas, formatted, wrap_width);
 
-        let font_metrics = canvas.measure_font(&base_paint)?;
-        let measured_cursor = canvas
-            .measure_text(self.pos.x, self.pos.y, "|", &base_paint)
-            .ok();
+        let font_metrics = canvas.measure_font(paint)?;
+        let measured_cursor = canvas.measure_text(self.pos.x, self.pos.y, "|", paint).ok();
 
         let mut line_height = measured_cursor
             .as_ref()
@@ -178,79 +1267,233 @@ impl Drawable for Text {
             (font_metrics.height() / canva_scale).abs()
         };
 
+        // line breaks take no space, the alignment would be off by them
+        let line_widths: Vec<f32> = lines
+            .iter()
+            .map(|range| {
+                let end = range.start + text[range.clone()].trim_end_matches('\n').len();
+                formatted.width(canvas, range.start..end)
+            })
+            .collect();
+        let width = self
+            .wrap_width
+            .unwrap_or_else(|| line_widths.iter().copied().fold(0.0, f32::max));
+
         let mut line_layouts: Vec<LineLayout> = Vec::with_capacity(lines.len());
         let mut baseline = self.pos.y;
-        for line_range in &lines {
+        for (line_range, line_width) in lines.iter().zip(line_widths) {
             line_layouts.push(LineLayout {
                 range: line_range.clone(),
                 baseline,
+                x: self.aligned_x(width, line_width),
             });
             baseline += line_height;
         }
 
-        let cursor_metrics = CursorMetrics {
-            top_offset: cursor_top_offset,
-            height: cursor_height,
-            line_height,
-        };
+        Ok(TextLayout {
+            width,
+            lines: line_layouts,
+            cursor: CursorMetrics {
+                top_offset: cursor_top_offset,
+                height: cursor_height,
+                line_height,
+            },
+        })
+    }
 
-        let layout_context = TextDrawingContext {
-            paint: &base_paint,
-            text,
-            lines: &line_layouts,
-        };
+    /// Byte ranges of the lines, they break after line breaks and before words that do not fit
+    /// within `width` anymore. Words wider than a line on their own break between characters.
+    fn break_lines(
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        formatted: &FormattedText<'_>,
+        width: f32,
+    ) -> Vec<Range<usize>> {
+        let text = formatted.text;
+        let mut lines = Vec::new();
+        let mut paragraph_start = 0;
+        for paragraph in text.split_inclusive('\n') {
+            let paragraph_end = paragraph_start + paragraph.len();
+
+            // words end where the whitespace after them ends
+            let mut word_ends = Vec::new();
+            let mut after_whitespace = false;
+            for (i, c) in paragraph.char_indices() {
+                if after_whitespace && !c.is_whitespace() {
+                    word_ends.push(paragraph_start + i);
+                }
+                after_whitespace = c.is_whitespace();
+            }
+            word_ends.push(paragraph_end);
+
+            let mut line_start = paragraph_start;
+            let mut word_start = paragraph_start;
+            for word_end in word_ends {
+                // whitespace at the end of a line takes no space
+                let visible_end = word_start + text[word_start..word_end].trim_end().len();
+                if line_start < word_start
+                    && formatted.width(canvas, line_start..visible_end) > width
+                {
+                    lines.push(line_start..word_start);
+                    line_start = word_start;
+                }
 
-        if self.editing {
-            if let (Some(preedit), Some(preedit_range)) = (&self.preedit, &display.preedit_range) {
-                self.draw_preedit_background(
-                    canvas,
-                    &layout_context,
-                    preedit,
-                    preedit_range,
-                    cursor_metrics,
-                );
+                while line_start < visible_end
+                    && formatted.width(canvas, line_start..visible_end) > width
+                {
+                    // as many characters as fit, but at least one
+                    let fitting = text[line_start..visible_end]
+                        .char_indices()
+                        .skip(1)
+                        .map(|(i, _)| line_start + i)
+                        .take_while(|end| formatted.width(canvas, line_start..*end) <= width)
+                        .last()
+                        .unwrap_or_else(|| {
+                            line_start + text[line_start..].chars().next().map_or(1, char::len_utf8)
+                        });
+                    lines.push(line_start..fitting);
+                    line_start = fitting;
+                }
+                word_start = word_end;
             }
+            lines.push(line_start..paragraph_end);
+            paragraph_start = paragraph_end;
         }
+        lines
+    }
 
-        let mut draw_baseline = self.pos.y;
-        for line_range in &lines {
-            canvas.fill_text(
-                self.pos.x,
-                draw_baseline,
-                &text[line_range.clone()],
-                &base_paint,
-            )?;
-            draw_baseline += line_height;
-        }
+    /// Frame of the wrap box with the handles to resize it, only shown while editing.
+    fn draw_wrap_box(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        width: f32,
+        cursor: CursorMetrics,
+        line_count: usize,
+    ) {
+        let scale = canvas.transform().average_scale();
+        let top = self.pos.y + cursor.top_offset;
+        let height = cursor.line_height * line_count.max(1) as f32;
+
+        let mut frame = Path::new();
+        frame.rect(self.pos.x, top, width, height);
+        canvas.stroke_path(
+            &frame,
+            &Paint::color(femtovg::Color::rgbaf(0.5, 0.5, 0.5, 0.8)).with_line_width(1.0 / scale),
+        );
 
-        if self.editing {
-            if let (Some(preedit), Some(preedit_range)) = (&self.preedit, &display.preedit_range) {
-                self.draw_preedit_overlays(
-                    canvas,
-                    font,
-                    &layout_context,
-                    preedit,
-                    preedit_range,
-                    cursor_metrics,
-                )?;
+        // the handles need the bounds, an empty text has none
+        if line_count > 0 {
+            for x in [self.pos.x, self.pos.x + width] {
+                Crop::draw_single_handle(canvas, Vec2D::new(x, top + height / 2.0), scale);
             }
         }
+    }
 
-        if self.editing {
-            self.draw_cursor_and_update_ime(
-                canvas,
-                font,
-                &layout_context,
-                cursor_metrics,
-                display.cursor_byte_pos,
-            );
-        }
+    /// Places the cursor at the character boundary closest to `pos`, on the line below it or
+    /// the last one.
+    fn place_cursor(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        fonts: Fonts,
+        pos: Vec2D,
+        extend: bool,
+    ) -> Result<()> {
+        let gtext = self.text();
+        let text = gtext.as_str();
+        let display = DisplayContent {
+            text: Cow::Borrowed(text),
+            cursor_byte_pos: 0,
+            preedit_range: None,
+        };
+        let formatted = self.formatted(&display, fonts);
+        let layout = self.layout(canvas, &formatted)?;
+        let Some(line) = layout
+            .lines
+            .iter()
+            .find(|line| pos.y < line.baseline)
+            .or(layout.lines.last())
+        else {
+            return Ok(());
+        };
 
+        let start = line.range.start;
+        let end = start + text[line.range.clone()].trim_end_matches('\n').len();
+        let byte = text[start..end]
+            .char_indices()
+            .map(|(i, _)| start + i)
+            .chain([end])
+            .map(|byte| {
+                let x = line.x + formatted.width(canvas, start..byte);
+                (byte, (x - pos.x).abs())
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(end, |(byte, _)| byte);
+
+        let iter = self
+            .text_buffer()
+            .iter_at_offset(text[..byte].chars().count() as i32);
+        if extend {
+            self.text_buffer()
+                .move_mark(&self.text_buffer().get_insert(), &iter);
+        } else {
+            self.text_buffer().place_cursor(&iter);
+        }
         Ok(())
     }
-}
 
-impl Text {
+    /// Highlights the selected characters in the color of the text.
+    fn draw_selection(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        context: &TextDrawingContext<'_>,
+        text: &str,
+        cursor: CursorMetrics,
+    ) {
+        let Some((start, end)) = self.text_buffer().selection_bounds() else {
+            return;
+        };
+        let selection = Self::byte_index_from_char_index(text, start.offset() as usize)
+            ..Self::byte_index_from_char_index(text, end.offset() as usize);
+
+        let mut color: femtovg::Color = self.style.color.into();
+        color.set_alphaf(0.3);
+        let paint = Paint::color(color);
+        for line in context.lines {
+            for (start_x, end_x) in
+                self.segments_for_line_span(canvas, context, line, selection.clone())
+            {
+                let mut path = Path::new();
+                path.rect(
+                    start_x,
+                    line.baseline + cursor.top_offset,
+                    end_x - start_x,
+                    cursor.height,
+                );
+                canvas.fill_path(&path, &paint);
+            }
+        }
+    }
+
+    /// Area taken by the text including the preedit, unlike the bounding box an empty text
+    /// still takes up one line.
+    pub(super) fn measure(
+        &self,
+        canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
+        fonts: Fonts,
+    ) -> Result<(Vec2D, Vec2D)> {
+        let gtext = self.text();
+        let display = self.display_text(gtext.as_str());
+        let formatted = self.formatted(&display, fonts);
+        let layout = self.layout(canvas, &formatted)?;
+
+        // after a trailing line break the cursor is on a line of its own
+        let line_count =
+            layout.lines.len().max(1) + usize::from(self.editing && formatted.text.ends_with('\n'));
+        Ok((
+            Vec2D::new(self.pos.x, self.pos.y + layout.cursor.top_offset),
+            Vec2D::new(layout.width, layout.cursor.line_height * line_count as f32),
+        ))
+    }
+
     fn draw_preedit_background(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
@@ -293,7 +1536,6 @@ impl Text {
     fn draw_preedit_overlays(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
         context: &TextDrawingContext<'_>,
         preedit: &Preedit,
         preedit_range: &Range<usize>,
@@ -316,9 +1558,6 @@ impl Text {
                 }
 
                 if let Some(color) = span.foreground {
-                    let mut overlay_paint: Paint = self.style.into();
-                    overlay_paint.set_font(&[font]);
-                    overlay_paint.set_color(color.into());
                     for (start_x, end_x) in &segments {
                         let width = (*end_x - *start_x).max(0.0);
                         if width <= f32::EPSILON {
@@ -331,11 +1570,16 @@ impl Text {
                             (width + 2.0).ceil(),
                             (cursor.height + 2.0).ceil(),
                         );
-                        canvas.fill_text(
-                            self.pos.x,
-                            line.baseline,
-                            &context.text[line.range.clone()],
-                            &overlay_paint,
+                        Self::fill_line(
+                            canvas,
+                            context.formatted,
+                            line,
+                            |canvas, x, text, paint, _| {
+                                let mut paint = paint.clone();
+                                paint.set_color(color.into());
+                                canvas.fill_text(x, line.baseline, text, &paint)?;
+                                Ok(())
+                            },
                         )?;
                         canvas.restore();
                     }
@@ -426,15 +1670,8 @@ impl Text {
             return Vec::new();
         }
 
-        let line_text = &context.text[line.range.clone()];
-        let start_offset = overlap_start.saturating_sub(line_start);
-        let end_offset = overlap_end.saturating_sub(line_start);
-
-        let prefix = &line_text[..start_offset];
-        let selected = &line_text[start_offset..end_offset];
-
-        let start_x = self.pos.x + Self::text_width(canvas, context.paint, prefix);
-        let width = Self::text_width(canvas, context.paint, selected);
+        let start_x = line.x + context.formatted.width(canvas, line_start..overlap_start);
+        let width = context.formatted.width(canvas, overlap_start..overlap_end);
 
         vec![(start_x, start_x + width.max(0.0))]
     }
@@ -453,15 +1690,14 @@ impl Text {
         let mut newline_pending_baseline: Option<f32> = None;
 
         for line in context.lines {
-            let line_text = &context.text[line.range.clone()];
+            let line_text = &context.formatted.text[line.range.clone()];
 
             if cursor_byte_pos < line.range.end {
-                let prefix_len = cursor_byte_pos
-                    .saturating_sub(line.range.start)
-                    .min(line_text.len());
-                let prefix = &line_text[..prefix_len];
-                let offset = Self::text_width(canvas, context.paint, prefix);
-                return (self.pos.x + offset, line.baseline + cursor.top_offset);
+                let prefix_end = cursor_byte_pos.max(line.range.start);
+                let offset = context
+                    .formatted
+                    .width(canvas, line.range.start..prefix_end);
+                return (line.x + offset, line.baseline + cursor.top_offset);
             }
 
             if cursor_byte_pos == line.range.end {
@@ -472,20 +1708,19 @@ impl Text {
                         Some(line.baseline + cursor.top_offset + cursor.line_height);
                     continue;
                 }
-                let offset = Self::text_width(canvas, context.paint, line_text);
-                return (self.pos.x + offset, line.baseline + cursor.top_offset);
+                let offset = context.formatted.width(canvas, line.range.clone());
+                return (line.x + offset, line.baseline + cursor.top_offset);
             }
         }
 
         if let Some(baseline) = newline_pending_baseline {
-            return (self.pos.x, baseline);
+            return (self.aligned_x(context.width, 0.0), baseline);
         }
 
         if let Some(last_line) = context.lines.last() {
-            let line_text = &context.text[last_line.range.clone()];
-            let offset = Self::text_width(canvas, context.paint, line_text);
+            let offset = context.formatted.width(canvas, last_line.range.clone());
             (
-                self.pos.x + offset,
+                last_line.x + offset,
                 last_line.baseline + cursor.top_offset + cursor.line_height,
             )
         } else {
@@ -496,7 +1731,6 @@ impl Text {
     fn draw_cursor_and_update_ime(
         &self,
         canvas: &mut femtovg::Canvas<femtovg::renderer::OpenGl>,
-        font: FontId,
         context: &TextDrawingContext<'_>,
         cursor: CursorMetrics,
         cursor_byte_pos: usize,
@@ -504,8 +1738,7 @@ impl Text {
         let (cursor_x, cursor_top) = self.caret_top_left(canvas, context, cursor_byte_pos, cursor);
         let caret_height = cursor.height;
 
-        let mut caret_paint: Paint = self.style.into();
-        caret_paint.set_font(&[font]);
+        let caret_paint: Paint = self.style.into();
         let extra_height = caret_height * 0.05;
         let mut path = Path::new();
         path.move_to(cursor_x, cursor_top - extra_height);
@@ -544,12 +1777,48 @@ impl Text {
     }
 }
 
-#[derive(Default)]
+/// Attributes of a formatted svg `tspan`, unformatted text inherits everything.
+fn svg_format(format: TextFormat) -> String {
+    let mut attributes = String::new();
+    if format.bold {
+        attributes.push_str(r#" font-weight="bold""#);
+    }
+    if format.italic {
+        attributes.push_str(r#" font-style="italic""#);
+    }
+    if format.underline {
+        attributes.push_str(r#" text-decoration="underline""#);
+    }
+    if let Some(color) = format.color {
+        attributes.push_str(&format!(
+            r#" fill="{}" fill-opacity="{}""#,
+            export::svg_color(color),
+            color.a as f32 / 255.0
+        ));
+    }
+    attributes
+}
+
 pub struct TextTool {
     text: Option<Text>,
+    // index and original of a committed text that is edited again, it is hidden meanwhile
+    reopened: Option<(usize, TextData)>,
+    drawables: DrawableStore,
     style: Style,
     input_enabled: bool,
     im_context: Option<InputContext>,
+    drag: Option<TextDrag>,
+}
+
+/// What dragging with the text tool does.
+#[derive(Clone, Copy)]
+enum TextDrag {
+    // selects the text from where the drag started
+    Select(Vec2D),
+    // the box of a new text from where the drag started, its width is the wrap width
+    Box(Vec2D),
+    // moves one edge of the wrap box, the other one stays where it is
+    Resize { right: bool, left: f32, width: f32 },
 }
 
 impl Tool for TextTool {
@@ -568,7 +1837,7 @@ impl Tool for TextTool {
     fn set_im_context(&mut self, context: Option<InputContext>) {
         self.im_context = context.clone();
         if let Some(text) = &mut self.text {
-            text.im_context = context;
+            text.set_im_context(context);
         }
     }
 
@@ -579,160 +1848,46 @@ impl Tool for TextTool {
         }
     }
 
+    fn get_hidden_drawable(&self) -> Option<usize> {
+        self.reopened.as_ref().map(|(index, _)| *index)
+    }
+
     fn handle_style_event(&mut self, style: Style) -> ToolUpdateResult {
-        self.style = style;
+        let previous = std::mem::replace(&mut self.style, style);
         if let Some(t) = &mut self.text {
-            t.style = style;
+            // once there is text a new color only applies to the selection or what is typed next
+            if style.color != previous.color && !t.text().is_empty() {
+                t.set_run_color(style.color);
+                t.style = Style {
+                    color: t.style.color,
+                    ..style
+                };
+            } else {
+                t.style = style;
+            }
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
         }
     }
 
-    fn handle_text_event(&mut self, event: crate::sketch_board::TextEventMsg) -> ToolUpdateResult {
-        if let Some(t) = &mut self.text {
-            match event {
-                TextEventMsg::Commit(text) => {
-                    t.preedit = None;
-                    t.text_buffer.insert_at_cursor(&text);
-                    ToolUpdateResult::Redraw
-                }
-                TextEventMsg::Preedit {
-                    text,
-                    cursor_chars,
-                    spans,
-                } => {
-                    if text.is_empty() {
-                        if t.preedit.take().is_some() {
-                            ToolUpdateResult::Redraw
-                        } else {
-                            ToolUpdateResult::Unmodified
-                        }
-                    } else {
-                        t.preedit = Some(Preedit {
-                            text,
-                            cursor_chars,
-                            spans,
-                        });
-                        ToolUpdateResult::Redraw
-                    }
-                }
-                TextEventMsg::PreeditEnd => {
-                    if t.preedit.take().is_some() {
-                        ToolUpdateResult::Redraw
-                    } else {
-                        ToolUpdateResult::Unmodified
-                    }
-                }
-            }
-        } else {
-            ToolUpdateResult::Unmodified
+    fn handle_text_event(&mut self, event: TextEventMsg) -> ToolUpdateResult {
+        match &mut self.text {
+            Some(t) => t.handle_text_event(event),
+            None => ToolUpdateResult::Unmodified,
         }
     }
 
     fn handle_key_event(&mut self, event: KeyEventMsg) -> ToolUpdateResult {
         if let Some(t) = &mut self.text {
-            if event.key == Key::Return {
-                if event.modifier == ModifierType::SHIFT_MASK {
-                    t.text_buffer.insert_at_cursor("\n");
-                    return ToolUpdateResult::Redraw;
-                } else {
-                    t.preedit = None;
-                    t.editing = false;
-                    t.im_context = None;
-                    let result = t.clone_box();
-                    self.text = None;
-                    self.input_enabled = false;
-                    return ToolUpdateResult::Commit(result);
-                }
+            if event.key == Key::Return && event.modifier != ModifierType::SHIFT_MASK {
+                self.input_enabled = false;
+                return self.finish();
             } else if event.key == Key::Escape {
                 return self.handle_deactivated();
-            } else if event.key == Key::BackSpace {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::Delete,
-                        ActionScope::BackwardWord,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::Delete,
-                        ActionScope::BackwardChar,
-                    );
-                }
-            } else if event.key == Key::Delete {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::Delete,
-                        ActionScope::ForwardWord,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::Delete,
-                        ActionScope::ForwardChar,
-                    );
-                }
-            } else if event.key == Key::Left {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::BackwardWord,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::BackwardChar,
-                    );
-                }
-            } else if event.key == Key::Right {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::ForwardWord,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::ForwardChar,
-                    );
-                }
-            } else if event.key == Key::Home {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::BufferStart,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::BackwardLine,
-                    );
-                }
-            } else if event.key == Key::End {
-                if event.modifier == ModifierType::CONTROL_MASK {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::BufferEnd,
-                    );
-                } else {
-                    return Self::handle_text_buffer_action(
-                        &mut t.text_buffer,
-                        Action::MoveCursor,
-                        ActionScope::ForwardLine,
-                    );
-                }
             }
-        };
+            return t.handle_edit_key(event);
+        }
         ToolUpdateResult::Unmodified
     }
 
@@ -740,19 +1895,55 @@ impl Tool for TextTool {
         match event.type_ {
             MouseEventType::Click => {
                 if event.button == MouseButton::Primary {
+                    self.drag = self.text.as_ref().and_then(|t| {
+                        let right = t.wrap_handle_at(event.pos, Self::HANDLE_MARGIN)?;
+                        Some(TextDrag::Resize {
+                            right,
+                            left: t.pos().x,
+                            width: t.wrap_width()?,
+                        })
+                    });
+                    if self.drag.is_some() {
+                        return ToolUpdateResult::Unmodified;
+                    }
+
+                    // clicks into the edited text only move the cursor, shift selects up to it
+                    if let Some(t) = self.text.as_ref().filter(|t| t.hit_test(event.pos, 0.0)) {
+                        t.move_cursor_to(
+                            event.pos,
+                            event.modifier.contains(ModifierType::SHIFT_MASK),
+                        );
+                        return ToolUpdateResult::Redraw;
+                    }
+
+                    let clicked = self.find_text_at(event.pos);
+
                     // create commit message if necessary
-                    let return_value = match &mut self.text {
-                        Some(l) => {
-                            l.preedit = None;
-                            l.editing = false;
-                            l.im_context = None;
-                            ToolUpdateResult::Commit(l.clone_box())
-                        }
-                        None => ToolUpdateResult::Redraw,
+                    let return_value = match self.finish() {
+                        ToolUpdateResult::Unmodified => ToolUpdateResult::Redraw,
+                        result => result,
                     };
 
-                    // create a new Text
-                    self.text = Some(Text::new(event.pos, self.style, self.im_context.clone()));
+                    match clicked {
+                        Some((mut index, mut text)) => {
+                            // the committed text has to be edited where it is in the stack
+                            if let ToolUpdateResult::Edit(EditCommand::Remove { index: removed }) =
+                                &return_value
+                            {
+                                if *removed < index {
+                                    index -= 1;
+                                }
+                            }
+                            text.reopen(self.im_context.clone(), event.pos);
+                            self.reopened = Some((index, TextData::from(text.clone())));
+                            self.text = Some(text);
+                        }
+                        // create a new Text
+                        None => {
+                            self.text =
+                                Some(Text::new(event.pos, self.style, self.im_context.clone()))
+                        }
+                    }
 
                     self.set_input_enabled(true);
 
@@ -762,22 +1953,56 @@ impl Tool for TextTool {
                     ToolUpdateResult::Unmodified
                 }
             }
+            // the click before grabbed a handle, placed the cursor or started a new text,
+            // dragging from there selects, or draws the wrap box of the still empty text
+            MouseEventType::BeginDrag if event.button == MouseButton::Primary => {
+                if self.drag.is_none() {
+                    self.drag = self.text.as_ref().map(|t| {
+                        if t.text().is_empty() {
+                            TextDrag::Box(event.pos)
+                        } else {
+                            TextDrag::Select(event.pos)
+                        }
+                    });
+                }
+                ToolUpdateResult::Unmodified
+            }
+            MouseEventType::UpdateDrag | MouseEventType::EndDrag => {
+                let (Some(t), Some(drag)) = (&mut self.text, self.drag) else {
+                    return ToolUpdateResult::Unmodified;
+                };
+                match drag {
+                    TextDrag::Select(start) => t.move_cursor_to(start + event.pos, true),
+                    TextDrag::Box(start) => {
+                        let width = event.pos.x.abs();
+                        if width < Self::MIN_WRAP_WIDTH {
+                            t.set_wrap_box(start.x, None);
+                        } else {
+                            t.set_wrap_box(start.x.min(start.x + event.pos.x), Some(width));
+                        }
+                    }
+                    TextDrag::Resize { right, left, width } => {
+                        if right {
+                            let width = (width + event.pos.x).max(Self::MIN_WRAP_WIDTH);
+                            t.set_wrap_box(left, Some(width));
+                        } else {
+                            let resized = (width - event.pos.x).max(Self::MIN_WRAP_WIDTH);
+                            t.set_wrap_box(left + width - resized, Some(resized));
+                        }
+                    }
+                }
+                if event.type_ == MouseEventType::EndDrag {
+                    self.drag = None;
+                }
+                ToolUpdateResult::Redraw
+            }
             _ => ToolUpdateResult::Unmodified,
         }
     }
 
     fn handle_deactivated(&mut self) -> ToolUpdateResult {
         self.input_enabled = false;
-        if let Some(t) = &mut self.text {
-            t.preedit = None;
-            t.editing = false;
-            t.im_context = None;
-            let result = t.clone_box();
-            self.text = None;
-            ToolUpdateResult::Commit(result)
-        } else {
-            ToolUpdateResult::Unmodified
-        }
+        self.finish()
     }
 
     fn active(&self) -> bool {
@@ -786,7 +2011,7 @@ impl Tool for TextTool {
 
     fn handle_undo(&mut self) -> ToolUpdateResult {
         if let Some(t) = &self.text {
-            t.text_buffer.undo();
+            t.undo();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -795,7 +2020,7 @@ impl Tool for TextTool {
 
     fn handle_redo(&mut self) -> ToolUpdateResult {
         if let Some(t) = &self.text {
-            t.text_buffer.redo();
+            t.redo();
             ToolUpdateResult::Redraw
         } else {
             ToolUpdateResult::Unmodified
@@ -811,22 +2036,83 @@ enum ActionScope {
     BackwardWord,
     BufferStart,
     BufferEnd,
+    // a character offset found by the caller
+    Offset(i32),
 }
 
+#[derive(Clone, Copy)]
 enum Action {
     Delete,
     MoveCursor,
+    Select,
 }
 
 impl TextTool {
+    const HANDLE_MARGIN: f32 = 15.0;
+    const MIN_WRAP_WIDTH: f32 = 20.0;
+
+    pub fn new(drawables: DrawableStore) -> Self {
+        Self {
+            text: None,
+            reopened: None,
+            drawables,
+            style: Style::default(),
+            input_enabled: false,
+            im_context: None,
+            drag: None,
+        }
+    }
+
+    /// The topmost committed text at `pos`, as a copy with a buffer of its own.
+    fn find_text_at(&self, pos: Vec2D) -> Option<(usize, Text)> {
+        let skipped = self.get_hidden_drawable();
+        self.drawables
+            .borrow()
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(index, d)| Some(*index) != skipped && d.hit_test(pos, 0.0))
+            .find_map(|(index, d)| match d.to_data() {
+                Some(DrawableData::Text(text)) => Some((index, Text::from(TextData::from(text)))),
+                _ => None,
+            })
+    }
+
+    /// Ends editing the current text. New texts are committed, reopened ones replace their
+    /// committed version unless nothing changed, or are removed when they were emptied.
+    fn finish(&mut self) -> ToolUpdateResult {
+        let Some(mut text) = self.text.take() else {
+            return ToolUpdateResult::Unmodified;
+        };
+        text.finish_editing();
+
+        match self.reopened.take() {
+            None => ToolUpdateResult::Commit(Box::new(text)),
+            Some((index, _)) if text.text().is_empty() => {
+                ToolUpdateResult::Edit(EditCommand::Remove { index })
+            }
+            Some((_, original)) if TextData::from(text.clone()) == original => {
+                ToolUpdateResult::Redraw
+            }
+            Some((index, _)) => ToolUpdateResult::Edit(EditCommand::Modify {
+                index,
+                drawable: Box::new(text),
+            }),
+        }
+    }
+
     fn handle_text_buffer_action(
-        text_buffer: &mut TextBuffer,
+        text_buffer: &TextBuffer,
         action: Action,
         action_scope: ActionScope,
     ) -> ToolUpdateResult {
         let mut start_cursor_itr = text_buffer.iter_at_mark(&text_buffer.get_insert());
 
         match action {
+            Action::Delete if text_buffer.has_selection() => {
+                text_buffer.delete_selection(true, true);
+                ToolUpdateResult::Redraw
+            }
             Action::Delete => {
                 let mut end_cursor_itr = start_cursor_itr;
 
@@ -845,7 +2131,7 @@ impl TextTool {
                     ToolUpdateResult::Unmodified
                 }
             }
-            Action::MoveCursor => {
+            Action::MoveCursor | Action::Select => {
                 let mut cursor_itr = start_cursor_itr;
                 match action_scope {
                     ActionScope::ForwardChar => cursor_itr.forward_char(),
@@ -873,12 +2159,21 @@ impl TextTool {
                         }
                         false
                     }
+                    ActionScope::Offset(offset) => {
+                        cursor_itr.set_offset(offset);
+                        false
+                    }
                 };
 
-                text_buffer.place_cursor(&cursor_itr);
+                let had_selection = text_buffer.has_selection();
+                if let Action::Select = action {
+                    text_buffer.move_mark(&text_buffer.get_insert(), &cursor_itr);
+                } else {
+                    text_buffer.place_cursor(&cursor_itr);
+                }
                 let new_cursor_itr = text_buffer.iter_at_mark(&text_buffer.get_insert());
 
-                if new_cursor_itr != start_cursor_itr {
+                if new_cursor_itr != start_cursor_itr || had_selection {
                     ToolUpdateResult::Redraw
                 } else {
                     ToolUpdateResult::Unmodified
@@ -887,3 +2182,59 @@ impl TextTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use gdk_pixbuf::{Colorspace, Pixbuf};
+
+    use crate::project::Project;
+
+    use super::*;
+
+    #[test]
+    fn saved_project_reopens_text_without_gtk() {
+        let mut text = Text::new(Vec2D::new(10.0, 20.0), Style::default(), None);
+        text.stored_text = "bold\nplain".to_string();
+        text.stored_runs = vec![TextRun {
+            start: 0,
+            end: 4,
+            format: TextFormat {
+                bold: true,
+                ..TextFormat::default()
+            },
+        }];
+        text.finish_editing();
+
+        let background = Pixbuf::new(Colorspace::Rgb, true, 8, 4, 4).unwrap();
+        let path = std::env::temp_dir().join(format!("satty-text-{}.satty", std::process::id()));
+        let filename = path.to_str().unwrap();
+        Project::save(filename, &background, None, &[Box::new(text)]).unwrap();
+        let project = Project::load(filename);
+        std::fs::remove_file(&path).unwrap();
+
+        let drawables = project.unwrap().drawables;
+        let [DrawableData::Text(text)] = drawables.as_slice() else {
+            panic!("expected a single text, got {drawables:?}");
+        };
+        assert_eq!(text.text(), "bold\nplain");
+        assert_eq!(text.pos(), Vec2D::new(10.0, 20.0));
+        assert_eq!(
+            text.runs(),
+            vec![
+                TextRun {
+                    start: 0,
+                    end: 4,
+                    format: TextFormat {
+                        bold: true,
+                        ..TextFormat::default()
+                    },
+                },
+                TextRun {
+                    start: 4,
+                    end: 10,
+                    format: TextFormat::default(),
+                },
+            ]
+        );
+    }
+}
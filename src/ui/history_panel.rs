@@ -0,0 +1,166 @@
+use relm4::{
+    gtk::{prelude::*, Align},
+    prelude::*,
+};
+
+use crate::{
+    configuration::APP_CONFIG,
+    style::Color,
+    tools::History,
+    ui::toolbars::{create_icon, ToolbarEvent},
+};
+
+pub struct HistoryPanel {
+    visible: bool,
+    expanded: bool,
+    list: gtk::ListBox,
+}
+
+#[derive(Debug, Clone)]
+pub enum HistoryPanelInput {
+    SetVisibility(bool),
+    ToggleVisibility,
+    ToggleExpanded,
+    Update(History),
+}
+
+impl HistoryPanel {
+    fn create_row(label: &str, color: Option<Color>, applied: bool) -> gtk::ListBoxRow {
+        let content = gtk::Box::new(gtk::Orientation::Horizontal, 6);
+        content.set_margin_all(4);
+
+        let icon = match color {
+            Some(color) => create_icon(color),
+            None => gtk::Image::new(),
+        };
+        icon.set_size_request(16, 16);
+        content.append(&icon);
+
+        let label = gtk::Label::builder()
+            .label(label)
+            .xalign(0.0)
+            .hexpand(true)
+            .ellipsize(gtk::pango::EllipsizeMode::End)
+            .build();
+        content.append(&label);
+
+        let row = gtk::ListBoxRow::builder()
+            .focusable(false)
+            .child(&content)
+            .build();
+
+        // undone steps stay in the list until something new is drawn
+        if !applied {
+            row.add_css_class("dim-label");
+        }
+        row
+    }
+
+    fn show_history(&self, history: History) {
+        while let Some(row) = self.list.row_at_index(0) {
+            self.list.remove(&row);
+        }
+
+        // the first row is the image without any annotation, row n has the first n steps applied
+        self.list
+            .append(&Self::create_row("Original image", None, true));
+        for (index, entry) in history.entries.iter().enumerate() {
+            self.list.append(&Self::create_row(
+                &entry.to_string(),
+                entry.drawable.as_ref().and_then(|d| d.color),
+                index < history.position,
+            ));
+        }
+
+        self.list
+            .select_row(self.list.row_at_index(history.position as i32).as_ref());
+    }
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for HistoryPanel {
+    type Init = ();
+    type Input = HistoryPanelInput;
+    type Output = ToolbarEvent;
+
+    view! {
+        root = gtk::Box {
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 2,
+            set_valign: Align::Center,
+            set_halign: Align::End,
+            add_css_class: "toolbar",
+            add_css_class: "toolbar-right",
+
+            #[watch]
+            set_visible: model.visible,
+
+            gtk::Button {
+                set_focusable: false,
+                set_hexpand: false,
+                set_halign: Align::End,
+
+                set_icon_name: "history-regular",
+                #[watch]
+                set_tooltip: if model.expanded { "Hide history" } else { "Show history" },
+                connect_clicked => HistoryPanelInput::ToggleExpanded,
+            },
+            gtk::Revealer {
+                set_transition_type: gtk::RevealerTransitionType::SlideDown,
+                #[watch]
+                set_reveal_child: model.expanded,
+
+                gtk::ScrolledWindow {
+                    set_hscrollbar_policy: gtk::PolicyType::Never,
+                    set_propagate_natural_height: true,
+                    set_max_content_height: 400,
+                    set_min_content_width: 220,
+
+                    #[local_ref]
+                    list -> gtk::ListBox {
+                        set_focusable: false,
+                        set_selection_mode: gtk::SelectionMode::Single,
+                        set_activate_on_single_click: true,
+
+                        connect_row_activated[sender] => move |_, row| {
+                            sender
+                                .output_sender()
+                                .emit(ToolbarEvent::JumpToHistory(row.index() as usize));
+                        },
+                    },
+                },
+            },
+        },
+    }
+
+    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+        match message {
+            HistoryPanelInput::SetVisibility(visible) => self.visible = visible,
+            HistoryPanelInput::ToggleVisibility => {
+                self.visible = !self.visible;
+            }
+            HistoryPanelInput::ToggleExpanded => {
+                self.expanded = !self.expanded;
+            }
+            HistoryPanelInput::Update(history) => self.show_history(history),
+        }
+    }
+
+    fn init(
+        _: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let list = gtk::ListBox::new();
+        let model = HistoryPanel {
+            visible: !APP_CONFIG.read().default_hide_toolbars(),
+            expanded: false,
+            list: list.clone(),
+        };
+        model.show_history(History::default());
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+}
@@ -1 +1,2 @@
+pub mod history_panel;
 pub mod toolbars;
@@ -1,8 +1,8 @@
 use std::{borrow::Cow, collections::HashMap};
 
 use crate::{
-    configuration::APP_CONFIG,
-    style::{Color, Size},
+    configuration::{KeyAction, APP_CONFIG},
+    style::{Color, LineDash, Size, SizePreset},
     tools::Tools,
 };
 
@@ -29,6 +29,13 @@ pub struct StyleToolbar {
     custom_color: Color,
     custom_color_pixbuf: Pixbuf,
     color_action: SimpleAction,
+    size: Size,
+    fill: bool,
+    fill_color: Option<Color>,
+    fill_color_pixbuf: Pixbuf,
+    fill_opacity: f32,
+    shadow: bool,
+    outline: bool,
     visible: bool,
     annotation_size: f32,
     annotation_size_formatted: String,
@@ -44,14 +51,21 @@ pub enum ToolbarEvent {
     ToolSelected(Tools),
     ColorSelected(Color),
     SizeSelected(Size),
+    LineDashSelected(LineDash),
+    FillColorSelected(Option<Color>),
+    FillOpacityChanged(f32),
     Redo,
     Undo,
     SaveFile,
     CopyClipboard,
     ToggleFill,
+    ToggleShadow,
+    ToggleOutline,
     AnnotationSizeChanged(f32),
     Reset,
     SaveFileAs,
+    // undo or redo until this many steps of the history are applied
+    JumpToHistory(usize),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -70,6 +84,15 @@ pub enum StyleToolbarInput {
     ToggleVisibility,
     ShowAnnotationDialog,
     AnnotationDialogFinished(Option<f32>),
+    SwitchColor(ColorButtons),
+    SwitchSize(Size),
+    ToggleFill,
+    ToggleShadow,
+    ToggleOutline,
+    // `None` fills with the stroke color
+    FillColorSelected(Option<Color>),
+    ShowFillColorDialog,
+    FillOpacityChanged(f32),
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -91,7 +114,25 @@ fn create_icon_pixbuf(color: Color) -> Pixbuf {
     pixbuf.fill(color.to_rgba_u32());
     pixbuf
 }
-fn create_icon(color: Color) -> gtk::Image {
+// the slider covers the size steps and the presets
+fn size_adjustment() -> gtk::Adjustment {
+    let config = APP_CONFIG.read();
+    let steps = config.size_steps();
+    let small = Size::from(SizePreset::Small).width();
+    let large = Size::from(SizePreset::Large).width();
+    let min = steps.first().map_or(small, |w| w.min(small));
+    let max = steps.last().map_or(large, |w| w.max(large));
+    gtk::Adjustment::new(
+        Size::default().width().into(),
+        min.into(),
+        max.into(),
+        0.5,
+        1.0,
+        0.0,
+    )
+}
+
+pub fn create_icon(color: Color) -> gtk::Image {
     gtk::Image::from_pixbuf(Some(&create_icon_pixbuf(color)))
 }
 
@@ -114,29 +155,32 @@ impl SimpleComponent for ToolsToolbar {
             set_visible: model.visible,
 
 
+            #[name(reset_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "recycling-bin",
-                set_tooltip: "Reset",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::Reset);},
             },
             gtk::Separator {},
+            #[name(undo_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "arrow-undo-filled",
-                set_tooltip: "Undo (Ctrl-Z)",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::Undo);},
             },
+            #[name(redo_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "arrow-redo-filled",
-                set_tooltip: "Redo (Ctrl-Y)",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::Redo);},
             },
             gtk::Separator {},
@@ -203,6 +247,15 @@ impl SimpleComponent for ToolsToolbar {
                 // tooltip set programatically
                 ActionablePlus::set_action::<ToolsAction>: Tools::Ellipse,
             },
+            #[name(polygon_button)]
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "hexagon-regular",
+                // tooltip set programatically
+                ActionablePlus::set_action::<ToolsAction>: Tools::Polygon,
+            },
             #[name(text_button)]
             gtk::ToggleButton {
                 set_focusable: false,
@@ -212,6 +265,15 @@ impl SimpleComponent for ToolsToolbar {
                 // tooltip set programatically
                 ActionablePlus::set_action::<ToolsAction>: Tools::Text,
             },
+            #[name(callout_button)]
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "comment-regular",
+                // tooltip set programatically
+                ActionablePlus::set_action::<ToolsAction>: Tools::Callout,
+            },
             #[name(marker_button)]
             gtk::ToggleButton {
                 set_focusable: false,
@@ -239,31 +301,52 @@ impl SimpleComponent for ToolsToolbar {
                 // tooltip set programatically
                 ActionablePlus::set_action::<ToolsAction>: Tools::Highlight,
             },
+            #[name(spotlight_button)]
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "flashlight-regular",
+                // tooltip set programatically
+                ActionablePlus::set_action::<ToolsAction>: Tools::Spotlight,
+            },
+            #[name(magnifier_button)]
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "zoom-in-regular",
+                // tooltip set programatically
+                ActionablePlus::set_action::<ToolsAction>: Tools::Magnifier,
+            },
             gtk::Separator {},
+            #[name(copy_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "copy-regular",
-                set_tooltip: "Copy to clipboard (Ctrl+C)",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::CopyClipboard);},
             },
+            #[name(save_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "save-regular",
-                set_tooltip: "Save (Ctrl+S)",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::SaveFile);},
 
                 set_visible: APP_CONFIG.read().output_filename().is_some()
             },
+            #[name(save_as_button)]
             gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_icon_name: "save-multiple-regular",
-                set_tooltip: "Save as (Ctrl+Shift+S)",
+                // tooltip set programatically
                 connect_clicked[sender] => move |_| {sender.output_sender().emit(ToolbarEvent::SaveFileAs);},
             },
         },
@@ -319,10 +402,14 @@ impl SimpleComponent for ToolsToolbar {
             (Tools::Arrow, widgets.arrow_button.clone()),
             (Tools::Rectangle, widgets.rectangle_button.clone()),
             (Tools::Ellipse, widgets.ellipse_button.clone()),
+            (Tools::Polygon, widgets.polygon_button.clone()),
             (Tools::Text, widgets.text_button.clone()),
+            (Tools::Callout, widgets.callout_button.clone()),
             (Tools::Marker, widgets.marker_button.clone()),
             (Tools::Blur, widgets.blur_button.clone()),
             (Tools::Highlight, widgets.highlight_button.clone()),
+            (Tools::Spotlight, widgets.spotlight_button.clone()),
+            (Tools::Magnifier, widgets.magnifier_button.clone()),
         ]);
 
         // reverse shortcuts mapping
@@ -346,6 +433,21 @@ impl SimpleComponent for ToolsToolbar {
             button.set_tooltip_text(Some(tooltip));
         }
 
+        for (action, name, button) in [
+            (KeyAction::Reset, "Reset", &widgets.reset_button),
+            (KeyAction::Undo, "Undo", &widgets.undo_button),
+            (KeyAction::Redo, "Redo", &widgets.redo_button),
+            (KeyAction::Copy, "Copy to clipboard", &widgets.copy_button),
+            (KeyAction::Save, "Save", &widgets.save_button),
+            (KeyAction::SaveAs, "Save as", &widgets.save_as_button),
+        ] {
+            let tooltip = match config.keybinds().accelerator(action) {
+                Some(accelerator) => format!("{name} ({accelerator})"),
+                None => name.to_string(),
+            };
+            button.set_tooltip_text(Some(&tooltip));
+        }
+
         // Set initial active button correctly
         let initial_tool = APP_CONFIG.read().initial_tool();
         if let Some(button) = model.tool_buttons.get(&initial_tool) {
@@ -367,8 +469,14 @@ pub enum ColorButtons {
 }
 
 impl StyleToolbar {
-    fn show_color_dialog(&self, sender: ComponentSender<StyleToolbar>, root: Option<Window>) {
-        let current_color: RGBA = self.custom_color.into();
+    fn show_color_dialog(
+        &self,
+        sender: ComponentSender<StyleToolbar>,
+        root: Option<Window>,
+        current_color: Color,
+        finished: fn(Option<Color>) -> StyleToolbarInput,
+    ) {
+        let current_color: RGBA = current_color.into();
         relm4::spawn_local(async move {
             let mut builder = ColorChooserDialog::builder()
                 .modal(true)
@@ -407,7 +515,7 @@ impl StyleToolbar {
                 if r == ResponseType::Ok {
                     dialog_copy.hide();
                     let color = Color::from_gdk(dialog_copy.rgba());
-                    sender.input(StyleToolbarInput::ColorDialogFinished(Some(color)));
+                    sender.input(finished(Some(color)));
                 } else if r == ResponseType::Cancel || r == ResponseType::Close {
                     dialog_copy.hide();
                 }
@@ -493,30 +601,103 @@ impl Component for StyleToolbar {
 
                 connect_clicked => StyleToolbarInput::ShowColorDialog,
             },
+            gtk::MenuButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_tooltip: "Fill color, right click a palette color to fill with it",
+                #[wrap(Some)]
+                set_child = &gtk::Box {
+                    set_spacing: 2,
+
+                    gtk::Image {
+                        set_icon_name: Some("color-fill-regular"),
+                    },
+                    gtk::Image::from_pixbuf(Some(&model.fill_color_pixbuf)) {
+                        #[watch]
+                        set_visible: model.fill_color.is_some(),
+                        #[watch]
+                        set_from_pixbuf: Some(&model.fill_color_pixbuf),
+                    },
+                },
+                #[wrap(Some)]
+                set_popover = &gtk::Popover {
+                    gtk::Box {
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 4,
+
+                        gtk::Button {
+                            set_label: "Same as stroke",
+                            connect_clicked => StyleToolbarInput::FillColorSelected(None),
+                        },
+                        gtk::Button {
+                            set_label: "Pick fill color",
+                            connect_clicked => StyleToolbarInput::ShowFillColorDialog,
+                        },
+                        gtk::Label {
+                            set_label: "Fill opacity",
+                            set_halign: Align::Start,
+                        },
+                        gtk::Scale {
+                            set_width_request: 150,
+
+                            set_adjustment: &gtk::Adjustment::new(1.0, 0.0, 1.0, 0.05, 0.1, 0.0),
+                            set_digits: 2,
+                            set_draw_value: true,
+                            set_value_pos: gtk::PositionType::Right,
+                            #[watch]
+                            #[block_signal(fill_opacity_changed)]
+                            set_value: model.fill_opacity.into(),
+
+                            connect_value_changed[sender] => move |scale| {
+                                sender.input(StyleToolbarInput::FillOpacityChanged(scale.value() as f32));
+                            } @fill_opacity_changed,
+                        },
+                    },
+                },
+            },
             gtk::Separator {},
-            gtk::ToggleButton {
+            gtk::Scale {
+                set_focusable: false,
+                set_hexpand: false,
+                set_width_request: 120,
+
+                set_tooltip: "Stroke width, change it while drawing with the mouse wheel or [ and ]",
+                set_adjustment: &size_adjustment(),
+                set_digits: 1,
+                set_draw_value: true,
+                set_value_pos: gtk::PositionType::Right,
+                #[watch]
+                #[block_signal(size_changed)]
+                set_value: model.size.width().into(),
+
+                connect_value_changed[sender] => move |scale| {
+                    sender.input(StyleToolbarInput::SwitchSize(Size::new(scale.value() as f32)));
+                } @size_changed,
+            },
+            gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_label: "S",
                 set_tooltip: "Small size",
-                ActionablePlus::set_action::<SizeAction>: Size::Small,
+                connect_clicked => StyleToolbarInput::SwitchSize(SizePreset::Small.into()),
             },
-            gtk::ToggleButton {
+            gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_label: "M",
                 set_tooltip: "Medium size",
-                ActionablePlus::set_action::<SizeAction>: Size::Medium,
+                connect_clicked => StyleToolbarInput::SwitchSize(SizePreset::Medium.into()),
             },
-            gtk::ToggleButton {
+            gtk::Button {
                 set_focusable: false,
                 set_hexpand: false,
 
                 set_label: "L",
                 set_tooltip: "Large size",
-                ActionablePlus::set_action::<SizeAction>: Size::Large,
+                connect_clicked => StyleToolbarInput::SwitchSize(SizePreset::Large.into()),
             },
             gtk::Label {
                 set_focusable: false,
@@ -539,21 +720,65 @@ impl Component for StyleToolbar {
                 set_focusable: false,
                 set_hexpand: false,
 
-                set_icon_name: if APP_CONFIG.read().default_fill_shapes() {
+                #[watch]
+                set_icon_name: if model.fill {
                     "paint-bucket-filled"
                 } else {
                     "paint-bucket-regular"
                 },
                 set_tooltip: "Fill shape",
-                connect_clicked[sender] => move |button| {
-                    sender.output_sender().emit(ToolbarEvent::ToggleFill);
-                    let new_icon = if button.icon_name() == Some("paint-bucket-regular".into()) {
-                        "paint-bucket-filled"
-                    } else {
-                        "paint-bucket-regular"
-                    };
-                    button.set_icon_name(new_icon);
+                connect_clicked => StyleToolbarInput::ToggleFill,
+            },
+            gtk::Button {
+                set_focusable: false,
+                set_hexpand: false,
+
+                #[watch]
+                set_icon_name: if model.shadow {
+                    "square-multiple-filled"
+                } else {
+                    "square-multiple-regular"
+                },
+                set_tooltip: "Drop shadow",
+                connect_clicked => StyleToolbarInput::ToggleShadow,
+            },
+            gtk::Button {
+                set_focusable: false,
+                set_hexpand: false,
+
+                #[watch]
+                set_icon_name: if model.outline {
+                    "circle-multiple-concentric-filled"
+                } else {
+                    "circle-multiple-concentric-regular"
                 },
+                set_tooltip: "Contrasting outline",
+                connect_clicked => StyleToolbarInput::ToggleOutline,
+            },
+            gtk::Separator {},
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "line-horizontal-1-regular",
+                set_tooltip: "Solid line",
+                ActionablePlus::set_action::<LineDashAction>: LineDash::Solid,
+            },
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "line-horizontal-1-dashes-regular",
+                set_tooltip: "Dashed line",
+                ActionablePlus::set_action::<LineDashAction>: LineDash::Dashed,
+            },
+            gtk::ToggleButton {
+                set_focusable: false,
+                set_hexpand: false,
+
+                set_icon_name: "more-horizontal-regular",
+                set_tooltip: "Dotted line",
+                ActionablePlus::set_action::<LineDashAction>: LineDash::Dotted,
             },
         },
     }
@@ -561,7 +786,35 @@ impl Component for StyleToolbar {
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>, root: &Self::Root) {
         match message {
             StyleToolbarInput::ShowColorDialog => {
-                self.show_color_dialog(sender, root.toplevel_window());
+                self.show_color_dialog(
+                    sender,
+                    root.toplevel_window(),
+                    self.custom_color,
+                    StyleToolbarInput::ColorDialogFinished,
+                );
+            }
+            StyleToolbarInput::ShowFillColorDialog => {
+                self.show_color_dialog(
+                    sender,
+                    root.toplevel_window(),
+                    self.fill_color.unwrap_or(self.custom_color),
+                    StyleToolbarInput::FillColorSelected,
+                );
+            }
+            StyleToolbarInput::FillColorSelected(color) => {
+                self.fill_color = color;
+                if let Some(color) = color {
+                    self.fill_color_pixbuf = create_icon_pixbuf(color);
+                }
+                sender
+                    .output_sender()
+                    .emit(ToolbarEvent::FillColorSelected(color));
+            }
+            StyleToolbarInput::FillOpacityChanged(opacity) => {
+                self.fill_opacity = opacity;
+                sender
+                    .output_sender()
+                    .emit(ToolbarEvent::FillOpacityChanged(opacity));
             }
             StyleToolbarInput::ColorDialogFinished(color) => {
                 if let Some(color) = color {
@@ -585,6 +838,31 @@ impl Component for StyleToolbar {
                     .emit(ToolbarEvent::ColorSelected(color));
             }
 
+            // Change state of actions, let GTK update the UI
+            StyleToolbarInput::SwitchColor(button) => {
+                self.color_action.change_state(&button.to_variant());
+            }
+            StyleToolbarInput::SwitchSize(size) => {
+                if size != self.size {
+                    self.size = size;
+                    sender
+                        .output_sender()
+                        .emit(ToolbarEvent::SizeSelected(size));
+                }
+            }
+            StyleToolbarInput::ToggleFill => {
+                self.fill = !self.fill;
+                sender.output_sender().emit(ToolbarEvent::ToggleFill);
+            }
+            StyleToolbarInput::ToggleShadow => {
+                self.shadow = !self.shadow;
+                sender.output_sender().emit(ToolbarEvent::ToggleShadow);
+            }
+            StyleToolbarInput::ToggleOutline => {
+                self.outline = !self.outline;
+                sender.output_sender().emit(ToolbarEvent::ToggleOutline);
+            }
+
             StyleToolbarInput::ShowAnnotationDialog => {
                 self.show_annotation_dialog(sender, root.toplevel_window());
             }
@@ -626,6 +904,17 @@ impl Component for StyleToolbar {
                 .child(&create_icon(color))
                 .build();
             btn.set_action::<ColorAction>(ColorButtons::Palette(i as u64));
+
+            // right click fills with the color
+            let right_click = gtk::GestureClick::builder()
+                .button(gtk::gdk::BUTTON_SECONDARY)
+                .build();
+            let sender_tmp = sender.clone();
+            right_click.connect_pressed(move |_, _, _, _| {
+                sender_tmp.input(StyleToolbarInput::FillColorSelected(Some(color)));
+            });
+            btn.add_controller(right_click);
+
             root.prepend(&btn);
         }
 
@@ -640,15 +929,18 @@ impl Component for StyleToolbar {
             },
         );
 
-        // Size Action for selecting sizes
+        // Line Dash Action for selecting line styles
         let sender_tmp = sender.clone();
-        let size_action: RelmAction<SizeAction> =
-            RelmAction::new_stateful_with_target_value(&Size::Medium, move |_, state, value| {
-                *state = value;
-                sender_tmp
-                    .output_sender()
-                    .emit(ToolbarEvent::SizeSelected(*state));
-            });
+        let line_dash_action: RelmAction<LineDashAction> =
+            RelmAction::new_stateful_with_target_value(
+                &APP_CONFIG.read().default_line_dash(),
+                move |_, state, value| {
+                    *state = value;
+                    sender_tmp
+                        .output_sender()
+                        .emit(ToolbarEvent::LineDashSelected(*state));
+                },
+            );
 
         let custom_color = APP_CONFIG
             .read()
@@ -664,6 +956,13 @@ impl Component for StyleToolbar {
             custom_color,
             custom_color_pixbuf,
             color_action: SimpleAction::from(color_action.clone()),
+            size: Size::default(),
+            fill: APP_CONFIG.read().default_fill_shapes(),
+            fill_color: None,
+            fill_color_pixbuf: create_icon_pixbuf(custom_color),
+            fill_opacity: 1.0,
+            shadow: APP_CONFIG.read().default_shadow(),
+            outline: APP_CONFIG.read().default_outline(),
             visible: !APP_CONFIG.read().default_hide_toolbars(),
             annotation_size: APP_CONFIG.read().annotation_size_factor(),
             annotation_size_formatted: format!(
@@ -678,7 +977,7 @@ impl Component for StyleToolbar {
 
         let mut group = RelmActionGroup::<StyleToolbarActionGroup>::new();
         group.add_action(color_action);
-        group.add_action(size_action);
+        group.add_action(line_dash_action);
 
         group.register_for_widget(&widgets.root);
 
@@ -703,7 +1002,13 @@ impl Clone for ColorAction {
     }
 }
 
-relm4::new_stateful_action!(SizeAction, StyleToolbarActionGroup, "sizes", Size, Size);
+relm4::new_stateful_action!(
+    LineDashAction,
+    StyleToolbarActionGroup,
+    "line-dashes",
+    LineDash,
+    LineDash
+);
 
 impl StaticVariantType for ColorButtons {
     fn static_variant_type() -> Cow<'static, VariantTy> {